@@ -8,6 +8,12 @@ use rand_core::OsRng;
 const N: u32 = 20;
 const T: u32 = 13;
 const K: u32 = 4;
+// run with and without `--features parallel` to compare the two modes
+const MODE: &str = if cfg!(feature = "parallel") {
+    "parallel"
+} else {
+    "sequential"
+};
 
 #[allow(non_snake_case)]
 pub fn bench_dkg(c: &mut Criterion) {
@@ -28,7 +34,7 @@ pub fn bench_dkg(c: &mut Criterion) {
         })
         .collect();
 
-    let s = format!("v2 dkg N={} T={} K={}", N, T, K);
+    let s = format!("v2 dkg N={} T={} K={} {}", N, T, K, MODE);
     c.bench_function(&s, |b| b.iter(|| dkg(&mut signers, &mut rng)));
 }
 
@@ -61,7 +67,7 @@ pub fn bench_party_sign(c: &mut Criterion) {
 
     let mut signers = signers[..(K * 3 / 4).try_into().unwrap()].to_vec();
 
-    let s = format!("v2 party sign N={} T={} K={}", N, T, K);
+    let s = format!("v2 party sign N={} T={} K={} {}", N, T, K, MODE);
     c.bench_function(&s, |b| b.iter(|| sign(&msg, &mut signers, &mut rng)));
 }
 
@@ -93,14 +99,13 @@ pub fn bench_aggregator_sign(c: &mut Criterion) {
     };
 
     let mut signers = signers[..(K * 3 / 4).try_into().unwrap()].to_vec();
-    let mut aggregator =
-        v2::SignatureAggregator::new(N, T, A.clone()).expect("aggregator ctor failed");
+    let mut aggregator = v2::SignatureAggregator::new(N, T, &A).expect("aggregator ctor failed");
 
-    let (nonces, sig_shares, key_ids) = sign(&msg, &mut signers, &mut rng);
+    let (session_id, nonces, sig_shares, key_ids) = sign(&msg, &mut signers, &mut rng);
 
-    let s = format!("v2 group sign N={} T={} K={}", N, T, K);
+    let s = format!("v2 group sign N={} T={} K={} {}", N, T, K, MODE);
     c.bench_function(&s, |b| {
-        b.iter(|| aggregator.sign(&msg, &nonces, &sig_shares, &key_ids))
+        b.iter(|| aggregator.sign(session_id, &msg, &nonces, &sig_shares, &key_ids))
     });
 }
 
@@ -1,8 +1,10 @@
+use num_traits::{One, Zero};
 use p256k1::{
     field,
     point::{Error as PointError, Point, G},
     scalar::Scalar,
 };
+use rand_core::{CryptoRng, RngCore};
 
 use crate::{common::Signature, compute};
 
@@ -55,6 +57,63 @@ impl SchnorrProof {
         Rp.x() == self.r
     }
 
+    /// Verify a batch of (public key, message, proof) triples with a random linear combination of the verification equations, returning the indices of any proofs which fail to verify
+    #[allow(non_snake_case)]
+    pub fn verify_batch<RNG: RngCore + CryptoRng>(
+        proofs: &[(&field::Element, &[u8], &SchnorrProof)],
+        rng: &mut RNG,
+    ) -> Result<(), Vec<usize>> {
+        let mut s = Scalar::zero();
+        let mut scalars = Vec::with_capacity(2 * proofs.len() + 1);
+        let mut points = Vec::with_capacity(2 * proofs.len() + 1);
+        let mut lifted = true;
+
+        for (i, (public_key, msg, proof)) in proofs.iter().enumerate() {
+            let (Y, R) = match (Point::lift_x(public_key), Point::lift_x(&proof.r)) {
+                (Ok(Y), Ok(R)) => (Y, R),
+                _ => {
+                    lifted = false;
+                    break;
+                }
+            };
+            let c = compute::challenge(&Y, &R, msg);
+            // the first weight can be one without weakening the check
+            let a = if i == 0 {
+                Scalar::one()
+            } else {
+                Scalar::random(rng)
+            };
+
+            s += a * proof.s;
+            scalars.push(-(a * c));
+            points.push(Y);
+            scalars.push(-a);
+            points.push(R);
+        }
+        scalars.push(s);
+        points.push(G);
+
+        if lifted {
+            if let Ok(sum) = Point::multimult(scalars, points) {
+                if sum.is_zero() {
+                    return Ok(());
+                }
+            }
+        }
+
+        let bad: Vec<usize> = proofs
+            .iter()
+            .enumerate()
+            .filter(|(_, (public_key, msg, proof))| !proof.verify(public_key, msg))
+            .map(|(i, _)| i)
+            .collect();
+        if bad.is_empty() {
+            Ok(())
+        } else {
+            Err(bad)
+        }
+    }
+
     /// Serialize this proof into a 64-byte buffer
     pub fn to_bytes(&self) -> [u8; 64] {
         let mut bytes = [0u8; 64];
@@ -84,9 +143,9 @@ impl From<[u8; 64]> for SchnorrProof {
 /// Helper functions for tests
 pub mod test_helpers {
     use crate::{
-        common::{PolyCommitment, PublicNonce, SignatureShare},
+        common::{PolyCommitment, PublicNonce, SessionId, SignatureShare},
         errors::DkgError,
-        traits, Point,
+        traits,
     };
 
     use hashbrown::HashMap;
@@ -97,29 +156,13 @@ pub mod test_helpers {
     pub fn dkg<RNG: RngCore + CryptoRng, Signer: traits::Signer>(
         signers: &mut [Signer],
         rng: &mut RNG,
-    ) -> Result<Vec<PolyCommitment>, HashMap<u32, DkgError>> {
-        let mut A: Vec<PolyCommitment> = signers
+    ) -> Result<HashMap<u32, PolyCommitment>, HashMap<u32, DkgError>> {
+        // the group key may have either parity, since signing handles an odd one
+        let A: HashMap<u32, PolyCommitment> = signers
             .iter()
             .flat_map(|s| s.get_poly_commitments(rng))
             .collect();
 
-        // keep trying until the group key has even y coord
-        loop {
-            let group_key = A.iter().fold(Point::new(), |s, a| s + a.A[0]);
-            if group_key.has_even_y() {
-                break;
-            }
-
-            for signer in signers.iter_mut() {
-                signer.reset_polys(rng);
-            }
-
-            A = signers
-                .iter()
-                .flat_map(|s| s.get_poly_commitments(rng))
-                .collect();
-        }
-
         let mut private_shares = HashMap::new();
         for signer in signers.iter() {
             for (signer_id, signer_shares) in signer.get_shares() {
@@ -141,32 +184,61 @@ pub mod test_helpers {
         }
     }
 
-    /// Run a signing round for the passed `msg`
+    /// Run a signing round for the passed `msg`, returning the session ID which the aggregator must check the signature shares against
     #[allow(non_snake_case)]
     pub fn sign<RNG: RngCore + CryptoRng, Signer: traits::Signer>(
         msg: &[u8],
         signers: &mut [Signer],
         rng: &mut RNG,
-    ) -> (Vec<PublicNonce>, Vec<SignatureShare>) {
+    ) -> (SessionId, Vec<PublicNonce>, Vec<SignatureShare>) {
         let signer_ids: Vec<u32> = signers.iter().map(|s| s.get_id()).collect();
         let key_ids: Vec<u32> = signers.iter().flat_map(|s| s.get_key_ids()).collect();
-        let mut nonces: Vec<PublicNonce> =
-            signers.iter_mut().flat_map(|s| s.gen_nonces(rng)).collect();
+        // every signer shares the session ID, which is bound into the binding factor under ProtocolVersion::V1
+        let session_id = rng.next_u64();
+        // R may have either parity, since signing handles an odd one
+        let nonces: Vec<PublicNonce> = signers
+            .iter_mut()
+            .flat_map(|s| s.gen_session_nonces(session_id, rng))
+            .collect();
 
-        loop {
-            let (_, R) = Signer::compute_intermediate(msg, &signer_ids, &key_ids, &nonces);
-            if R.has_even_y() {
-                break;
-            }
-            nonces = signers.iter_mut().flat_map(|s| s.gen_nonces(rng)).collect();
-        }
+        let shares = signers
+            .iter_mut()
+            .flat_map(|s| {
+                s.sign(session_id, msg, &signer_ids, &key_ids, &nonces)
+                    .expect("signing failed")
+            })
+            .collect();
+
+        (session_id, nonces, shares)
+    }
+
+    /// Run a signing round for the passed `msg`, for the BIP-341 output key of the group key and an optional script `merkle_root`, returning the session ID which the aggregator must check the signature shares against
+    #[allow(non_snake_case)]
+    pub fn sign_taproot<RNG: RngCore + CryptoRng, Signer: traits::Signer>(
+        msg: &[u8],
+        signers: &mut [Signer],
+        merkle_root: Option<[u8; 32]>,
+        rng: &mut RNG,
+    ) -> (SessionId, Vec<PublicNonce>, Vec<SignatureShare>) {
+        let signer_ids: Vec<u32> = signers.iter().map(|s| s.get_id()).collect();
+        let key_ids: Vec<u32> = signers.iter().flat_map(|s| s.get_key_ids()).collect();
+        // every signer shares the session ID, which is bound into the binding factor under ProtocolVersion::V1
+        let session_id = rng.next_u64();
+        // R may have either parity, since signing handles an odd one
+        let nonces: Vec<PublicNonce> = signers
+            .iter_mut()
+            .flat_map(|s| s.gen_session_nonces(session_id, rng))
+            .collect();
 
         let shares = signers
-            .iter()
-            .flat_map(|s| s.sign(msg, &signer_ids, &key_ids, &nonces))
+            .iter_mut()
+            .flat_map(|s| {
+                s.sign_taproot(session_id, msg, &signer_ids, &key_ids, &nonces, merkle_root)
+                    .expect("signing failed")
+            })
             .collect();
 
-        (nonces, shares)
+        (session_id, nonces, shares)
     }
 }
 
@@ -174,44 +246,42 @@ pub mod test_helpers {
 mod test {
     use super::{test_helpers, SchnorrProof};
 
-    use crate::{traits::Signer, v1, v2};
-    use rand_core::OsRng;
+    use crate::{common, common::ProtocolVersion, compute, traits::Signer, v1, v2, Scalar};
+    use rand_core::{OsRng, RngCore};
 
-    #[test]
-    #[allow(non_snake_case)]
-    fn test_schnorr_sign_verify_v1() {
-        let mut rng = OsRng::default();
+    /// The number of keys in the group most tests use
+    const NUM_KEYS: u32 = 10;
 
-        // First create and verify a frost signature
-        let msg = "It was many and many a year ago".as_bytes();
-        let N: u32 = 10;
-        let T: u32 = 7;
-        let signer_ids: Vec<Vec<u32>> = [
+    /// The threshold of the group most tests use
+    const THRESHOLD: u32 = 7;
+
+    /// The key IDs of each signer in the group most tests use, where signers [0,1,3] hold exactly `THRESHOLD` keys
+    fn signer_key_ids() -> Vec<Vec<u32>> {
+        [
             [0, 1, 2].to_vec(),
             [3, 4].to_vec(),
             [5, 6, 7].to_vec(),
             [8, 9].to_vec(),
         ]
-        .to_vec();
-        let mut signers: Vec<v1::Signer> = signer_ids
-            .iter()
-            .enumerate()
-            .map(|(id, ids)| v1::Signer::new(id.try_into().unwrap(), ids, N, T, &mut rng))
-            .collect();
+        .to_vec()
+    }
 
-        let A = match test_helpers::dkg(&mut signers, &mut rng) {
-            Ok(A) => A,
-            Err(secret_errors) => {
-                panic!("Got secret errors from DKG: {:?}", secret_errors);
-            }
-        };
+    #[test]
+    #[allow(non_snake_case)]
+    fn test_schnorr_sign_verify_v1() {
+        let mut rng = OsRng::default();
+
+        // First create and verify a frost signature
+        let msg = "It was many and many a year ago".as_bytes();
+        let mut signers = v1::test_helpers::new_signers(&signer_key_ids(), THRESHOLD, &mut rng);
+        let A = test_helpers::dkg(&mut signers, &mut rng).expect("DKG failed");
 
         let mut S = [signers[0].clone(), signers[1].clone(), signers[3].clone()].to_vec();
         let mut sig_agg =
-            v1::SignatureAggregator::new(N, T, A.clone()).expect("aggregator ctor failed");
+            v1::SignatureAggregator::new(NUM_KEYS, THRESHOLD, &A).expect("aggregator ctor failed");
 
-        let (nonces, sig_shares) = test_helpers::sign(&msg, &mut S, &mut rng);
-        let sig = match sig_agg.sign(&msg, &nonces, &sig_shares) {
+        let (session_id, nonces, sig_shares) = test_helpers::sign(&msg, &mut S, &mut rng);
+        let sig = match sig_agg.sign(session_id, &msg, &nonces, &sig_shares) {
             Err(e) => panic!("Aggregator sign failed: {:?}", e),
             Ok(sig) => sig,
         };
@@ -236,36 +306,16 @@ mod test {
 
         // First create and verify a frost signature
         let msg = "It was many and many a year ago".as_bytes();
-        let Nk: u32 = 10;
-        let Np: u32 = 4;
-        let T: u32 = 7;
-        let signer_ids: Vec<Vec<u32>> = [
-            [0, 1, 2].to_vec(),
-            [3, 4].to_vec(),
-            [5, 6, 7].to_vec(),
-            [8, 9].to_vec(),
-        ]
-        .to_vec();
-        let mut signers: Vec<v2::Signer> = signer_ids
-            .iter()
-            .enumerate()
-            .map(|(id, ids)| v2::Signer::new(id.try_into().unwrap(), ids, Np, Nk, T, &mut rng))
-            .collect();
-
-        let A = match test_helpers::dkg(&mut signers, &mut rng) {
-            Ok(A) => A,
-            Err(secret_errors) => {
-                panic!("Got secret errors from DKG: {:?}", secret_errors);
-            }
-        };
+        let mut signers = v2::test_helpers::new_parties(&signer_key_ids(), THRESHOLD, &mut rng);
+        let A = test_helpers::dkg(&mut signers, &mut rng).expect("DKG failed");
 
         let mut S = [signers[0].clone(), signers[1].clone(), signers[3].clone()].to_vec();
         let key_ids = S.iter().flat_map(|s| s.get_key_ids()).collect::<Vec<u32>>();
         let mut sig_agg =
-            v2::SignatureAggregator::new(Nk, T, A.clone()).expect("aggregator ctor failed");
+            v2::SignatureAggregator::new(NUM_KEYS, THRESHOLD, &A).expect("aggregator ctor failed");
 
-        let (nonces, sig_shares) = test_helpers::sign(&msg, &mut S, &mut rng);
-        let sig = match sig_agg.sign(&msg, &nonces, &sig_shares, &key_ids) {
+        let (session_id, nonces, sig_shares) = test_helpers::sign(&msg, &mut S, &mut rng);
+        let sig = match sig_agg.sign(session_id, &msg, &nonces, &sig_shares, &key_ids) {
             Err(e) => panic!("Aggregator sign failed: {:?}", e),
             Ok(sig) => sig,
         };
@@ -282,4 +332,228 @@ mod test {
         assert_eq!(proof, proof_deser);
         assert!(proof_deser.verify(&sig_agg.poly[0].x(), msg));
     }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn test_taproot_sign_verify_v1() {
+        let mut rng = OsRng::default();
+        let msg = "It was many and many a year ago".as_bytes();
+        // the group key may have either parity
+        let (signers, A) = v1::test_helpers::setup_dkg(&signer_key_ids(), THRESHOLD, &mut rng);
+
+        let mut S = [signers[0].clone(), signers[1].clone(), signers[3].clone()].to_vec();
+        let mut sig_agg =
+            v1::SignatureAggregator::new(NUM_KEYS, THRESHOLD, &A).expect("aggregator ctor failed");
+        let output_key = compute::tweaked_public_key(&sig_agg.poly[0], None);
+
+        let (session_id, nonces, sig_shares) =
+            test_helpers::sign_taproot(msg, &mut S, None, &mut rng);
+        let sig = sig_agg
+            .sign_taproot(session_id, msg, &nonces, &sig_shares, None)
+            .expect("aggregator sign failed");
+        let proof = SchnorrProof::new(&sig).unwrap();
+
+        assert!(proof.verify(&output_key.x(), msg));
+        assert!(!proof.verify(&sig_agg.poly[0].x(), msg));
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn test_taproot_sign_verify_v2() {
+        let mut rng = OsRng::default();
+        let msg = "It was many and many a year ago".as_bytes();
+        let merkle_root = Some([7u8; 32]);
+        // the group key may have either parity
+        let (signers, A) = v2::test_helpers::setup_dkg(&signer_key_ids(), THRESHOLD, &mut rng);
+
+        let mut S = [signers[0].clone(), signers[1].clone(), signers[3].clone()].to_vec();
+        let key_ids = S.iter().flat_map(|s| s.get_key_ids()).collect::<Vec<u32>>();
+        let mut sig_agg =
+            v2::SignatureAggregator::new(NUM_KEYS, THRESHOLD, &A).expect("aggregator ctor failed");
+        let output_key = compute::tweaked_public_key(&sig_agg.poly[0], merkle_root);
+
+        let (session_id, nonces, sig_shares) =
+            test_helpers::sign_taproot(msg, &mut S, merkle_root, &mut rng);
+        let sig = sig_agg
+            .sign_taproot(session_id, msg, &nonces, &sig_shares, &key_ids, merkle_root)
+            .expect("aggregator sign failed");
+        let proof = SchnorrProof::new(&sig).unwrap();
+
+        assert!(proof.verify(&output_key.x(), msg));
+        assert!(!proof.verify(
+            &compute::tweaked_public_key(&sig_agg.poly[0], None).x(),
+            msg
+        ));
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn test_sign_verify_versioned_binding() {
+        let mut rng = OsRng::default();
+        let msg = "It was many and many a year ago".as_bytes();
+
+        // every signer and the aggregator bind the one session ID which the helpers pick
+        let (signers, A) = v1::test_helpers::setup_dkg(&signer_key_ids(), THRESHOLD, &mut rng);
+        let mut S = [signers[0].clone(), signers[1].clone(), signers[3].clone()].to_vec();
+        for signer in S.iter_mut() {
+            signer.set_protocol_version(ProtocolVersion::V1);
+        }
+        let mut sig_agg =
+            v1::SignatureAggregator::new(NUM_KEYS, THRESHOLD, &A).expect("aggregator ctor failed");
+        sig_agg.set_protocol_version(ProtocolVersion::V1);
+
+        let (session_id, nonces, sig_shares) = test_helpers::sign(msg, &mut S, &mut rng);
+        let sig = sig_agg
+            .sign(session_id, msg, &nonces, &sig_shares)
+            .expect("aggregator sign failed");
+        assert!(SchnorrProof::new(&sig)
+            .unwrap()
+            .verify(&sig_agg.poly[0].x(), msg));
+        assert!(sig_agg
+            .sign(session_id.wrapping_add(1), msg, &nonces, &sig_shares)
+            .is_err());
+
+        let (signers, A) = v2::test_helpers::setup_dkg(&signer_key_ids(), THRESHOLD, &mut rng);
+        let mut S = [signers[0].clone(), signers[1].clone(), signers[3].clone()].to_vec();
+        for signer in S.iter_mut() {
+            signer.set_protocol_version(ProtocolVersion::V1);
+        }
+        let key_ids = S.iter().flat_map(|s| s.get_key_ids()).collect::<Vec<u32>>();
+        let mut sig_agg =
+            v2::SignatureAggregator::new(NUM_KEYS, THRESHOLD, &A).expect("aggregator ctor failed");
+        sig_agg.set_protocol_version(ProtocolVersion::V1);
+        let output_key = compute::tweaked_public_key(&sig_agg.poly[0], None);
+
+        let (session_id, nonces, sig_shares) =
+            test_helpers::sign_taproot(msg, &mut S, None, &mut rng);
+        let sig = sig_agg
+            .sign_taproot(session_id, msg, &nonces, &sig_shares, &key_ids, None)
+            .expect("aggregator sign failed");
+        assert!(SchnorrProof::new(&sig)
+            .unwrap()
+            .verify(&output_key.x(), msg));
+        assert!(sig_agg
+            .sign_taproot(
+                session_id.wrapping_add(1),
+                msg,
+                &nonces,
+                &sig_shares,
+                &key_ids,
+                None
+            )
+            .is_err());
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn test_schnorr_sign_verify_odd_group_key() {
+        let mut rng = OsRng::default();
+        let msg = "It was many and many a year ago".as_bytes();
+        let Nk: u32 = 4;
+        let T: u32 = 3;
+        let signer_ids: Vec<Vec<u32>> = [[0, 1].to_vec(), [2, 3].to_vec()].to_vec();
+
+        // run DKG until it happens to produce an odd group key
+        let (mut signers, A) = loop {
+            let mut signers = v2::test_helpers::new_parties(&signer_ids, T, &mut rng);
+            let A = test_helpers::dkg(&mut signers, &mut rng).expect("DKG failed");
+            let sig_agg = v2::SignatureAggregator::new(Nk, T, &A).unwrap();
+            if !sig_agg.poly[0].has_even_y() {
+                break (signers, A);
+            }
+        };
+
+        let key_ids = signers
+            .iter()
+            .flat_map(|s| s.get_key_ids())
+            .collect::<Vec<u32>>();
+        let mut sig_agg = v2::SignatureAggregator::new(Nk, T, &A).expect("aggregator ctor failed");
+
+        let (session_id, nonces, sig_shares) = test_helpers::sign(msg, &mut signers, &mut rng);
+        let sig = sig_agg
+            .sign(session_id, msg, &nonces, &sig_shares, &key_ids)
+            .expect("aggregator sign failed");
+        let proof = SchnorrProof::new(&sig).unwrap();
+
+        // the group signed for the even-y key, so only the x-only verifier accepts the odd group key
+        assert!(sig.verify_xonly(&sig_agg.poly[0], msg));
+        assert!(!sig.verify(&sig_agg.poly[0], msg));
+        assert!(sig.verify(&-sig_agg.poly[0], msg));
+        assert!(proof.verify(&sig_agg.poly[0].x(), msg));
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn test_schnorr_sign_verify_odd_R() {
+        let mut rng = OsRng::default();
+        let msg = "It was many and many a year ago".as_bytes();
+        let N: u32 = 4;
+        let T: u32 = 3;
+        let signer_ids: Vec<Vec<u32>> = [[0, 1].to_vec(), [2, 3].to_vec()].to_vec();
+        let mut signers = v1::test_helpers::new_signers(&signer_ids, T, &mut rng);
+        let A = test_helpers::dkg(&mut signers, &mut rng).expect("DKG failed");
+        let mut sig_agg = v1::SignatureAggregator::new(N, T, &A).expect("aggregator ctor failed");
+
+        // generate nonces until they happen to aggregate to an odd R
+        let key_ids: Vec<u32> = signers.iter().flat_map(|s| s.get_key_ids()).collect();
+        let (session_id, nonces) = loop {
+            let session_id = rng.next_u64();
+            let nonces: Vec<_> = signers
+                .iter_mut()
+                .flat_map(|s| s.gen_session_nonces(session_id, &mut rng))
+                .collect();
+            let (_, R) = v1::Signer::compute_intermediate(msg, &[], &key_ids, &nonces);
+            if !R.has_even_y() {
+                break (session_id, nonces);
+            }
+        };
+
+        let sig_shares: Vec<_> = signers
+            .iter_mut()
+            .flat_map(|s| {
+                s.sign(session_id, msg, &[], &key_ids, &nonces)
+                    .expect("signing failed")
+            })
+            .collect();
+        let sig = sig_agg
+            .sign(session_id, msg, &nonces, &sig_shares)
+            .expect("aggregator sign failed");
+        let proof = SchnorrProof::new(&sig).unwrap();
+
+        assert!(sig.verify_xonly(&sig_agg.poly[0], msg));
+        assert!(proof.verify(&sig_agg.poly[0].x(), msg));
+    }
+
+    #[test]
+    fn test_schnorr_verify_batch() {
+        let mut rng = OsRng::default();
+        let msgs: Vec<Vec<u8>> = (0..8u8).map(|i| vec![i; 32]).collect();
+        let mut proofs: Vec<_> = msgs
+            .iter()
+            .map(|msg| {
+                let (public_key, sig) = common::test_helpers::sign(msg, &mut rng);
+                (public_key.x(), SchnorrProof::new(&sig).unwrap())
+            })
+            .collect();
+
+        let batch: Vec<_> = proofs
+            .iter()
+            .zip(&msgs)
+            .map(|((public_key, proof), msg)| (public_key, msg.as_slice(), proof))
+            .collect();
+        assert!(SchnorrProof::verify_batch(&batch, &mut rng).is_ok());
+
+        // corrupt two proofs, which the fallback identifies
+        proofs[1].1.s = Scalar::random(&mut rng);
+        proofs[7].0 = proofs[0].0;
+        let batch: Vec<_> = proofs
+            .iter()
+            .zip(&msgs)
+            .map(|((public_key, proof), msg)| (public_key, msg.as_slice(), proof))
+            .collect();
+        assert_eq!(
+            SchnorrProof::verify_batch(&batch, &mut rng),
+            Err(vec![1, 7])
+        );
+    }
 }
@@ -1,17 +1,21 @@
 use core::{
     fmt::{Debug, Display, Formatter, Result as FmtResult},
-    ops::Add,
+    ops::{Add, Deref, DerefMut},
+    time::Duration,
 };
-use num_traits::Zero;
+use hashbrown::{HashMap, HashSet};
+use num_traits::{One, Zero};
 use p256k1::{
     point::{Point, G},
     scalar::Scalar,
 };
 use rand_core::{CryptoRng, RngCore};
 use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, time::Instant};
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
-use crate::compute::challenge;
-use crate::schnorr::ID;
+use crate::compute::{self, challenge};
+use crate::schnorr::{DkgContext, ID};
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[allow(non_snake_case)]
@@ -24,9 +28,22 @@ pub struct PolyCommitment {
 }
 
 impl PolyCommitment {
-    /// Verify the wrapped schnorr ID
-    pub fn verify(&self) -> bool {
-        self.id.verify(&self.A[0])
+    /// Verify the committed polynomial has one coefficient for each of the `ctx` threshold, and that the wrapped schnorr ID was made in the DKG round `ctx`
+    pub fn verify(&self, ctx: &DkgContext) -> bool {
+        self.A.len() == usize::try_from(ctx.threshold).unwrap() && self.id.verify(&self.A[0], ctx)
+    }
+
+    /// Verify the poly commitment as `verify` does, and that the committed polynomial has a zero constant term so it refreshes shares without changing the group key
+    pub fn verify_refresh(&self, ctx: &DkgContext) -> bool {
+        self.verify(ctx) && self.A[0].is_zero()
+    }
+
+    /// Verify that `share` is the committed polynomial evaluated at `key_id`
+    pub fn verify_share(&self, key_id: u32, share: &Scalar) -> bool {
+        match compute::poly(&compute::id(key_id), &self.A) {
+            Ok(p) => share * G == p,
+            Err(_) => false,
+        }
     }
 }
 
@@ -40,8 +57,125 @@ impl Display for PolyCommitment {
     }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
-/// A composite private nonce used as a random commitment in the protocol
+/// The text which secret types print in place of their values
+const REDACTED: &str = "<redacted>";
+
+#[derive(Clone, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(transparent)]
+/// A private scalar, which is zeroized on drop and redacted when printed
+pub struct SecretScalar(Scalar);
+
+impl SecretScalar {
+    /// Construct a zero secret
+    pub fn zero() -> Self {
+        Self(Scalar::zero())
+    }
+}
+
+impl From<Scalar> for SecretScalar {
+    fn from(s: Scalar) -> Self {
+        Self(s)
+    }
+}
+
+impl Deref for SecretScalar {
+    type Target = Scalar;
+
+    fn deref(&self) -> &Scalar {
+        &self.0
+    }
+}
+
+impl DerefMut for SecretScalar {
+    fn deref_mut(&mut self) -> &mut Scalar {
+        &mut self.0
+    }
+}
+
+impl Zeroize for SecretScalar {
+    fn zeroize(&mut self) {
+        self.0.scalar.d.zeroize();
+    }
+}
+
+impl Drop for SecretScalar {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for SecretScalar {}
+
+impl Debug for SecretScalar {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "SecretScalar({})", REDACTED)
+    }
+}
+
+impl Display for SecretScalar {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}", REDACTED)
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Deserialize, Serialize)]
+/// A private polynomial, whose coefficients are zeroized on drop and redacted when printed
+///
+/// This serializes the same way as `polynomial::Polynomial<Scalar>`
+pub struct SecretPoly {
+    data: Vec<Scalar>,
+}
+
+impl SecretPoly {
+    /// Construct a polynomial from its coefficients, lowest degree first
+    pub fn new(data: Vec<Scalar>) -> Self {
+        Self { data }
+    }
+
+    /// Get the coefficients, lowest degree first
+    pub fn data(&self) -> &[Scalar] {
+        &self.data
+    }
+
+    /// Evaluate the polynomial at `x`
+    pub fn eval(&self, x: Scalar) -> Scalar {
+        self.data
+            .iter()
+            .rev()
+            .fold(Scalar::zero(), |sum, a| sum * x + a)
+    }
+}
+
+impl Zeroize for SecretPoly {
+    fn zeroize(&mut self) {
+        for a in self.data.iter_mut() {
+            a.scalar.d.zeroize();
+        }
+    }
+}
+
+impl Drop for SecretPoly {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for SecretPoly {}
+
+impl Debug for SecretPoly {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "SecretPoly({})", REDACTED)
+    }
+}
+
+impl Display for SecretPoly {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}", REDACTED)
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Deserialize, Serialize)]
+/// A composite private nonce used as a random commitment in the protocol, which is zeroized on drop and redacted when printed
 pub struct Nonce {
     /// The first committed value
     pub d: Scalar,
@@ -50,13 +184,31 @@ pub struct Nonce {
 }
 
 impl Nonce {
-    /// Construct a random nonce
+    /// Construct a random nonce, which depends entirely on `rng`
     pub fn random<RNG: RngCore + CryptoRng>(rng: &mut RNG) -> Self {
         Self {
             d: Scalar::random(rng),
             e: Scalar::random(rng),
         }
     }
+
+    /// Construct a nonce hedged against a weak or repeated `rng`, by hashing fresh randomness with the signing party's `secrets` and the signing `context`
+    pub fn hedged<RNG: RngCore + CryptoRng>(
+        secrets: &[&Scalar],
+        context: &[u8],
+        rng: &mut RNG,
+    ) -> Self {
+        let mut randomness = [0u8; 32];
+        rng.fill_bytes(&mut randomness);
+
+        let nonce = Self {
+            d: compute::hedged_nonce(b"d", &randomness, secrets, context),
+            e: compute::hedged_nonce(b"e", &randomness, secrets, context),
+        };
+        randomness.zeroize();
+
+        nonce
+    }
 }
 
 impl Zero for Nonce {
@@ -72,6 +224,33 @@ impl Zero for Nonce {
     }
 }
 
+impl Zeroize for Nonce {
+    fn zeroize(&mut self) {
+        self.d.scalar.d.zeroize();
+        self.e.scalar.d.zeroize();
+    }
+}
+
+impl Drop for Nonce {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for Nonce {}
+
+impl Debug for Nonce {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "Nonce({})", REDACTED)
+    }
+}
+
+impl Display for Nonce {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}", REDACTED)
+    }
+}
+
 impl Add for Nonce {
     type Output = Self;
 
@@ -83,6 +262,190 @@ impl Add for Nonce {
     }
 }
 
+/// An identifier for a signing session, which scopes a party's pending nonce
+pub type SessionId = u64;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash, Deserialize, Serialize)]
+/// The version of the signing protocol used by a group, which selects what binding values commit to
+pub enum ProtocolVersion {
+    #[default]
+    /// Binding values commit to the party ID, the public nonces and the message
+    V0,
+    /// Binding values also commit to the group public key, the party and key IDs which own the public nonces, and the signing session ID, and DKG proofs of possession commit to the DKG context
+    V1,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash, Deserialize, Serialize)]
+/// How signers publish their polynomial commitments during DKG
+pub enum DkgMode {
+    #[default]
+    /// Signers publish their polynomial commitments in a single round
+    Plain,
+    /// Signers publish a digest of their polynomial commitments, and only reveal them once every signer has done so, so that no signer can choose its commitments after seeing the others and bias the group key
+    CommitReveal,
+}
+
+/// A set of key IDs indexed by party ID, such as a signing set or the qualified set of a DKG round
+pub type SelectedSigners = HashMap<u32, HashSet<u32>>;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
+/// How the IDs of the parties which deal shares during DKG are assigned to signers
+pub enum PartyIds {
+    /// Each key is its own party, so a signer's party IDs are the key IDs it owns, as in `v1`
+    KeyIds,
+    /// Each signer is a single party, so its party ID is its signer ID, as in `v2`
+    SignerIds,
+}
+
+/// The default maximum number of pending signing sessions
+pub const DEFAULT_MAX_SESSIONS: usize = 64;
+/// The default lifetime of a pending signing session
+pub const DEFAULT_SESSION_TTL: Duration = Duration::from_secs(600);
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+/// Pending private nonces indexed by signing session, bounded in number and lifetime
+pub struct NonceSessions {
+    nonces: HashMap<SessionId, (Nonce, Instant)>,
+    max_sessions: usize,
+    ttl: Duration,
+}
+
+impl NonceSessions {
+    /// Construct an empty set of sessions which holds at most `max_sessions`, each for at most `ttl`
+    pub fn new(max_sessions: usize, ttl: Duration) -> Self {
+        Self {
+            nonces: HashMap::new(),
+            max_sessions,
+            ttl,
+        }
+    }
+
+    /// Store `nonce` for `session_id`, replacing any pending nonce for it; expired sessions are dropped, and the oldest session is evicted if there is no room
+    pub fn insert(&mut self, session_id: SessionId, nonce: Nonce) {
+        self.insert_at(session_id, nonce, Instant::now());
+    }
+
+    /// Store `nonce` for `session_id` as `insert` does, taking the current time to be `now`
+    pub fn insert_at(&mut self, session_id: SessionId, nonce: Nonce, now: Instant) {
+        self.purge(now);
+        self.nonces.remove(&session_id);
+        while !self.nonces.is_empty() && self.nonces.len() >= self.max_sessions {
+            let oldest = self
+                .nonces
+                .iter()
+                .min_by_key(|(_, (_, created))| *created)
+                .map(|(id, _)| *id);
+            if let Some(oldest) = oldest {
+                self.nonces.remove(&oldest);
+            }
+        }
+        self.nonces.insert(session_id, (nonce, now));
+    }
+
+    /// Remove and return the nonce for `session_id`, unless it has expired
+    pub fn take(&mut self, session_id: SessionId) -> Option<Nonce> {
+        self.take_at(session_id, Instant::now())
+    }
+
+    /// Remove and return the nonce for `session_id` as `take` does, taking the current time to be `now`
+    pub fn take_at(&mut self, session_id: SessionId, now: Instant) -> Option<Nonce> {
+        self.purge(now);
+        self.nonces.remove(&session_id).map(|(nonce, _)| nonce)
+    }
+
+    /// Check if there is an unexpired nonce for `session_id`
+    pub fn contains(&self, session_id: SessionId) -> bool {
+        self.contains_at(session_id, Instant::now())
+    }
+
+    /// Check if there is an unexpired nonce for `session_id` as `contains` does, taking the current time to be `now`
+    pub fn contains_at(&self, session_id: SessionId, now: Instant) -> bool {
+        match self.nonces.get(&session_id) {
+            Some((_, created)) => now.saturating_duration_since(*created) < self.ttl,
+            None => false,
+        }
+    }
+
+    /// The number of pending sessions, including any which have expired but not yet been dropped
+    pub fn len(&self) -> usize {
+        self.nonces.len()
+    }
+
+    /// Check if there are no pending sessions
+    pub fn is_empty(&self) -> bool {
+        self.nonces.is_empty()
+    }
+
+    /// Drop every session which has expired by `now`
+    fn purge(&mut self, now: Instant) {
+        let ttl = self.ttl;
+        self.nonces
+            .retain(|_, (_, created)| now.saturating_duration_since(*created) < ttl);
+    }
+}
+
+impl Default for NonceSessions {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_SESSIONS, DEFAULT_SESSION_TTL)
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+/// Private nonces generated ahead of signing, indexed so that each can be used exactly once
+///
+/// Indices are never reused, so an index below `next_index` which is not pending has been consumed
+///
+/// Saved state holding a batch must never be restored out of order: an older snapshot still holds the nonces used since it was taken, and signing with one of them a second time reveals the private key. Save after every preprocessed signature and only ever load the latest save
+pub struct NonceBatch {
+    nonces: BTreeMap<u32, Nonce>,
+    next_index: u32,
+}
+
+impl NonceBatch {
+    /// Add `nonce` at the next index, returning the index and public nonce
+    pub fn push(&mut self, nonce: Nonce) -> (u32, PublicNonce) {
+        let index = self.next_index;
+        let public_nonce = PublicNonce::from(&nonce);
+        self.nonces.insert(index, nonce);
+        self.next_index += 1;
+
+        (index, public_nonce)
+    }
+
+    /// The indices and public nonces of every pending nonce, in index order
+    pub fn public_nonces(&self) -> Vec<(u32, PublicNonce)> {
+        self.nonces
+            .iter()
+            .map(|(index, nonce)| (*index, PublicNonce::from(nonce)))
+            .collect()
+    }
+
+    /// Remove and return the nonce at `index`, unless it has already been consumed
+    pub fn take(&mut self, index: u32) -> Option<Nonce> {
+        self.nonces.remove(&index)
+    }
+
+    /// Check if the nonce at `index` is still pending
+    pub fn contains(&self, index: u32) -> bool {
+        self.nonces.contains_key(&index)
+    }
+
+    /// The number of pending nonces
+    pub fn len(&self) -> usize {
+        self.nonces.len()
+    }
+
+    /// Check if there are no pending nonces
+    pub fn is_empty(&self) -> bool {
+        self.nonces.is_empty()
+    }
+
+    /// The index which the next generated nonce will have
+    pub fn next_index(&self) -> u32 {
+        self.next_index
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
 #[allow(non_snake_case)]
 /// A commitment to the private nonce
@@ -120,6 +483,82 @@ pub struct SignatureShare {
     pub key_ids: Vec<u32>,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+/// A complaint that the private share a party dealt to a key failed to decrypt or verify
+pub struct DkgComplaint {
+    /// The accused party ID
+    pub party_id: u32,
+    /// The key ID which received the bad share
+    pub key_id: u32,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+/// A private share which the dealing party publicly disclosed in response to a complaint
+pub struct DisclosedShare {
+    /// The dealing party ID
+    pub party_id: u32,
+    /// The key ID which the share was dealt to
+    pub key_id: u32,
+    /// The share itself
+    pub share: Scalar,
+}
+
+#[derive(Clone, Debug)]
+/// The outcome of a DKG complaint round, which every participant computes identically from the same public data
+pub struct Verdict {
+    /// The parties which failed to disclose a valid share for a complaint against them
+    pub cheaters: Vec<u32>,
+    /// The parties which remain qualified to contribute to the group key
+    pub qualified: Vec<u32>,
+    /// The disclosed shares which resolved complaints against honest parties
+    pub resolved: Vec<DisclosedShare>,
+}
+
+impl Verdict {
+    #[allow(non_snake_case)]
+    /// Judge the `complaints` using the `disclosures` from the accused parties and the poly commitments `A`, indexed by party ID
+    pub fn new(
+        complaints: &[DkgComplaint],
+        disclosures: &[DisclosedShare],
+        A: &HashMap<u32, PolyCommitment>,
+    ) -> Self {
+        let mut cheaters = Vec::new();
+        let mut resolved = Vec::new();
+
+        for complaint in complaints {
+            let disclosed = disclosures.iter().find(|disclosure| {
+                disclosure.party_id == complaint.party_id
+                    && disclosure.key_id == complaint.key_id
+                    && match A.get(&complaint.party_id) {
+                        Some(A_i) => A_i.verify_share(disclosure.key_id, &disclosure.share),
+                        None => false,
+                    }
+            });
+
+            match disclosed {
+                Some(disclosure) => resolved.push(disclosure.clone()),
+                None => cheaters.push(complaint.party_id),
+            }
+        }
+
+        cheaters.sort();
+        cheaters.dedup();
+
+        let mut qualified: Vec<u32> = A
+            .keys()
+            .filter(|party_id| !cheaters.contains(party_id))
+            .cloned()
+            .collect();
+        qualified.sort();
+
+        Self {
+            cheaters,
+            qualified,
+            resolved,
+        }
+    }
+}
+
 #[allow(non_snake_case)]
 /// An aggregated group signature
 pub struct Signature {
@@ -131,17 +570,122 @@ pub struct Signature {
 
 impl Signature {
     #[allow(non_snake_case)]
-    /// Verify the aggregated group signature
+    /// Verify the aggregated group signature against exactly `public_key`
     pub fn verify(&self, public_key: &Point, msg: &[u8]) -> bool {
         let c = challenge(public_key, &self.R, msg);
         let R = &self.z * G + (-c) * public_key;
 
         R == self.R
     }
+
+    /// Verify the aggregated group signature against the x coordinate of `public_key` only, as BIP-340 does, by lifting it to the point with even y
+    ///
+    /// Groups sign for the even-y point, so a group key with odd y needs this rather than `verify`
+    pub fn verify_xonly(&self, public_key: &Point, msg: &[u8]) -> bool {
+        self.verify(&even_y(public_key), msg)
+    }
+
+    #[allow(non_snake_case)]
+    /// Verify a batch of (public key, message, signature) triples with a random linear combination of the verification equations, returning the indices of any signatures which fail to verify
+    pub fn verify_batch<RNG: RngCore + CryptoRng>(
+        sigs: &[(&Point, &[u8], &Signature)],
+        rng: &mut RNG,
+    ) -> Result<(), Vec<usize>> {
+        let mut z = Scalar::zero();
+        let mut scalars = Vec::with_capacity(2 * sigs.len() + 1);
+        let mut points = Vec::with_capacity(2 * sigs.len() + 1);
+
+        for (i, (public_key, msg, sig)) in sigs.iter().enumerate() {
+            let c = challenge(public_key, &sig.R, msg);
+            // the first weight can be one without weakening the check
+            let a = if i == 0 {
+                Scalar::one()
+            } else {
+                Scalar::random(rng)
+            };
+
+            z += a * sig.z;
+            scalars.push(-(a * c));
+            points.push(**public_key);
+            scalars.push(-a);
+            points.push(sig.R);
+        }
+        scalars.push(z);
+        points.push(G);
+
+        match Point::multimult(scalars, points) {
+            Ok(sum) if sum.is_zero() => Ok(()),
+            _ => {
+                let bad: Vec<usize> = sigs
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (public_key, msg, sig))| !sig.verify(public_key, msg))
+                    .map(|(i, _)| i)
+                    .collect();
+                if bad.is_empty() {
+                    Ok(())
+                } else {
+                    Err(bad)
+                }
+            }
+        }
+    }
+
+    /// Verify a batch of (public key, message, signature) triples like `verify_batch`, but against the x coordinate of each public key only, as `verify_xonly` does
+    pub fn verify_batch_xonly<RNG: RngCore + CryptoRng>(
+        sigs: &[(&Point, &[u8], &Signature)],
+        rng: &mut RNG,
+    ) -> Result<(), Vec<usize>> {
+        let public_keys: Vec<Point> = sigs
+            .iter()
+            .map(|(public_key, _, _)| even_y(public_key))
+            .collect();
+        let sigs: Vec<(&Point, &[u8], &Signature)> = sigs
+            .iter()
+            .zip(&public_keys)
+            .map(|((_, msg, sig), public_key)| (public_key, *msg, *sig))
+            .collect();
+
+        Self::verify_batch(&sigs, rng)
+    }
+}
+
+/// Get the point with even y and the same x coordinate as `p`
+fn even_y(p: &Point) -> Point {
+    if p.has_even_y() {
+        *p
+    } else {
+        -p
+    }
 }
 
 /// Helper functions for tests
 pub mod test_helpers {
+    use p256k1::{point::G, scalar::Scalar};
+    use rand_core::{CryptoRng, RngCore};
+
+    use super::Signature;
+    use crate::{compute, Point};
+
+    #[allow(non_snake_case)]
+    /// Sign `msg` with a fresh single party key, returning the x-only public key and a signature with even R
+    pub fn sign<RNG: RngCore + CryptoRng>(msg: &[u8], rng: &mut RNG) -> (Point, Signature) {
+        let mut x = Scalar::random(rng);
+        if !(x * G).has_even_y() {
+            x = -x;
+        }
+        let mut k = Scalar::random(rng);
+        if !(k * G).has_even_y() {
+            k = -k;
+        }
+
+        let public_key = x * G;
+        let R = k * G;
+        let c = compute::challenge(&public_key, &R, msg);
+
+        (public_key, Signature { R, z: k + c * x })
+    }
+
     /// Generate a set of `k` vectors which divide `n` IDs evenly
     pub fn gen_signer_ids(n: u32, k: u32) -> Vec<Vec<u32>> {
         let mut ids = Vec::new();
@@ -158,3 +702,134 @@ pub mod test_helpers {
         ids
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{test_helpers, Nonce, SecretPoly, SecretScalar, Signature};
+    use crate::Scalar;
+
+    use num_traits::Zero;
+    use rand_core::{CryptoRng, OsRng, RngCore};
+    use zeroize::Zeroize;
+
+    /// An RNG which always returns the same bytes, like a VM which was restored from a snapshot
+    struct StuckRng;
+
+    impl RngCore for StuckRng {
+        fn next_u32(&mut self) -> u32 {
+            7
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            7
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            dest.fill(7);
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    impl CryptoRng for StuckRng {}
+
+    #[test]
+    fn secrets_redacted_and_zeroized() {
+        let mut rng = OsRng::default();
+        let x = Scalar::random(&mut rng);
+
+        let mut secret = SecretScalar::from(x);
+        let mut poly = SecretPoly::new(vec![x, x]);
+        let mut nonce = Nonce { d: x, e: x };
+        for s in [
+            format!("{:?} {} {:?} {}", secret, secret, poly, poly),
+            format!("{:?} {}", nonce, nonce),
+        ] {
+            assert!(s.contains("<redacted>"));
+            assert!(!s.contains("secp256k1_scalar"));
+            assert!(!s.contains(&x.to_string()));
+        }
+
+        assert_eq!(poly.eval(Scalar::from(2)), x + x * Scalar::from(2));
+
+        secret.zeroize();
+        poly.zeroize();
+        nonce.zeroize();
+        assert_eq!(*secret, Scalar::zero());
+        assert!(poly.data().iter().all(|a| *a == Scalar::zero()));
+        assert_eq!(nonce.d, Scalar::zero());
+        assert_eq!(nonce.e, Scalar::zero());
+    }
+
+    #[test]
+    fn hedged_nonces() {
+        let mut rng = OsRng::default();
+        let x = Scalar::random(&mut rng);
+        let y = Scalar::random(&mut rng);
+
+        // a stuck RNG repeats random nonces, which would leak the key share
+        assert!(Nonce::random(&mut StuckRng) == Nonce::random(&mut StuckRng));
+
+        // hedged nonces still differ across contexts and keys
+        let nonce = Nonce::hedged(&[&x], b"session 1", &mut StuckRng);
+        assert!(nonce == Nonce::hedged(&[&x], b"session 1", &mut StuckRng));
+        assert!(nonce != Nonce::hedged(&[&x], b"session 2", &mut StuckRng));
+        assert!(nonce != Nonce::hedged(&[&y], b"session 1", &mut StuckRng));
+        assert!(nonce.d != nonce.e);
+
+        // and with a working RNG they never repeat
+        assert!(
+            Nonce::hedged(&[&x], b"session 1", &mut rng)
+                != Nonce::hedged(&[&x], b"session 1", &mut rng)
+        );
+    }
+
+    #[test]
+    fn verify_batch() {
+        let mut rng = OsRng::default();
+        let msgs: Vec<Vec<u8>> = (0..8u8).map(|i| vec![i; 32]).collect();
+        let mut sigs: Vec<_> = msgs
+            .iter()
+            .map(|msg| test_helpers::sign(msg, &mut rng))
+            .collect();
+
+        let batch: Vec<_> = sigs
+            .iter()
+            .zip(&msgs)
+            .map(|((public_key, sig), msg)| (public_key, msg.as_slice(), sig))
+            .collect();
+        assert!(Signature::verify_batch(&batch, &mut rng).is_ok());
+        assert!(Signature::verify_batch(&[], &mut rng).is_ok());
+
+        // corrupt two signatures, which the fallback identifies
+        sigs[2].1.z = Scalar::random(&mut rng);
+        sigs[5].0 = sigs[6].0;
+        let batch: Vec<_> = sigs
+            .iter()
+            .zip(&msgs)
+            .map(|((public_key, sig), msg)| (public_key, msg.as_slice(), sig))
+            .collect();
+        assert_eq!(Signature::verify_batch(&batch, &mut rng), Err(vec![2, 5]));
+    }
+
+    #[test]
+    fn verify_parity() {
+        let mut rng = OsRng::default();
+        let msg = "In a kingdom by the sea".as_bytes();
+        let (public_key, sig) = test_helpers::sign(msg, &mut rng);
+        let odd_key = -public_key;
+
+        // the odd-y key has the same x coordinate, which only the x-only verifiers accept
+        assert!(sig.verify(&public_key, msg));
+        assert!(!sig.verify(&odd_key, msg));
+        assert!(sig.verify_xonly(&public_key, msg));
+        assert!(sig.verify_xonly(&odd_key, msg));
+
+        let batch = [(&odd_key, msg, &sig), (&public_key, msg, &sig)];
+        assert_eq!(Signature::verify_batch(&batch, &mut rng), Err(vec![0]));
+        assert!(Signature::verify_batch_xonly(&batch, &mut rng).is_ok());
+    }
+}
@@ -1,9 +1,19 @@
-use core::iter::zip;
+use core::{
+    fmt::{Debug, Formatter, Result as FmtResult},
+    iter::zip,
+};
+use hashbrown::HashMap;
 use num_traits::{One, Zero};
-use p256k1::{point::Error as PointError, point::Point, scalar::Scalar};
+use p256k1::{
+    point::{Error as PointError, Point, G},
+    scalar::Scalar,
+};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 use sha2::{Digest, Sha256};
+use std::sync::{Arc, Mutex, MutexGuard};
 
-use crate::common::PublicNonce;
+use crate::common::{PolyCommitment, ProtocolVersion, PublicNonce, SessionId};
 use crate::util::hash_to_scalar;
 
 #[allow(non_snake_case)]
@@ -23,6 +33,86 @@ pub fn binding(id: &Scalar, B: &[PublicNonce], msg: &[u8]) -> Scalar {
     hash_to_scalar(&mut hasher)
 }
 
+#[derive(Clone, Copy, Debug)]
+/// The public context of a signing round, which versioned binding values commit to
+pub struct BindingContext<'a> {
+    /// The protocol version of the group
+    pub version: ProtocolVersion,
+    /// The untweaked group public key
+    pub group_key: &'a Point,
+    /// The signing session ID, which every signer and the aggregator must agree on
+    pub session_id: SessionId,
+    /// The IDs of the parties which own each public nonce, in nonce order
+    pub party_ids: &'a [u32],
+    /// The key IDs of the signing parties, in party order
+    pub key_ids: &'a [u32],
+}
+
+#[allow(non_snake_case)]
+/// Compute the binding value of the party with ID `id` from the public nonces `B` and signed message, committing to everything which `ctx.version` requires
+pub fn versioned_binding(
+    ctx: &BindingContext,
+    id: &Scalar,
+    B: &[PublicNonce],
+    msg: &[u8],
+) -> Scalar {
+    match ctx.version {
+        ProtocolVersion::V0 => binding(id, B, msg),
+        ProtocolVersion::V1 => {
+            let mut hasher = Sha256::new();
+            let prefix = "WSTS/binding/v1";
+
+            hasher.update(prefix.as_bytes());
+            hasher.update(id.to_bytes());
+            hasher.update(ctx.group_key.compress().as_bytes());
+            hasher.update(ctx.session_id.to_be_bytes());
+            hasher.update((B.len() as u64).to_be_bytes());
+            for (party_id, b) in zip(ctx.party_ids, B) {
+                hasher.update(party_id.to_be_bytes());
+                hasher.update(b.D.compress().as_bytes());
+                hasher.update(b.E.compress().as_bytes());
+            }
+            hasher.update((ctx.key_ids.len() as u64).to_be_bytes());
+            for key_id in ctx.key_ids {
+                hasher.update(key_id.to_be_bytes());
+            }
+            hasher.update(msg);
+
+            hash_to_scalar(&mut hasher)
+        }
+    }
+}
+
+/// Compute a digest which binds the signer `signer_id` to its polynomial commitments `comms`, indexed by party ID, in the DKG round `dkg_id`, so it can commit to them before revealing them
+pub fn poly_commitments_digest(
+    dkg_id: u64,
+    signer_id: u32,
+    comms: &HashMap<u32, PolyCommitment>,
+) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    let prefix = "WSTS/dkg/commit";
+
+    hasher.update(prefix.as_bytes());
+    hasher.update(dkg_id.to_be_bytes());
+    hasher.update(signer_id.to_be_bytes());
+    hasher.update((comms.len() as u64).to_be_bytes());
+    let mut party_ids: Vec<&u32> = comms.keys().collect();
+    party_ids.sort();
+    for party_id in party_ids {
+        let comm = &comms[party_id];
+        hasher.update(party_id.to_be_bytes());
+        hasher.update(comm.id.id.to_bytes());
+        hasher.update(comm.id.kG.compress().as_bytes());
+        hasher.update(comm.id.kca.to_bytes());
+        hasher.update((comm.A.len() as u64).to_be_bytes());
+        for a in &comm.A {
+            hasher.update(a.compress().as_bytes());
+        }
+    }
+
+    hasher.finalize().into()
+}
+
 #[allow(non_snake_case)]
 /// Compute the schnorr challenge from the public key, aggregated commitments, and the signed message
 pub fn challenge(publicKey: &Point, R: &Point, msg: &[u8]) -> Scalar {
@@ -44,6 +134,34 @@ pub fn challenge(publicKey: &Point, R: &Point, msg: &[u8]) -> Scalar {
     hash_to_scalar(&mut hasher)
 }
 
+/// Derive a nonce value from a tagged hash of fresh `randomness`, the `secrets` of the party which will sign with it, and the signing `context`, so that the nonce stays unpredictable unless both the randomness and the secrets are known
+pub fn hedged_nonce(
+    label: &[u8],
+    randomness: &[u8; 32],
+    secrets: &[&Scalar],
+    context: &[u8],
+) -> Scalar {
+    let prefix = "WSTS/nonce";
+    let mut hasher = Sha256::new();
+    let mut prefix_hasher = Sha256::new();
+
+    prefix_hasher.update(prefix.as_bytes());
+    let prefix_hash = prefix_hasher.finalize();
+
+    hasher.update(prefix_hash);
+    hasher.update(prefix_hash);
+    hasher.update(randomness);
+    hasher.update((secrets.len() as u64).to_be_bytes());
+    for secret in secrets {
+        hasher.update(secret.to_bytes());
+    }
+    hasher.update((context.len() as u64).to_be_bytes());
+    hasher.update(context);
+    hasher.update(label);
+
+    hash_to_scalar(&mut hasher)
+}
+
 /// Compute the Lagrange interpolation value
 pub fn lambda(i: u32, key_ids: &[u32]) -> Scalar {
     let mut lambda = Scalar::one();
@@ -57,6 +175,111 @@ pub fn lambda(i: u32, key_ids: &[u32]) -> Scalar {
     lambda
 }
 
+/// Compute the Lagrange interpolation value of every ID in `key_ids`, using a single batch inversion
+pub fn lambdas(key_ids: &[u32]) -> HashMap<u32, Scalar> {
+    let mut key_ids = key_ids.to_vec();
+    key_ids.sort();
+    key_ids.dedup();
+
+    let xs: Vec<Scalar> = key_ids.iter().map(|i| id(*i)).collect();
+    let prod = xs.iter().fold(Scalar::one(), |prod, x| prod * x);
+    // lambda_i = prod / (x_i * prod_{j != i} (x_j - x_i))
+    let denoms: Vec<Scalar> = xs
+        .iter()
+        .enumerate()
+        .map(|(i, x_i)| {
+            xs.iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .fold(*x_i, |denom, (_, x_j)| denom * (x_j - x_i))
+        })
+        .collect();
+
+    zip(key_ids, batch_invert(&denoms))
+        .map(|(i, inv)| (i, prod * inv))
+        .collect()
+}
+
+/// Invert every scalar in `xs` with a single inversion, using Montgomery's trick
+pub fn batch_invert(xs: &[Scalar]) -> Vec<Scalar> {
+    let mut prefix = Vec::with_capacity(xs.len());
+    let mut acc = Scalar::one();
+    for x in xs {
+        prefix.push(acc);
+        acc *= x;
+    }
+
+    let mut inv = acc.invert();
+    let mut invs = vec![Scalar::zero(); xs.len()];
+    for i in (0..xs.len()).rev() {
+        invs[i] = inv * prefix[i];
+        inv *= xs[i];
+    }
+
+    invs
+}
+
+/// The number of signing sets whose Lagrange interpolation values a `LagrangeCache` holds before it is cleared
+const LAGRANGE_CACHE_SIZE: usize = 16;
+
+/// Lagrange interpolation values indexed by the sorted signing set and then ID
+type LagrangeSets = HashMap<Vec<u32>, Arc<HashMap<u32, Scalar>>>;
+
+#[derive(Default)]
+/// A cache of the Lagrange interpolation values for recent signing sets, which is safe to share between threads
+pub struct LagrangeCache {
+    sets: Mutex<LagrangeSets>,
+}
+
+impl LagrangeCache {
+    /// Get the Lagrange interpolation values of every ID in `key_ids`, computing and caching them on first use
+    pub fn lambdas(&self, key_ids: &[u32]) -> Arc<HashMap<u32, Scalar>> {
+        let mut set = key_ids.to_vec();
+        set.sort();
+        set.dedup();
+
+        if let Some(lambdas) = self.lock().get(&set) {
+            return lambdas.clone();
+        }
+
+        let lambdas = Arc::new(lambdas(&set));
+        let mut sets = self.lock();
+        if sets.len() >= LAGRANGE_CACHE_SIZE {
+            sets.clear();
+        }
+        sets.insert(set, lambdas.clone());
+
+        lambdas
+    }
+
+    /// Get the Lagrange interpolation value of `i` in `key_ids`, which falls back to `lambda` if `i` is not in the set
+    pub fn lambda(&self, i: u32, key_ids: &[u32]) -> Scalar {
+        match self.lambdas(key_ids).get(&i) {
+            Some(lambda) => *lambda,
+            None => lambda(i, key_ids),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, LagrangeSets> {
+        // the cache is always consistent, so a panic while it was held leaves nothing to recover
+        self.sets.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Clone for LagrangeCache {
+    fn clone(&self) -> Self {
+        Self {
+            sets: Mutex::new(self.lock().clone()),
+        }
+    }
+}
+
+impl Debug for LagrangeCache {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "LagrangeCache({} sets)", self.lock().len())
+    }
+}
+
 // Is this the best way to return these values?
 #[allow(non_snake_case)]
 /// Compute the intermediate values used in both the parties and the aggregator
@@ -65,6 +288,29 @@ pub fn intermediate(msg: &[u8], party_ids: &[u32], nonces: &[PublicNonce]) -> (V
         .iter()
         .map(|&i| binding(&id(i), nonces, msg))
         .collect();
+
+    commitments(nonces, rhos)
+}
+
+#[allow(non_snake_case)]
+/// Compute the intermediate values used in both the parties and the aggregator, with the binding values for `ctx`
+pub fn versioned_intermediate(
+    ctx: &BindingContext,
+    msg: &[u8],
+    nonces: &[PublicNonce],
+) -> (Vec<Point>, Point) {
+    let rhos: Vec<Scalar> = ctx
+        .party_ids
+        .iter()
+        .map(|&i| versioned_binding(ctx, &id(i), nonces, msg))
+        .collect();
+
+    commitments(nonces, rhos)
+}
+
+#[allow(non_snake_case)]
+/// Bind each public nonce with its binding value, returning the per-party commitments and their sum
+fn commitments(nonces: &[PublicNonce], rhos: Vec<Scalar>) -> (Vec<Point>, Point) {
     let R_vec: Vec<Point> = zip(nonces, rhos)
         .map(|(nonce, rho)| nonce.D + rho * nonce.E)
         .collect();
@@ -73,9 +319,9 @@ pub fn intermediate(msg: &[u8], party_ids: &[u32], nonces: &[PublicNonce]) -> (V
     (R_vec, R)
 }
 
-/// Compute a one-based Scalar from a zero-based integer
+/// Compute a one-based Scalar from a zero-based integer; the addition is done in the field, so no ID maps to zero, where the polynomial evaluates to the secret
 pub fn id(i: u32) -> Scalar {
-    Scalar::from(i + 1)
+    Scalar::from(i) + Scalar::one()
 }
 
 /// Evaluate the public polynomial `f` at scalar `x` using multi-exponentiation
@@ -89,3 +335,221 @@ pub fn poly(x: &Scalar, f: &Vec<Point>) -> Result<Point, PointError> {
 
     Point::multimult(s, f.clone())
 }
+
+/// Evaluate the group polynomial `f` at each of the `key_ids` to get their public keys, omitting any which fail to evaluate
+pub fn public_keys(key_ids: &[u32], f: &[Point]) -> HashMap<u32, Point> {
+    let f = f.to_vec();
+
+    into_par_iter!(0..key_ids.len())
+        .filter_map(|i| poly(&id(key_ids[i]), &f).ok().map(|p| (key_ids[i], p)))
+        .collect()
+}
+
+/// Compute the BIP-341 TapTweak of the x-only `public_key` and an optional script `merkle_root`
+pub fn tweak(public_key: &Point, merkle_root: Option<[u8; 32]>) -> Scalar {
+    let prefix = "TapTweak";
+    let mut hasher = Sha256::new();
+    let mut prefix_hasher = Sha256::new();
+
+    prefix_hasher.update(prefix.as_bytes());
+    let prefix_hash = prefix_hasher.finalize();
+
+    hasher.update(prefix_hash);
+    hasher.update(prefix_hash);
+    hasher.update(public_key.x().to_bytes());
+    if let Some(root) = merkle_root {
+        hasher.update(root);
+    }
+
+    hash_to_scalar(&mut hasher)
+}
+
+/// Compute the BIP-341 output key from the internal `public_key` and an optional script `merkle_root`; only its x coordinate goes in the taproot output
+pub fn tweaked_public_key(public_key: &Point, merkle_root: Option<[u8; 32]>) -> Point {
+    let internal_key = if public_key.has_even_y() {
+        *public_key
+    } else {
+        -public_key
+    };
+
+    internal_key + tweak(public_key, merkle_root) * G
+}
+
+#[allow(non_snake_case)]
+/// Compute the even-y key which a signature tweaked by `tweak` must verify against, as `(a * group_key + b * G, a, b)`; each party multiplies its private key by `a` and the aggregator adds `c * b` to the response
+pub fn tweaked_key(group_key: &Point, tweak: &Scalar) -> (Point, Scalar, Scalar) {
+    let mut a = if group_key.has_even_y() {
+        Scalar::one()
+    } else {
+        -Scalar::one()
+    };
+    let mut b = *tweak;
+
+    let Q = a * group_key + b * G;
+    if !Q.has_even_y() {
+        a = -a;
+        b = -b;
+    }
+
+    (a * group_key + b * G, a, b)
+}
+
+#[allow(non_snake_case)]
+/// Check that each `(key_id, sender, share)` matches the sender's poly commitment in `A`, indexed by party ID, returning the indices of the bad shares, including any from an unknown sender
+///
+/// All checks are combined into one multi-exponentiation using weights derived by hashing every input, and the shares are only bisected to find the bad ones if the combined check fails
+pub fn verify_shares(
+    shares: &[(u32, u32, Scalar)],
+    A: &HashMap<u32, PolyCommitment>,
+) -> Result<Vec<usize>, PointError> {
+    let mut hasher = Sha256::new();
+    hasher.update("WSTS/share_weights".as_bytes());
+    for (key_id, sender, share) in shares {
+        hasher.update(key_id.to_be_bytes());
+        hasher.update(sender.to_be_bytes());
+        hasher.update(share.to_bytes());
+    }
+    let mut party_ids: Vec<&u32> = A.keys().collect();
+    party_ids.sort();
+    for party_id in party_ids {
+        hasher.update(party_id.to_be_bytes());
+        for a in &A[party_id].A {
+            hasher.update(a.compress().as_bytes());
+        }
+    }
+    let weights: Vec<Scalar> = into_par_iter!(0..shares.len())
+        .map(|i| {
+            let mut weight_hasher = hasher.clone();
+            weight_hasher.update(i.to_be_bytes());
+            hash_to_scalar(&mut weight_hasher)
+        })
+        .collect();
+
+    let mut bad = Vec::new();
+    let mut indices = Vec::new();
+    for (i, (_, sender, _)) in shares.iter().enumerate() {
+        if A.contains_key(sender) {
+            indices.push(i);
+        } else {
+            bad.push(i);
+        }
+    }
+
+    bad.extend(bisect_shares(shares, A, &weights, &indices)?);
+    bad.sort();
+
+    Ok(bad)
+}
+
+#[allow(non_snake_case)]
+/// Recursively split `indices` until every bad share is isolated, returning the bad indices
+fn bisect_shares(
+    shares: &[(u32, u32, Scalar)],
+    A: &HashMap<u32, PolyCommitment>,
+    weights: &[Scalar],
+    indices: &[usize],
+) -> Result<Vec<usize>, PointError> {
+    if indices.is_empty() || check_shares(shares, A, weights, indices)? {
+        return Ok(Vec::new());
+    }
+    if indices.len() == 1 {
+        return Ok(indices.to_vec());
+    }
+
+    let (left, right) = indices.split_at(indices.len() / 2);
+    #[cfg(feature = "parallel")]
+    let (left, right) = rayon::join(
+        || bisect_shares(shares, A, weights, left),
+        || bisect_shares(shares, A, weights, right),
+    );
+    #[cfg(not(feature = "parallel"))]
+    let (left, right) = (
+        bisect_shares(shares, A, weights, left),
+        bisect_shares(shares, A, weights, right),
+    );
+
+    let mut bad = left?;
+    bad.extend(right?);
+    Ok(bad)
+}
+
+#[allow(non_snake_case)]
+/// Check the weighted sum of the share equations at `indices` with one multi-exponentiation
+fn check_shares(
+    shares: &[(u32, u32, Scalar)],
+    A: &HashMap<u32, PolyCommitment>,
+    weights: &[Scalar],
+    indices: &[usize],
+) -> Result<bool, PointError> {
+    let mut z = Scalar::zero();
+    let mut coeffs: HashMap<u32, Vec<Scalar>> = HashMap::new();
+
+    for i in indices {
+        let (key_id, sender, share) = &shares[*i];
+        let A_i = &A[sender].A;
+        let x = id(*key_id);
+        let mut pow = weights[*i];
+
+        z += weights[*i] * share;
+        let c = coeffs
+            .entry(*sender)
+            .or_insert_with(|| vec![Scalar::zero(); A_i.len()]);
+        for c_k in c.iter_mut() {
+            *c_k += pow;
+            pow *= x;
+        }
+    }
+
+    let mut scalars = vec![z];
+    let mut points = vec![G];
+    for (sender, c) in coeffs {
+        let A_i = &A[&sender].A;
+        for (c_k, a) in c.into_iter().zip(A_i) {
+            scalars.push(-c_k);
+            points.push(*a);
+        }
+    }
+
+    Ok(Point::multimult(scalars, points)?.is_zero())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{id, lambda, lambdas, LagrangeCache};
+    use crate::Scalar;
+
+    use num_traits::Zero;
+    use rand_core::OsRng;
+
+    #[test]
+    fn id_never_zero() {
+        assert_eq!(id(0), Scalar::from(1));
+        assert_ne!(id(u32::MAX), Scalar::zero());
+        assert_eq!(id(u32::MAX) - id(u32::MAX - 1), Scalar::from(1));
+    }
+
+    #[test]
+    fn batch_lambdas() {
+        let key_ids = [0, 3, 4, 7, 8, 11, 12, 20];
+        let batch = lambdas(&key_ids);
+        assert_eq!(batch.len(), key_ids.len());
+        for i in &key_ids {
+            assert_eq!(batch[i], lambda(*i, &key_ids));
+        }
+
+        // order and duplicates do not change the signing set
+        let cache = LagrangeCache::default();
+        let shuffled = [20, 3, 0, 12, 4, 8, 7, 11, 3];
+        for i in &key_ids {
+            assert_eq!(cache.lambda(*i, &shuffled), batch[i]);
+        }
+        assert_eq!(cache.lambda(5, &key_ids), lambda(5, &key_ids));
+
+        let xs: Vec<Scalar> = (0..8)
+            .map(|_| Scalar::random(&mut OsRng::default()))
+            .collect();
+        for (x, inv) in xs.iter().zip(super::batch_invert(&xs)) {
+            assert_eq!(x * inv, Scalar::from(1));
+        }
+    }
+}
@@ -1,6 +1,9 @@
 use p256k1::{point::Error as PointError, scalar::Scalar};
+use std::io::Error as IoError;
 use thiserror::Error;
 
+use crate::common::SessionId;
+
 #[derive(Error, Debug, Clone)]
 /// Errors which can happen during distributed key generation
 pub enum DkgError {
@@ -19,6 +22,21 @@ pub enum DkgError {
     #[error("point error {0:?}")]
     /// An error during point operations
     Point(PointError),
+    #[error("failed to decrypt shares from {0:?}")]
+    /// The signers whose encrypted shares failed to decrypt
+    BadEncryptedShares(Vec<u32>),
+    #[error("bad refresh commitments {0:?}")]
+    /// The IDs whose refresh commitments had a nonzero constant term or failed to verify
+    BadRefreshCommitments(Vec<u32>),
+    #[error("bad reshare commitments {0:?}")]
+    /// The old key IDs whose reshare commitments did not match their public key shares or failed to verify
+    BadReshareCommitments(Vec<u32>),
+    #[error("not enough dealers (got {0} need {1})")]
+    /// Fewer keys dealt shares than the threshold, either old keys in a resharing round or keys held by a qualified set of parties
+    NotEnoughDealers(usize, usize),
+    #[error("no private key for key {0}")]
+    /// The party has no private key for the key ID, so it cannot deal sub-shares of it
+    NoPrivateKey(u32),
 }
 
 impl From<PointError> for DkgError {
@@ -27,6 +45,58 @@ impl From<PointError> for DkgError {
     }
 }
 
+#[derive(Error, Debug, Clone)]
+/// Errors which can happen when a party signs
+pub enum SignError {
+    #[error("no fresh nonce for party {0}")]
+    /// The party has not generated a nonce since it last signed
+    NoNonce(u32),
+    #[error("zero nonce for party {0}")]
+    /// The party's nonce was zero
+    ZeroNonce(u32),
+    #[error("no preprocessed nonce for party {0} at index {1}")]
+    /// The party never generated a preprocessed nonce at the index, or has already used it
+    NoPreprocessedNonce(u32, u32),
+    #[error("no private key for key {0}")]
+    /// The party has no private key for the key ID, so DKG has not completed for it
+    NoPrivateKey(u32),
+    #[error("nonce store error {0:?}")]
+    /// The nonce store could not durably consume the nonce
+    Store(NonceStoreError),
+}
+
+impl From<NonceStoreError> for SignError {
+    fn from(e: NonceStoreError) -> Self {
+        SignError::Store(e)
+    }
+}
+
+#[derive(Error, Debug, Clone)]
+/// Errors which can happen when issuing or consuming nonces in a nonce store
+pub enum NonceStoreError {
+    #[error("io error {0}")]
+    /// Reading or durably writing the store failed
+    Io(String),
+    #[error("corrupt nonce store at line {0}")]
+    /// A complete record in the store could not be parsed or contradicted an earlier one
+    Corrupt(usize),
+    #[error("nonce already issued for party {0} session {1}")]
+    /// A nonce was already issued to the party for the session, so issuing another could reuse the session
+    AlreadyIssued(u32, SessionId),
+    #[error("nonce never issued for party {0} session {1}")]
+    /// No nonce was ever issued to the party for the session
+    NotIssued(u32, SessionId),
+    #[error("nonce already consumed for party {0} session {1}")]
+    /// The nonce for the session was already used to sign, so it must never be used again
+    Consumed(u32, SessionId),
+}
+
+impl From<IoError> for NonceStoreError {
+    fn from(e: IoError) -> Self {
+        NonceStoreError::Io(e.to_string())
+    }
+}
+
 #[derive(Error, Debug, Clone)]
 /// Errors which can happen during signature aggregation
 pub enum AggregatorError {
@@ -36,6 +106,9 @@ pub enum AggregatorError {
     #[error("bad poly commitments {0:?}")]
     /// The polynomial commitments which failed verification
     BadPolyCommitments(Vec<Scalar>),
+    #[error("bad refresh commitments {0:?}")]
+    /// The refresh commitments which had a nonzero constant term or failed verification
+    BadRefreshCommitments(Vec<Scalar>),
     #[error("bad nonce length (expected {0} got {1}")]
     /// The nonce length was the wrong size
     BadNonceLen(usize, usize),
@@ -48,4 +121,33 @@ pub enum AggregatorError {
     #[error("bad group sig")]
     /// The aggregate group signature failed to verify
     BadGroupSig,
+    #[error("reshare error {0:?}")]
+    /// The reshare commitments failed verification
+    Reshare(DkgError),
+    #[error("not enough dealers (got {0} need {1})")]
+    /// The qualified set of parties held fewer keys than the threshold
+    NotEnoughDealers(usize, usize),
+}
+
+#[derive(Error, Debug, Clone)]
+/// Errors which can happen during encryption and decryption of private shares
+pub enum EncryptionError {
+    #[error("missing public key for {0}")]
+    /// The public key for this ID was missing
+    MissingPublicKey(u32),
+    #[error("ciphertext too short")]
+    /// The ciphertext was too short to hold a nonce
+    CiphertextTooShort,
+    #[error("bad plaintext length {0}")]
+    /// The plaintext was not a serialized scalar
+    BadPlaintextLen(usize),
+    #[error("bad key length")]
+    /// The symmetric key was the wrong length
+    BadKeyLen,
+    #[error("encryption failed")]
+    /// The AEAD encryption failed
+    EncryptionFailed,
+    #[error("decryption failed")]
+    /// The AEAD decryption failed, so the ciphertext or associated data was tampered with
+    DecryptionFailed,
 }
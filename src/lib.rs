@@ -1,6 +1,28 @@
 #![deny(missing_docs)]
 #![doc = include_str!("../README.md")]
 
+/// Iterate over mutable references to the items of a collection, in parallel when the `parallel` feature is enabled
+macro_rules! par_iter_mut {
+    ($e:expr) => {{
+        #[cfg(feature = "parallel")]
+        let iter = rayon::iter::IntoParallelRefMutIterator::par_iter_mut(&mut $e);
+        #[cfg(not(feature = "parallel"))]
+        let iter = $e.iter_mut();
+        iter
+    }};
+}
+
+/// Iterate over the items of a range, in parallel when the `parallel` feature is enabled
+macro_rules! into_par_iter {
+    ($e:expr) => {{
+        #[cfg(feature = "parallel")]
+        let iter = rayon::iter::IntoParallelIterator::into_par_iter($e);
+        #[cfg(not(feature = "parallel"))]
+        let iter = $e.into_iter();
+        iter
+    }};
+}
+
 /// Functions for doing BIP-340 schnorr proofs
 pub mod bip340;
 /// Types which are common to both v1 and v2
@@ -9,11 +31,19 @@ pub mod common;
 pub mod compute;
 /// Errors which are returned from objects and functions
 pub mod errors;
+/// Network messages
+pub mod net;
+/// Crash-safe storage for signing nonces
+pub mod nonce_store;
+/// Resharing the group private key to a new committee
+pub mod reshare;
 /// Schnorr utility types
 pub mod schnorr;
+/// State machines for driving DKG and signing rounds over a network
+pub mod state_machine;
 /// Traits which are used for v1 and v2
 pub mod traits;
-/// Utilities for hashing scalars
+/// Utilities for hashing scalars and encrypting data
 pub mod util;
 /// Version 1 of WSTS, which encapsulates a number of parties using vanilla FROST
 pub mod v1;
@@ -41,15 +41,16 @@ fn main() {
         let dkg_time = dkg_start.elapsed();
         let mut signers = signers[..(K * 3 / 4).try_into().unwrap()].to_vec();
 
-        let mut aggregator = v1::SignatureAggregator::new(N, T, A).expect("aggregator ctor failed");
+        let mut aggregator =
+            v1::SignatureAggregator::new(N, T, &A).expect("aggregator ctor failed");
 
         let party_sign_start = time::Instant::now();
-        let (nonces, sig_shares) = v1::test_helpers::sign(msg, &mut signers, &mut rng);
+        let (session_id, nonces, sig_shares) = v1::test_helpers::sign(msg, &mut signers, &mut rng);
         let party_sign_time = party_sign_start.elapsed();
 
         let group_sign_start = time::Instant::now();
         let _sig = aggregator
-            .sign(msg, &nonces, &sig_shares)
+            .sign(session_id, msg, &nonces, &sig_shares)
             .expect("v1 group sign failed");
         let group_sign_time = group_sign_start.elapsed();
 
@@ -76,15 +77,17 @@ fn main() {
         let dkg_time = dkg_start.elapsed();
         let mut signers = signers[..(K * 3 / 4).try_into().unwrap()].to_vec();
 
-        let mut aggregator = v2::SignatureAggregator::new(N, T, A).expect("aggregator ctor failed");
+        let mut aggregator =
+            v2::SignatureAggregator::new(N, T, &A).expect("aggregator ctor failed");
 
         let party_sign_start = time::Instant::now();
-        let (nonces, sig_shares, key_ids) = v2::test_helpers::sign(msg, &mut signers, &mut rng);
+        let (session_id, nonces, sig_shares, key_ids) =
+            v2::test_helpers::sign(msg, &mut signers, &mut rng);
         let party_sign_time = party_sign_start.elapsed();
 
         let group_sign_start = time::Instant::now();
         let _sig = aggregator
-            .sign(msg, &nonces, &sig_shares, &key_ids)
+            .sign(session_id, msg, &nonces, &sig_shares, &key_ids)
             .expect("v2 group sign failed");
         let group_sign_time = group_sign_start.elapsed();
 
@@ -0,0 +1,490 @@
+use hashbrown::HashMap;
+use p256k1::{ecdsa, point::Point, scalar::Scalar};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    common::{DisclosedShare, DkgComplaint, PartyIds, PolyCommitment, PublicNonce, SignatureShare},
+    state_machine::PublicKeys,
+};
+
+/// Trait to encapsulate sign/verify, users only need to impl hash
+pub trait Signable {
+    /// Hash this object in a consistent way so it can be signed/verified
+    fn hash(&self, hasher: &mut Sha256);
+
+    /// Sign a hash of this object using the passed private key
+    fn sign(&self, private_key: &Scalar) -> Result<Vec<u8>, ecdsa::Error> {
+        let mut hasher = Sha256::new();
+
+        self.hash(&mut hasher);
+
+        let hash = hasher.finalize();
+        let sig = ecdsa::Signature::new(hash.as_slice(), private_key)?;
+
+        Ok(sig.to_bytes().to_vec())
+    }
+
+    /// Verify a hash of this object using the passed public key
+    fn verify(&self, signature: &[u8], public_key: &Point) -> bool {
+        let mut hasher = Sha256::new();
+
+        self.hash(&mut hasher);
+
+        let hash = hasher.finalize();
+        let sig = match ecdsa::Signature::try_from(signature) {
+            Ok(sig) => sig,
+            Err(_) => return false,
+        };
+        let public_key = match ecdsa::PublicKey::try_from(public_key.compress().as_bytes()) {
+            Ok(public_key) => public_key,
+            Err(_) => return false,
+        };
+
+        sig.verify(hash.as_slice(), &public_key)
+    }
+}
+
+fn hash_poly_commitment(comm: &PolyCommitment, hasher: &mut Sha256) {
+    hasher.update(comm.id.id.to_bytes());
+    hasher.update(comm.id.kG.compress().as_bytes());
+    hasher.update(comm.id.kca.to_bytes());
+    for a in &comm.A {
+        hasher.update(a.compress().as_bytes());
+    }
+}
+
+fn hash_public_nonce(nonce: &PublicNonce, hasher: &mut Sha256) {
+    hasher.update(nonce.D.compress().as_bytes());
+    hasher.update(nonce.E.compress().as_bytes());
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+/// Encapsulation of all possible network message types
+pub enum Message {
+    /// Tell signers to begin DKG by sending DKG public shares
+    DkgBegin(DkgBegin),
+    /// Commit to DKG public shares before revealing them
+    DkgCommitment(DkgCommitment),
+    /// Send DKG public shares
+    DkgPublicShares(DkgPublicShares),
+    /// Send DKG private shares
+    DkgPrivateShares(DkgPrivateShares),
+    /// Tell signers to stop waiting for absent signers and finish DKG over the listed ones
+    DkgEndBegin(DkgEndBegin),
+    /// Tell coordinator that DKG is complete
+    DkgEnd(DkgEnd),
+    /// Disclose private shares which other signers complained about
+    DkgDisclosure(DkgDisclosure),
+    /// Tell signers to send signing nonces
+    NonceRequest(NonceRequest),
+    /// Tell coordinator signing nonces
+    NonceResponse(NonceResponse),
+    /// Tell signers to construct signature shares
+    SignatureShareRequest(SignatureShareRequest),
+    /// Tell coordinator signature shares
+    SignatureShareResponse(SignatureShareResponse),
+}
+
+impl Signable for Message {
+    fn hash(&self, hasher: &mut Sha256) {
+        match self {
+            Message::DkgBegin(msg) => msg.hash(hasher),
+            Message::DkgCommitment(msg) => msg.hash(hasher),
+            Message::DkgPublicShares(msg) => msg.hash(hasher),
+            Message::DkgPrivateShares(msg) => msg.hash(hasher),
+            Message::DkgEndBegin(msg) => msg.hash(hasher),
+            Message::DkgEnd(msg) => msg.hash(hasher),
+            Message::DkgDisclosure(msg) => msg.hash(hasher),
+            Message::NonceRequest(msg) => msg.hash(hasher),
+            Message::NonceResponse(msg) => msg.hash(hasher),
+            Message::SignatureShareRequest(msg) => msg.hash(hasher),
+            Message::SignatureShareResponse(msg) => msg.hash(hasher),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+/// DKG begin message from coordinator to signers
+pub struct DkgBegin {
+    /// DKG round ID
+    pub dkg_id: u64,
+}
+
+impl Signable for DkgBegin {
+    fn hash(&self, hasher: &mut Sha256) {
+        hasher.update("DKG_BEGIN".as_bytes());
+        hasher.update(self.dkg_id.to_be_bytes());
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+/// DKG commitment message from signer to all signers and coordinator, which binds the signer to the public shares it will reveal
+pub struct DkgCommitment {
+    /// DKG round ID
+    pub dkg_id: u64,
+    /// Signer ID
+    pub signer_id: u32,
+    /// The digest of the polynomial commitments for each of the signer's parties
+    pub digest: [u8; 32],
+}
+
+impl Signable for DkgCommitment {
+    fn hash(&self, hasher: &mut Sha256) {
+        hasher.update("DKG_COMMITMENT".as_bytes());
+        hasher.update(self.dkg_id.to_be_bytes());
+        hasher.update(self.signer_id.to_be_bytes());
+        hasher.update(self.digest);
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+/// DKG public shares message from signer to all signers and coordinator
+pub struct DkgPublicShares {
+    /// DKG round ID
+    pub dkg_id: u64,
+    /// Signer ID
+    pub signer_id: u32,
+    /// The polynomial commitments for each of the signer's parties, indexed by party ID
+    pub comms: HashMap<u32, PolyCommitment>,
+}
+
+impl Signable for DkgPublicShares {
+    fn hash(&self, hasher: &mut Sha256) {
+        hasher.update("DKG_PUBLIC_SHARES".as_bytes());
+        hasher.update(self.dkg_id.to_be_bytes());
+        hasher.update(self.signer_id.to_be_bytes());
+
+        // hash the commitments in a consistent order, since hashmap iteration order is random
+        let mut party_ids: Vec<&u32> = self.comms.keys().collect();
+        party_ids.sort();
+        for party_id in party_ids {
+            hasher.update(party_id.to_be_bytes());
+            hash_poly_commitment(&self.comms[party_id], hasher);
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+/// DKG private shares message from signer to all signers
+pub struct DkgPrivateShares {
+    /// DKG round ID
+    pub dkg_id: u64,
+    /// Signer ID
+    pub signer_id: u32,
+    /// The private shares, indexed by the sending party ID then the receiving key ID, each encrypted to the public key of the signer which owns the receiving key ID
+    pub shares: HashMap<u32, HashMap<u32, Vec<u8>>>,
+}
+
+impl Signable for DkgPrivateShares {
+    fn hash(&self, hasher: &mut Sha256) {
+        hasher.update("DKG_PRIVATE_SHARES".as_bytes());
+        hasher.update(self.dkg_id.to_be_bytes());
+        hasher.update(self.signer_id.to_be_bytes());
+
+        // hash the shares in a consistent order, since hashmap iteration order is random
+        let mut party_ids: Vec<&u32> = self.shares.keys().collect();
+        party_ids.sort();
+        for party_id in party_ids {
+            let party_shares = &self.shares[party_id];
+            let mut key_ids: Vec<&u32> = party_shares.keys().collect();
+            key_ids.sort();
+
+            hasher.update(party_id.to_be_bytes());
+            for key_id in key_ids {
+                hasher.update(key_id.to_be_bytes());
+                hasher.update(&party_shares[key_id]);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+/// DKG end begin message from coordinator to signers, which ends gathering early so that DKG finishes over the listed signers
+pub struct DkgEndBegin {
+    /// DKG round ID
+    pub dkg_id: u64,
+    /// The IDs of the signers which DKG finishes over
+    pub signer_ids: Vec<u32>,
+}
+
+impl Signable for DkgEndBegin {
+    fn hash(&self, hasher: &mut Sha256) {
+        hasher.update("DKG_END_BEGIN".as_bytes());
+        hasher.update(self.dkg_id.to_be_bytes());
+        for signer_id in &self.signer_ids {
+            hasher.update(signer_id.to_be_bytes());
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+/// DKG completion status
+pub enum DkgStatus {
+    /// DKG completed successfully
+    Success,
+    /// DKG failed
+    Failure(String),
+    /// The listed private shares failed to decrypt or verify
+    BadShares(Vec<DkgComplaint>),
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+/// DKG end message from signers to coordinator
+pub struct DkgEnd {
+    /// DKG round ID
+    pub dkg_id: u64,
+    /// Signer ID
+    pub signer_id: u32,
+    /// DKG status for this signer
+    pub status: DkgStatus,
+}
+
+impl Signable for DkgEnd {
+    fn hash(&self, hasher: &mut Sha256) {
+        hasher.update("DKG_END".as_bytes());
+        hasher.update(self.dkg_id.to_be_bytes());
+        hasher.update(self.signer_id.to_be_bytes());
+        match &self.status {
+            DkgStatus::Success => hasher.update("SUCCESS".as_bytes()),
+            DkgStatus::Failure(reason) => {
+                hasher.update("FAILURE".as_bytes());
+                hasher.update(reason.as_bytes());
+            }
+            DkgStatus::BadShares(complaints) => {
+                hasher.update("BAD_SHARES".as_bytes());
+                for complaint in complaints {
+                    hasher.update(complaint.party_id.to_be_bytes());
+                    hasher.update(complaint.key_id.to_be_bytes());
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+/// DKG disclosure message from an accused signer to all signers and coordinator
+pub struct DkgDisclosure {
+    /// DKG round ID
+    pub dkg_id: u64,
+    /// Signer ID
+    pub signer_id: u32,
+    /// The private shares which were complained about, in plain text
+    pub shares: Vec<DisclosedShare>,
+}
+
+impl Signable for DkgDisclosure {
+    fn hash(&self, hasher: &mut Sha256) {
+        hasher.update("DKG_DISCLOSURE".as_bytes());
+        hasher.update(self.dkg_id.to_be_bytes());
+        hasher.update(self.signer_id.to_be_bytes());
+        for disclosed_share in &self.shares {
+            hasher.update(disclosed_share.party_id.to_be_bytes());
+            hasher.update(disclosed_share.key_id.to_be_bytes());
+            hasher.update(disclosed_share.share.to_bytes());
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+/// Nonce request message from coordinator to signers
+pub struct NonceRequest {
+    /// DKG round ID
+    pub dkg_id: u64,
+    /// Signing round ID
+    pub sign_id: u64,
+    /// Signing round iteration ID
+    pub sign_iter_id: u64,
+}
+
+impl Signable for NonceRequest {
+    fn hash(&self, hasher: &mut Sha256) {
+        hasher.update("NONCE_REQUEST".as_bytes());
+        hasher.update(self.dkg_id.to_be_bytes());
+        hasher.update(self.sign_id.to_be_bytes());
+        hasher.update(self.sign_iter_id.to_be_bytes());
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+/// Nonce response message from signers to coordinator
+pub struct NonceResponse {
+    /// DKG round ID
+    pub dkg_id: u64,
+    /// Signing round ID
+    pub sign_id: u64,
+    /// Signing round iteration ID
+    pub sign_iter_id: u64,
+    /// Signer ID
+    pub signer_id: u32,
+    /// Key IDs
+    pub key_ids: Vec<u32>,
+    /// Public nonces
+    pub nonces: Vec<PublicNonce>,
+}
+
+impl Signable for NonceResponse {
+    fn hash(&self, hasher: &mut Sha256) {
+        hasher.update("NONCE_RESPONSE".as_bytes());
+        hasher.update(self.dkg_id.to_be_bytes());
+        hasher.update(self.sign_id.to_be_bytes());
+        hasher.update(self.sign_iter_id.to_be_bytes());
+        hasher.update(self.signer_id.to_be_bytes());
+        for key_id in &self.key_ids {
+            hasher.update(key_id.to_be_bytes());
+        }
+        for nonce in &self.nonces {
+            hash_public_nonce(nonce, hasher);
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+/// Signature share request message from coordinator to signers
+pub struct SignatureShareRequest {
+    /// DKG round ID
+    pub dkg_id: u64,
+    /// Signing round ID
+    pub sign_id: u64,
+    /// Signing round iteration ID
+    pub sign_iter_id: u64,
+    /// Nonces responses used for this signature
+    pub nonce_responses: Vec<NonceResponse>,
+    /// Bytes to sign
+    pub message: Vec<u8>,
+}
+
+impl Signable for SignatureShareRequest {
+    fn hash(&self, hasher: &mut Sha256) {
+        hasher.update("SIGNATURE_SHARE_REQUEST".as_bytes());
+        hasher.update(self.dkg_id.to_be_bytes());
+        hasher.update(self.sign_id.to_be_bytes());
+        hasher.update(self.sign_iter_id.to_be_bytes());
+        for nonce_response in &self.nonce_responses {
+            nonce_response.hash(hasher);
+        }
+        hasher.update(self.message.as_slice());
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+/// Signature share response message from signers to coordinator
+pub struct SignatureShareResponse {
+    /// DKG round ID
+    pub dkg_id: u64,
+    /// Signing round ID
+    pub sign_id: u64,
+    /// Signing round iteration ID
+    pub sign_iter_id: u64,
+    /// Signer ID
+    pub signer_id: u32,
+    /// Signature shares from this Signer
+    pub signature_shares: Vec<SignatureShare>,
+}
+
+impl Signable for SignatureShareResponse {
+    fn hash(&self, hasher: &mut Sha256) {
+        hasher.update("SIGNATURE_SHARE_RESPONSE".as_bytes());
+        hasher.update(self.dkg_id.to_be_bytes());
+        hasher.update(self.sign_id.to_be_bytes());
+        hasher.update(self.sign_iter_id.to_be_bytes());
+        hasher.update(self.signer_id.to_be_bytes());
+        for signature_share in &self.signature_shares {
+            hasher.update(signature_share.id.to_be_bytes());
+            hasher.update(signature_share.z_i.to_bytes());
+            for key_id in &signature_share.key_ids {
+                hasher.update(key_id.to_be_bytes());
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+/// Network packets need to be signed so they can be verified
+pub struct Packet {
+    /// The message to sign
+    pub msg: Message,
+    /// The bytes of the signature
+    pub sig: Vec<u8>,
+}
+
+impl Packet {
+    /// Sign `msg` with `private_key` and wrap it in a packet
+    pub fn new(msg: Message, private_key: &Scalar) -> Result<Self, ecdsa::Error> {
+        let sig = msg.sign(private_key)?;
+
+        Ok(Self { msg, sig })
+    }
+
+    /// Verify that the packet was signed by the configured public key for its sender,
+    /// and that any key IDs it claims, or party IDs it deals from as assigned by `party_ids`, belong to that sender
+    pub fn verify(&self, public_keys: &PublicKeys, party_ids: PartyIds) -> bool {
+        match &self.msg {
+            Message::DkgBegin(_)
+            | Message::DkgEndBegin(_)
+            | Message::NonceRequest(_)
+            | Message::SignatureShareRequest(_) => {
+                self.msg.verify(&self.sig, &public_keys.coordinator)
+            }
+            Message::DkgCommitment(msg) => self.verify_signer(msg.signer_id, &[], public_keys),
+            Message::DkgPublicShares(msg) => {
+                self.verify_dealer(msg.signer_id, msg.comms.keys(), public_keys, party_ids)
+            }
+            Message::DkgPrivateShares(msg) => {
+                self.verify_dealer(msg.signer_id, msg.shares.keys(), public_keys, party_ids)
+            }
+            Message::DkgEnd(msg) => {
+                // signers can only complain about shares sent to their own keys
+                let key_ids: Vec<u32> = match &msg.status {
+                    DkgStatus::BadShares(complaints) => complaints
+                        .iter()
+                        .map(|complaint| complaint.key_id)
+                        .collect(),
+                    _ => Vec::new(),
+                };
+                self.verify_signer(msg.signer_id, &key_ids, public_keys)
+            }
+            Message::DkgDisclosure(msg) => {
+                let dealt = msg.shares.iter().map(|share| &share.party_id);
+                self.verify_dealer(msg.signer_id, dealt, public_keys, party_ids)
+            }
+            Message::NonceResponse(msg) => {
+                self.verify_signer(msg.signer_id, &msg.key_ids, public_keys)
+            }
+            Message::SignatureShareResponse(msg) => {
+                let key_ids: Vec<u32> = msg
+                    .signature_shares
+                    .iter()
+                    .flat_map(|share| share.key_ids.clone())
+                    .collect();
+                self.verify_signer(msg.signer_id, &key_ids, public_keys)
+            }
+        }
+    }
+
+    fn verify_signer(&self, signer_id: u32, key_ids: &[u32], public_keys: &PublicKeys) -> bool {
+        let public_key = match public_keys.signers.get(&signer_id) {
+            Some(public_key) => public_key,
+            None => return false,
+        };
+
+        for key_id in key_ids {
+            if public_keys.key_ids.get(key_id) != Some(public_key) {
+                return false;
+            }
+        }
+
+        self.msg.verify(&self.sig, public_key)
+    }
+
+    fn verify_dealer<'a>(
+        &self,
+        signer_id: u32,
+        mut dealt: impl Iterator<Item = &'a u32>,
+        public_keys: &PublicKeys,
+        party_ids: PartyIds,
+    ) -> bool {
+        dealt.all(|party_id| public_keys.owns_party(signer_id, *party_id, party_ids))
+            && self.verify_signer(signer_id, &[], public_keys)
+    }
+}
@@ -0,0 +1,271 @@
+use hashbrown::HashMap;
+use p256k1::scalar::Scalar;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::common::{Nonce, SessionId};
+use crate::errors::NonceStoreError;
+use crate::traits::NonceStore;
+
+const ISSUED: &str = "issued";
+const CONSUMED: &str = "consumed";
+
+/// The state of the nonce issued to a party for a session; `None` once it has been consumed
+type NonceEntries = HashMap<(u32, SessionId), Option<Nonce>>;
+
+/// A nonce store backed by an append-only log file, which is synced to disk before each call returns
+///
+/// Each line records a nonce as issued or consumed. A line which was torn by a crash while it was being appended is dropped when the store is opened, which is safe because the call which wrote it never returned. Consumed nonces are remembered forever, so a restarted party refuses to sign with them again.
+pub struct FileNonceStore {
+    path: PathBuf,
+    file: File,
+    entries: NonceEntries,
+}
+
+impl FileNonceStore {
+    /// Open the store at `path`, creating it if it does not exist, and replay its log
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, NonceStoreError> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&path)?;
+
+        let mut log = String::new();
+        file.read_to_string(&mut log)?;
+
+        // a trailing line without a newline was torn by a crash before it was synced
+        let complete = log.rfind('\n').map_or(0, |i| i + 1);
+        if complete < log.len() {
+            file.set_len(complete as u64)?;
+            file.sync_all()?;
+        }
+
+        let mut entries = NonceEntries::new();
+        for (i, line) in log[..complete].lines().enumerate() {
+            replay(&mut entries, line).ok_or(NonceStoreError::Corrupt(i + 1))?;
+        }
+
+        Ok(Self {
+            path,
+            file,
+            entries,
+        })
+    }
+
+    /// Check if a nonce has been issued to `party_id` for `session_id` and not yet consumed
+    pub fn is_pending(&self, party_id: u32, session_id: SessionId) -> bool {
+        matches!(self.entries.get(&(party_id, session_id)), Some(Some(_)))
+    }
+
+    /// Check if the nonce issued to `party_id` for `session_id` has been consumed
+    pub fn is_consumed(&self, party_id: u32, session_id: SessionId) -> bool {
+        matches!(self.entries.get(&(party_id, session_id)), Some(None))
+    }
+
+    /// Rewrite the log without the private nonces which have been consumed, atomically replacing the old log
+    pub fn compact(&mut self) -> Result<(), NonceStoreError> {
+        let mut tmp_name = self.path.as_os_str().to_owned();
+        tmp_name.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_name);
+
+        let mut keys: Vec<&(u32, SessionId)> = self.entries.keys().collect();
+        keys.sort();
+
+        let mut log = String::new();
+        for key in keys {
+            log.push_str(&record(key.0, key.1, self.entries[key].as_ref()));
+        }
+
+        let mut tmp = File::create(&tmp_path)?;
+        tmp.write_all(log.as_bytes())?;
+        tmp.sync_all()?;
+        fs::rename(&tmp_path, &self.path)?;
+        sync_dir(&self.path)?;
+
+        self.file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .open(&self.path)?;
+
+        Ok(())
+    }
+
+    /// Append `line` to the log and sync it to disk
+    fn append(&mut self, line: &str) -> Result<(), NonceStoreError> {
+        self.file.write_all(line.as_bytes())?;
+        self.file.sync_data()?;
+
+        Ok(())
+    }
+}
+
+impl NonceStore for FileNonceStore {
+    fn issue(
+        &mut self,
+        party_id: u32,
+        session_id: SessionId,
+        nonce: &Nonce,
+    ) -> Result<(), NonceStoreError> {
+        if self.entries.contains_key(&(party_id, session_id)) {
+            return Err(NonceStoreError::AlreadyIssued(party_id, session_id));
+        }
+
+        self.append(&record(party_id, session_id, Some(nonce)))?;
+        self.entries
+            .insert((party_id, session_id), Some(nonce.clone()));
+
+        Ok(())
+    }
+
+    fn consume(&mut self, party_id: u32, session_id: SessionId) -> Result<Nonce, NonceStoreError> {
+        let nonce = match self.entries.get(&(party_id, session_id)) {
+            None => return Err(NonceStoreError::NotIssued(party_id, session_id)),
+            Some(None) => return Err(NonceStoreError::Consumed(party_id, session_id)),
+            Some(Some(nonce)) => nonce.clone(),
+        };
+
+        // the consumption must be durable before any signature share made with the nonce exists
+        self.append(&record(party_id, session_id, None))?;
+        self.entries.insert((party_id, session_id), None);
+
+        Ok(nonce)
+    }
+}
+
+/// Format a log line which records `nonce` as issued, or the nonce as consumed if it is `None`
+fn record(party_id: u32, session_id: SessionId, nonce: Option<&Nonce>) -> String {
+    match nonce {
+        Some(nonce) => format!(
+            "{} {} {} {} {}\n",
+            ISSUED,
+            party_id,
+            session_id,
+            hex::encode(nonce.d.to_bytes()),
+            hex::encode(nonce.e.to_bytes())
+        ),
+        None => format!("{} {} {}\n", CONSUMED, party_id, session_id),
+    }
+}
+
+/// Apply one log line to `entries`, returning `None` if it is malformed or contradicts an earlier line
+fn replay(entries: &mut NonceEntries, line: &str) -> Option<()> {
+    let fields: Vec<&str> = line.split(' ').collect();
+    let key = (
+        fields.get(1)?.parse::<u32>().ok()?,
+        fields.get(2)?.parse::<SessionId>().ok()?,
+    );
+
+    match (fields[0], fields.len()) {
+        (ISSUED, 5) => {
+            if entries.contains_key(&key) {
+                return None;
+            }
+            let nonce = Nonce {
+                d: parse_scalar(fields[3])?,
+                e: parse_scalar(fields[4])?,
+            };
+            entries.insert(key, Some(nonce));
+        }
+        // a compacted log keeps only the consumed marker, without the nonce
+        (CONSUMED, 3) => {
+            if let Some(None) = entries.get(&key) {
+                return None;
+            }
+            entries.insert(key, None);
+        }
+        _ => return None,
+    }
+
+    Some(())
+}
+
+/// Parse a hex encoded scalar
+fn parse_scalar(s: &str) -> Option<Scalar> {
+    Scalar::try_from(hex::decode(s).ok()?.as_slice()).ok()
+}
+
+#[cfg(unix)]
+/// Sync the directory which contains `path`, so that a rename into it is durable
+fn sync_dir(path: &Path) -> Result<(), NonceStoreError> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => File::open(dir)?.sync_all()?,
+        _ => File::open(".")?.sync_all()?,
+    }
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+/// Directories cannot be synced on this platform, so a rename is as durable as the filesystem makes it
+fn sync_dir(_path: &Path) -> Result<(), NonceStoreError> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FileNonceStore;
+    use crate::common::Nonce;
+    use crate::errors::NonceStoreError;
+    use crate::traits::NonceStore;
+
+    use rand_core::OsRng;
+    use std::{fs::OpenOptions, io::Write};
+
+    #[test]
+    fn file_store_survives_restart() {
+        let mut rng = OsRng::default();
+        let dir = tempfile::tempdir().expect("failed to make temp dir");
+        let path = dir.path().join("nonces");
+
+        let nonces = [Nonce::random(&mut rng), Nonce::random(&mut rng)];
+        {
+            let mut store = FileNonceStore::open(&path).expect("failed to open store");
+            store.issue(1, 7, &nonces[0]).expect("failed to issue");
+            store.issue(2, 7, &nonces[1]).expect("failed to issue");
+            assert!(matches!(
+                store.issue(1, 7, &nonces[1]),
+                Err(NonceStoreError::AlreadyIssued(1, 7))
+            ));
+            assert!(store.consume(1, 7).expect("failed to consume") == nonces[0]);
+        }
+
+        // simulate a crash while a record was being appended
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"consumed 2").unwrap();
+        drop(file);
+
+        let mut store = FileNonceStore::open(&path).expect("failed to reopen store");
+        assert!(store.is_consumed(1, 7));
+        assert!(store.is_pending(2, 7));
+        assert!(matches!(
+            store.consume(1, 7),
+            Err(NonceStoreError::Consumed(1, 7))
+        ));
+        assert!(matches!(
+            store.consume(3, 7),
+            Err(NonceStoreError::NotIssued(3, 7))
+        ));
+
+        // compaction drops consumed nonces but still refuses to reuse them
+        store.compact().expect("failed to compact");
+        let log = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(log.lines().count(), 2);
+        let mut store = FileNonceStore::open(&path).expect("failed to reopen store");
+        assert!(store.is_consumed(1, 7));
+        assert!(store.consume(2, 7).expect("failed to consume") == nonces[1]);
+
+        // a damaged record which is not the last one is never silently dropped
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"issued garbage\nconsumed 2 7\n").unwrap();
+        drop(file);
+        assert!(matches!(
+            FileNonceStore::open(&path),
+            Err(NonceStoreError::Corrupt(4))
+        ));
+    }
+}
@@ -0,0 +1,128 @@
+use hashbrown::HashMap;
+use num_traits::Zero;
+use p256k1::{
+    point::{Point, G},
+    scalar::Scalar,
+};
+use rand_core::{CryptoRng, RngCore};
+
+use crate::common::{PolyCommitment, SecretPoly};
+use crate::compute;
+use crate::errors::DkgError;
+use crate::schnorr::{DkgContext, ID};
+
+/// The reshare commitments and sub-shares dealt by each old key, indexed by old key ID and then, for the sub-shares, new key ID
+pub type Reshares = (
+    HashMap<u32, PolyCommitment>,
+    HashMap<u32, HashMap<u32, Scalar>>,
+);
+
+/// Deal sub-shares of the private key for `key_id` to the new keys `key_ids` of the resharing round `ctx`, any threshold of which can reconstruct it
+pub fn deal<RNG: RngCore + CryptoRng>(
+    key_id: u32,
+    private_key: &Scalar,
+    ctx: &DkgContext,
+    key_ids: &[u32],
+    rng: &mut RNG,
+) -> (PolyCommitment, HashMap<u32, Scalar>) {
+    let params: Vec<Scalar> = (0..ctx.threshold)
+        .map(|i| {
+            if i == 0 {
+                *private_key
+            } else {
+                Scalar::random(rng)
+            }
+        })
+        .collect();
+    let f = SecretPoly::new(params);
+
+    let comm = PolyCommitment {
+        id: ID::new(&compute::id(key_id), private_key, ctx, rng),
+        A: f.data().iter().map(|a| a * G).collect(),
+    };
+
+    let mut shares = HashMap::new();
+    for i in key_ids {
+        shares.insert(*i, f.eval(compute::id(*i)));
+    }
+
+    (comm, shares)
+}
+
+#[allow(non_snake_case)]
+/// Verify the resharing commitments `A` from the resharing round `ctx`, indexed by old key ID, against the old group polynomial, returning the new group polynomial
+pub fn group_poly(
+    A: &HashMap<u32, PolyCommitment>,
+    old_poly: &[Point],
+    ctx: &DkgContext,
+) -> Result<Vec<Point>, DkgError> {
+    if A.len() < old_poly.len() {
+        return Err(DkgError::NotEnoughDealers(A.len(), old_poly.len()));
+    }
+
+    let len = usize::try_from(ctx.threshold).unwrap();
+    let mut bad_ids = Vec::new();
+    for (key_id, A_i) in A {
+        // each dealt polynomial must hide the dealer's existing public key share
+        let public_key = compute::poly(&compute::id(*key_id), &old_poly.to_vec())?;
+        if A_i.id.id != compute::id(*key_id)
+            || A_i.A.len() != len
+            || A_i.A[0] != public_key
+            || !A_i.verify(ctx)
+        {
+            bad_ids.push(*key_id);
+        }
+    }
+    if !bad_ids.is_empty() {
+        bad_ids.sort();
+        return Err(DkgError::BadReshareCommitments(bad_ids));
+    }
+
+    let dealers: Vec<u32> = A.keys().cloned().collect();
+    let mut poly = vec![Point::zero(); len];
+    for (key_id, A_i) in A {
+        let lambda = compute::lambda(*key_id, &dealers);
+        for (p, a) in poly.iter_mut().zip(&A_i.A) {
+            *p += lambda * a;
+        }
+    }
+
+    Ok(poly)
+}
+
+#[allow(non_snake_case)]
+/// Verify the sub-shares for `key_id`, indexed by old key ID, against the resharing commitments `A`, and combine them into the new private key
+pub fn combine_shares(
+    key_id: u32,
+    shares: &HashMap<u32, Scalar>,
+    A: &HashMap<u32, PolyCommitment>,
+) -> Result<Scalar, DkgError> {
+    let mut missing_shares: Vec<u32> = A
+        .keys()
+        .filter(|dealer| !shares.contains_key(*dealer))
+        .cloned()
+        .collect();
+    if !missing_shares.is_empty() {
+        missing_shares.sort();
+        return Err(DkgError::MissingShares(missing_shares));
+    }
+
+    let mut bad_shares = Vec::new();
+    for (dealer, A_i) in A {
+        if shares[dealer] * G != compute::poly(&compute::id(key_id), &A_i.A)? {
+            bad_shares.push(*dealer);
+        }
+    }
+    if !bad_shares.is_empty() {
+        bad_shares.sort();
+        return Err(DkgError::BadShares(bad_shares));
+    }
+
+    let dealers: Vec<u32> = A.keys().cloned().collect();
+    let mut private_key = Scalar::zero();
+    for dealer in &dealers {
+        private_key += compute::lambda(*dealer, &dealers) * shares[dealer];
+    }
+
+    Ok(private_key)
+}
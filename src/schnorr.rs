@@ -6,8 +6,36 @@ use rand_core::{CryptoRng, RngCore};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
+use crate::common::ProtocolVersion;
 use crate::util::hash_to_scalar;
 
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash, Deserialize, Serialize)]
+/// The context of a DKG round, which proofs of possession commit to so they cannot be replayed in another round
+///
+/// Under `ProtocolVersion::V0` the context is ignored, so proofs verify as they did before contexts existed
+pub struct DkgContext {
+    /// The protocol version of the group
+    pub version: ProtocolVersion,
+    /// The ID of the DKG round, which must be unique for each group and round
+    pub dkg_id: u64,
+    /// The total number of keys
+    pub num_keys: u32,
+    /// The threshold of keys needed to sign
+    pub threshold: u32,
+}
+
+impl DkgContext {
+    /// Construct a DKG context with the passed parameters
+    pub fn new(version: ProtocolVersion, dkg_id: u64, num_keys: u32, threshold: u32) -> Self {
+        Self {
+            version,
+            dkg_id,
+            num_keys,
+            threshold,
+        }
+    }
+}
+
 #[allow(non_snake_case)]
 #[derive(Clone, Debug, Deserialize, Serialize)]
 /// ID type which encapsulates the ID and a schnorr proof of ownership of the polynomial
@@ -22,10 +50,15 @@ pub struct ID {
 
 #[allow(non_snake_case)]
 impl ID {
-    /// Construct a new schnorr ID which binds the passed `Scalar` `id` and `Scalar` `a`, with a zero-knowledge proof of ownership of `a`
-    pub fn new<RNG: RngCore + CryptoRng>(id: &Scalar, a: &Scalar, rng: &mut RNG) -> Self {
+    /// Construct a new schnorr ID which binds the passed `Scalar` `id` and `Scalar` `a`, with a zero-knowledge proof of ownership of `a` in the DKG round `ctx`
+    pub fn new<RNG: RngCore + CryptoRng>(
+        id: &Scalar,
+        a: &Scalar,
+        ctx: &DkgContext,
+        rng: &mut RNG,
+    ) -> Self {
         let k = Scalar::random(rng);
-        let c = Self::challenge(id, &(&k * &G), &(a * &G));
+        let c = Self::challenge(id, &(&k * &G), &(a * &G), ctx);
 
         Self {
             id: *id,
@@ -34,10 +67,16 @@ impl ID {
         }
     }
 
-    /// Compute the schnorr challenge
-    pub fn challenge(id: &Scalar, K: &Point, A: &Point) -> Scalar {
+    /// Compute the schnorr challenge, committing to everything in `ctx` which its version requires
+    pub fn challenge(id: &Scalar, K: &Point, A: &Point, ctx: &DkgContext) -> Scalar {
         let mut hasher = Sha256::new();
 
+        if ctx.version == ProtocolVersion::V1 {
+            hasher.update("WSTS/dkg/pop/v1".as_bytes());
+            hasher.update(ctx.dkg_id.to_be_bytes());
+            hasher.update(ctx.num_keys.to_be_bytes());
+            hasher.update(ctx.threshold.to_be_bytes());
+        }
         hasher.update(id.to_bytes());
         hasher.update(K.compress().as_bytes());
         hasher.update(A.compress().as_bytes());
@@ -45,9 +84,9 @@ impl ID {
         hash_to_scalar(&mut hasher)
     }
 
-    /// Verify the proof
-    pub fn verify(&self, A: &Point) -> bool {
-        let c = Self::challenge(&self.id, &self.kG, A);
+    /// Verify the proof was made in the DKG round `ctx`
+    pub fn verify(&self, A: &Point, ctx: &DkgContext) -> bool {
+        let c = Self::challenge(&self.id, &self.kG, A, ctx);
         &self.kca * &G == &self.kG + c * A
     }
 }
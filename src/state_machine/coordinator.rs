@@ -0,0 +1,1146 @@
+use std::collections::BTreeMap;
+
+use hashbrown::HashMap;
+use num_traits::Zero;
+use p256k1::scalar::Scalar;
+
+use crate::{
+    common::{DkgMode, PolyCommitment, ProtocolVersion, PublicNonce, SignatureShare, Verdict},
+    compute,
+    errors::AggregatorError,
+    net::{
+        DkgBegin, DkgCommitment, DkgDisclosure, DkgEnd, DkgEndBegin, DkgPublicShares, DkgStatus,
+        Message, NonceRequest, NonceResponse, Packet, SignatureShareRequest,
+        SignatureShareResponse,
+    },
+    schnorr::DkgContext,
+    state_machine::{
+        check_first_message, collect_complaints, find_bad_reveals, judge, merge_poly_commitments,
+        Error, OperationResult, PublicKeys, StateMachine,
+    },
+    traits::Aggregator,
+    Point,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Coordinator states
+pub enum State {
+    /// The coordinator is idle
+    Idle,
+    /// The coordinator is gathering DKG public shares and DKG end messages
+    DkgGather,
+    /// The coordinator is gathering disclosures from signers which were complained about
+    DkgDisclosureGather,
+    /// The coordinator is gathering nonces
+    NonceGather,
+    /// The coordinator is gathering signature shares
+    SigShareGather,
+}
+
+/// A state machine which coordinates DKG and signing rounds among a set of signers
+pub struct Coordinator<Aggregator> {
+    /// The current DKG round ID
+    pub current_dkg_id: u64,
+    /// The current signing round ID
+    pub current_sign_id: u64,
+    /// The current signing round iteration ID
+    pub current_sign_iter_id: u64,
+    /// The total number of signers
+    pub total_signers: u32,
+    /// The total number of keys
+    pub total_keys: u32,
+    /// The threshold of keys needed to construct a valid signature
+    pub threshold: u32,
+    /// The current state
+    pub state: State,
+    /// The aggregate group public key
+    pub aggregate_public_key: Point,
+    /// The long-term public keys of the coordinator and signers
+    pub public_keys: PublicKeys,
+    /// The protocol version of the group, which every signer must agree on
+    pub protocol_version: ProtocolVersion,
+    /// How DKG public shares are published, which every signer must agree on
+    pub dkg_mode: DkgMode,
+    /// The verdict of the last DKG complaint round, if there were any complaints
+    pub verdict: Option<Verdict>,
+    message_private_key: Scalar,
+    dkg_signer_ids: Option<Vec<u32>>,
+    dkg_digests: HashMap<u32, [u8; 32]>,
+    dkg_public_shares: BTreeMap<u32, DkgPublicShares>,
+    dkg_end_messages: BTreeMap<u32, DkgEnd>,
+    dkg_disclosures: HashMap<u32, DkgDisclosure>,
+    public_nonces: BTreeMap<u32, NonceResponse>,
+    signature_shares: BTreeMap<u32, Vec<SignatureShare>>,
+    message: Vec<u8>,
+    aggregator: Option<Aggregator>,
+}
+
+impl<A: Aggregator> Coordinator<A> {
+    /// Construct a Coordinator for a group with the passed parameters, which signs packets with `message_private_key`
+    pub fn new(
+        total_signers: u32,
+        total_keys: u32,
+        threshold: u32,
+        message_private_key: Scalar,
+        public_keys: PublicKeys,
+    ) -> Self {
+        Self {
+            current_dkg_id: 0,
+            current_sign_id: 0,
+            current_sign_iter_id: 0,
+            total_signers,
+            total_keys,
+            threshold,
+            state: State::Idle,
+            aggregate_public_key: Point::zero(),
+            public_keys,
+            protocol_version: ProtocolVersion::default(),
+            dkg_mode: DkgMode::default(),
+            verdict: None,
+            message_private_key,
+            dkg_signer_ids: None,
+            dkg_digests: HashMap::new(),
+            dkg_public_shares: BTreeMap::new(),
+            dkg_end_messages: BTreeMap::new(),
+            dkg_disclosures: HashMap::new(),
+            public_nonces: BTreeMap::new(),
+            signature_shares: BTreeMap::new(),
+            message: Vec::new(),
+            aggregator: None,
+        }
+    }
+
+    /// Start a new DKG round, returning the packet to broadcast to all signers
+    pub fn start_dkg_round(&mut self) -> Result<Packet, Error> {
+        self.move_to(State::DkgGather)?;
+        self.current_dkg_id = self.current_dkg_id.wrapping_add(1);
+        self.dkg_signer_ids = None;
+        self.verdict = None;
+        self.dkg_digests.clear();
+        self.dkg_public_shares.clear();
+        self.dkg_end_messages.clear();
+        self.dkg_disclosures.clear();
+        self.aggregator = None;
+
+        let dkg_begin = Message::DkgBegin(DkgBegin {
+            dkg_id: self.current_dkg_id,
+        });
+
+        Ok(Packet::new(dkg_begin, &self.message_private_key)?)
+    }
+
+    /// Stop waiting for absent signers, for example after a timeout, and finish DKG over the signers which have responded, returning the packet to broadcast to all signers
+    ///
+    /// The responders are the signers which have sent public shares, or under `DkgMode::CommitReveal` those which have committed if none has revealed yet
+    pub fn end_dkg_gather(&mut self) -> Result<Packet, Error> {
+        let mut signer_ids: Vec<u32> = if self.dkg_public_shares.is_empty() {
+            self.dkg_digests.keys().cloned().collect()
+        } else {
+            self.dkg_public_shares.keys().cloned().collect()
+        };
+        signer_ids.sort();
+
+        self.end_dkg_gather_with(&signer_ids)
+    }
+
+    /// Finish DKG over the agreed `signer_ids` without waiting for any other signer, returning the packet to broadcast to all signers
+    ///
+    /// This must happen before any signer has finished gathering, since signers which have already done so finish DKG over every signer
+    pub fn end_dkg_gather_with(&mut self, signer_ids: &[u32]) -> Result<Packet, Error> {
+        if self.state != State::DkgGather || !self.dkg_end_messages.is_empty() {
+            return Err(Error::BadStateChange(
+                "cannot end DKG gathering once signers have finished it".to_string(),
+            ));
+        }
+
+        let mut signer_ids = signer_ids.to_vec();
+        signer_ids.sort();
+        signer_ids.dedup();
+        let num_keys: usize = signer_ids
+            .iter()
+            .map(|signer_id| self.public_keys.signer_key_ids(*signer_id).len())
+            .sum();
+        let threshold = usize::try_from(self.threshold).unwrap();
+        if num_keys < threshold {
+            return Err(AggregatorError::NotEnoughDealers(num_keys, threshold).into());
+        }
+
+        self.dkg_digests.retain(|id, _| signer_ids.contains(id));
+        self.dkg_public_shares
+            .retain(|id, _| signer_ids.contains(id));
+        self.dkg_disclosures.retain(|id, _| signer_ids.contains(id));
+        self.dkg_signer_ids = Some(signer_ids.clone());
+
+        let dkg_end_begin = Message::DkgEndBegin(DkgEndBegin {
+            dkg_id: self.current_dkg_id,
+            signer_ids,
+        });
+
+        Ok(Packet::new(dkg_end_begin, &self.message_private_key)?)
+    }
+
+    /// Check whether `signer_id` takes part in the current DKG round, which is every signer unless gathering ended early
+    fn in_dkg(&self, signer_id: u32) -> bool {
+        match &self.dkg_signer_ids {
+            Some(signer_ids) => signer_ids.contains(&signer_id),
+            None => true,
+        }
+    }
+
+    /// Get the number of signers which the current DKG round is waiting on
+    fn dkg_signers(&self) -> usize {
+        match &self.dkg_signer_ids {
+            Some(signer_ids) => signer_ids.len(),
+            None => usize::try_from(self.total_signers).unwrap(),
+        }
+    }
+
+    /// Start a new signing round for `message`, returning the packet to broadcast to all signers
+    pub fn start_signing_round(&mut self, message: &[u8]) -> Result<Packet, Error> {
+        if self.aggregator.is_none() {
+            return Err(Error::BadStateChange(
+                "cannot sign before DKG completes".to_string(),
+            ));
+        }
+        self.move_to(State::NonceGather)?;
+        self.current_sign_id = self.current_sign_id.wrapping_add(1);
+        self.current_sign_iter_id = self.current_sign_iter_id.wrapping_add(1);
+        self.public_nonces.clear();
+        self.signature_shares.clear();
+        self.message = message.to_vec();
+
+        let nonce_request = Message::NonceRequest(NonceRequest {
+            dkg_id: self.current_dkg_id,
+            sign_id: self.current_sign_id,
+            sign_iter_id: self.current_sign_iter_id,
+        });
+
+        Ok(Packet::new(nonce_request, &self.message_private_key)?)
+    }
+
+    /// Verify and process an inbound packet, returning an optional signed outbound packet and an optional operation result
+    pub fn process(
+        &mut self,
+        packet: &Packet,
+    ) -> Result<(Option<Packet>, Option<OperationResult>), Error> {
+        if !packet.verify(&self.public_keys, A::PARTY_IDS) {
+            return Err(Error::InvalidPacketSignature);
+        }
+
+        let (message, result) = self.process_message(&packet.msg)?;
+        let packet = match message {
+            Some(message) => Some(Packet::new(message, &self.message_private_key)?),
+            None => None,
+        };
+
+        Ok((packet, result))
+    }
+
+    fn process_message(
+        &mut self,
+        message: &Message,
+    ) -> Result<(Option<Message>, Option<OperationResult>), Error> {
+        match message {
+            Message::DkgCommitment(dkg_commitment) => self.gather_dkg_commitment(dkg_commitment),
+            Message::DkgPublicShares(dkg_public_shares) => {
+                self.gather_dkg_public_shares(dkg_public_shares)
+            }
+            Message::DkgEnd(dkg_end) => self.gather_dkg_end(dkg_end),
+            Message::DkgDisclosure(dkg_disclosure) => self.gather_dkg_disclosure(dkg_disclosure),
+            Message::NonceResponse(nonce_response) => self.gather_nonces(nonce_response),
+            Message::SignatureShareResponse(sig_share_response) => {
+                self.gather_sig_shares(sig_share_response)
+            }
+            _ => Ok((None, None)),
+        }
+    }
+
+    fn gather_dkg_commitment(
+        &mut self,
+        dkg_commitment: &DkgCommitment,
+    ) -> Result<(Option<Message>, Option<OperationResult>), Error> {
+        if self.state != State::DkgGather
+            || self.dkg_mode != DkgMode::CommitReveal
+            || dkg_commitment.dkg_id != self.current_dkg_id
+            || !self.in_dkg(dkg_commitment.signer_id)
+        {
+            return Ok((None, None));
+        }
+
+        let signer_id = dkg_commitment.signer_id;
+        let first = self.dkg_digests.get(&signer_id).copied();
+        if !check_first_message(first, signer_id, dkg_commitment.digest)? {
+            return Ok((None, None));
+        }
+
+        self.dkg_digests.insert(signer_id, dkg_commitment.digest);
+
+        self.try_dkg_end()
+    }
+
+    fn gather_dkg_public_shares(
+        &mut self,
+        dkg_public_shares: &DkgPublicShares,
+    ) -> Result<(Option<Message>, Option<OperationResult>), Error> {
+        if self.state != State::DkgGather
+            || dkg_public_shares.dkg_id != self.current_dkg_id
+            || !self.in_dkg(dkg_public_shares.signer_id)
+        {
+            return Ok((None, None));
+        }
+
+        let signer_id = dkg_public_shares.signer_id;
+        let digest =
+            |comms| compute::poly_commitments_digest(self.current_dkg_id, signer_id, comms);
+        let first = self
+            .dkg_public_shares
+            .get(&signer_id)
+            .map(|first| digest(&first.comms));
+        if !check_first_message(first, signer_id, digest(&dkg_public_shares.comms))? {
+            return Ok((None, None));
+        }
+
+        self.dkg_public_shares
+            .insert(signer_id, dkg_public_shares.clone());
+
+        self.try_dkg_end()
+    }
+
+    fn gather_dkg_end(
+        &mut self,
+        dkg_end: &DkgEnd,
+    ) -> Result<(Option<Message>, Option<OperationResult>), Error> {
+        if self.state != State::DkgGather
+            || dkg_end.dkg_id != self.current_dkg_id
+            || !self.in_dkg(dkg_end.signer_id)
+        {
+            return Ok((None, None));
+        }
+
+        self.dkg_end_messages
+            .insert(dkg_end.signer_id, dkg_end.clone());
+
+        self.try_dkg_end()
+    }
+
+    fn try_dkg_end(&mut self) -> Result<(Option<Message>, Option<OperationResult>), Error> {
+        let dkg_signers = self.dkg_signers();
+        if self.dkg_mode == DkgMode::CommitReveal {
+            if self.dkg_digests.len() < dkg_signers {
+                return Ok((None, None));
+            }
+            // check the reveals before the DKG end messages, which signers never send after a bad reveal
+            if self.dkg_public_shares.len() >= dkg_signers {
+                let bad_reveals =
+                    find_bad_reveals(self.current_dkg_id, &self.dkg_digests, &self.dkg_comms());
+                if !bad_reveals.is_empty() {
+                    self.move_to(State::Idle)?;
+                    return Err(Error::DkgBadReveals(bad_reveals));
+                }
+            }
+        }
+        if self.dkg_public_shares.len() < dkg_signers || self.dkg_end_messages.len() < dkg_signers {
+            return Ok((None, None));
+        }
+
+        let failed: Vec<u32> = self
+            .dkg_end_messages
+            .values()
+            .filter(|dkg_end| matches!(dkg_end.status, DkgStatus::Failure(_)))
+            .map(|dkg_end| dkg_end.signer_id)
+            .collect();
+        if !failed.is_empty() {
+            self.move_to(State::Idle)?;
+            return Err(Error::DkgFailure(failed));
+        }
+
+        if collect_complaints(self.dkg_end_messages.values()).is_empty() {
+            self.move_to(State::Idle)?;
+            return self.finish_dkg(&[]);
+        }
+
+        self.move_to(State::DkgDisclosureGather)?;
+
+        self.try_dkg_verdict()
+    }
+
+    fn gather_dkg_disclosure(
+        &mut self,
+        dkg_disclosure: &DkgDisclosure,
+    ) -> Result<(Option<Message>, Option<OperationResult>), Error> {
+        // accused signers may disclose before the coordinator has gathered every DKG end message
+        if !matches!(self.state, State::DkgGather | State::DkgDisclosureGather)
+            || dkg_disclosure.dkg_id != self.current_dkg_id
+            || !self.in_dkg(dkg_disclosure.signer_id)
+        {
+            return Ok((None, None));
+        }
+
+        self.dkg_disclosures
+            .insert(dkg_disclosure.signer_id, dkg_disclosure.clone());
+
+        self.try_dkg_verdict()
+    }
+
+    fn try_dkg_verdict(&mut self) -> Result<(Option<Message>, Option<OperationResult>), Error> {
+        if self.state != State::DkgDisclosureGather {
+            return Ok((None, None));
+        }
+
+        let comms = self.dkg_comms();
+        let polys = merge_poly_commitments(&comms)?;
+        let complaints = collect_complaints(self.dkg_end_messages.values());
+        let Some(verdict) = judge(&complaints, &comms, &self.dkg_disclosures, &polys) else {
+            return Ok((None, None));
+        };
+
+        self.move_to(State::Idle)?;
+        self.verdict = Some(verdict.clone());
+
+        // DKG finishes without any parties which cheated, as long as enough keys remain qualified
+        if !verdict.cheaters.is_empty() {
+            let qualified = self
+                .public_keys
+                .selected_parties(&verdict.qualified, A::PARTY_IDS);
+            let num_keys: usize = qualified.values().map(|key_ids| key_ids.len()).sum();
+            if num_keys < usize::try_from(self.threshold).unwrap() {
+                return Err(Error::DkgCheaters(verdict));
+            }
+        }
+
+        self.finish_dkg(&verdict.cheaters)
+    }
+
+    /// Construct the aggregator from the gathered poly commitments, leaving out the parties of any `cheaters`
+    ///
+    /// DKG finishes over a qualified set of parties if gathering ended early or any party cheated
+    fn finish_dkg(
+        &mut self,
+        cheaters: &[u32],
+    ) -> Result<(Option<Message>, Option<OperationResult>), Error> {
+        let polys = merge_poly_commitments(&self.dkg_comms())?;
+        let ctx = DkgContext::new(
+            self.protocol_version,
+            self.current_dkg_id,
+            self.total_keys,
+            self.threshold,
+        );
+        let key_ids = self.public_keys.group_key_ids();
+        let aggregator = if self.dkg_signer_ids.is_none() && cheaters.is_empty() {
+            A::new_with_context(&ctx, &key_ids, &polys)?
+        } else {
+            let polys: HashMap<u32, PolyCommitment> = polys
+                .into_iter()
+                .filter(|(party_id, _)| !cheaters.contains(party_id))
+                .collect();
+            let party_ids: Vec<u32> = polys.keys().cloned().collect();
+            let qualified = self.public_keys.selected_parties(&party_ids, A::PARTY_IDS);
+            A::new_qualified(&ctx, &key_ids, &qualified, &polys)?
+        };
+
+        self.aggregate_public_key = aggregator.get_poly()[0];
+        self.aggregator = Some(aggregator);
+
+        Ok((None, Some(OperationResult::Dkg(self.aggregate_public_key))))
+    }
+
+    /// Get the poly commitments from every signer's DKG public shares, indexed by signer ID then party ID
+    fn dkg_comms(&self) -> HashMap<u32, HashMap<u32, PolyCommitment>> {
+        self.dkg_public_shares
+            .iter()
+            .map(|(signer_id, dkg_public_shares)| (*signer_id, dkg_public_shares.comms.clone()))
+            .collect()
+    }
+
+    fn gather_nonces(
+        &mut self,
+        nonce_response: &NonceResponse,
+    ) -> Result<(Option<Message>, Option<OperationResult>), Error> {
+        if self.state != State::NonceGather
+            || nonce_response.dkg_id != self.current_dkg_id
+            || nonce_response.sign_id != self.current_sign_id
+            || nonce_response.sign_iter_id != self.current_sign_iter_id
+        {
+            return Ok((None, None));
+        }
+
+        self.public_nonces
+            .insert(nonce_response.signer_id, nonce_response.clone());
+
+        let num_keys: usize = self.public_nonces.values().map(|nr| nr.key_ids.len()).sum();
+        if num_keys < usize::try_from(self.threshold).unwrap() {
+            return Ok((None, None));
+        }
+
+        self.move_to(State::SigShareGather)?;
+
+        Ok((
+            Some(Message::SignatureShareRequest(SignatureShareRequest {
+                dkg_id: self.current_dkg_id,
+                sign_id: self.current_sign_id,
+                sign_iter_id: self.current_sign_iter_id,
+                nonce_responses: self.public_nonces.values().cloned().collect(),
+                message: self.message.clone(),
+            })),
+            None,
+        ))
+    }
+
+    fn gather_sig_shares(
+        &mut self,
+        sig_share_response: &SignatureShareResponse,
+    ) -> Result<(Option<Message>, Option<OperationResult>), Error> {
+        if self.state != State::SigShareGather
+            || sig_share_response.dkg_id != self.current_dkg_id
+            || sig_share_response.sign_id != self.current_sign_id
+            || sig_share_response.sign_iter_id != self.current_sign_iter_id
+            || !self
+                .public_nonces
+                .contains_key(&sig_share_response.signer_id)
+        {
+            return Ok((None, None));
+        }
+
+        self.signature_shares.insert(
+            sig_share_response.signer_id,
+            sig_share_response.signature_shares.clone(),
+        );
+
+        if self.signature_shares.len() < self.public_nonces.len() {
+            return Ok((None, None));
+        }
+
+        self.move_to(State::Idle)?;
+
+        let nonces: Vec<PublicNonce> = self
+            .public_nonces
+            .values()
+            .flat_map(|nr| nr.nonces.clone())
+            .collect();
+        let key_ids: Vec<u32> = self
+            .public_nonces
+            .values()
+            .flat_map(|nr| nr.key_ids.clone())
+            .collect();
+        let shares: Vec<SignatureShare> = self
+            .public_nonces
+            .keys()
+            .flat_map(|signer_id| self.signature_shares[signer_id].clone())
+            .collect();
+
+        let aggregator = match self.aggregator.as_mut() {
+            Some(aggregator) => aggregator,
+            None => {
+                return Err(Error::BadStateChange(
+                    "cannot sign before DKG completes".to_string(),
+                ))
+            }
+        };
+        let sig = aggregator.sign(
+            self.current_sign_id,
+            &self.message,
+            &nonces,
+            &shares,
+            &key_ids,
+        )?;
+
+        Ok((None, Some(OperationResult::Sign(sig))))
+    }
+}
+
+impl<A: Aggregator> StateMachine<State, Error> for Coordinator<A> {
+    fn move_to(&mut self, state: State) -> Result<(), Error> {
+        self.can_move_to(&state)?;
+        self.state = state;
+        Ok(())
+    }
+
+    fn can_move_to(&self, state: &State) -> Result<(), Error> {
+        let prev_state = &self.state;
+        let accepted = match state {
+            State::Idle => true,
+            State::DkgGather => prev_state == &State::Idle || prev_state == &State::DkgGather,
+            State::DkgDisclosureGather => prev_state == &State::DkgGather,
+            State::NonceGather => {
+                prev_state == &State::Idle
+                    || prev_state == &State::NonceGather
+                    || prev_state == &State::SigShareGather
+            }
+            State::SigShareGather => prev_state == &State::NonceGather,
+        };
+        if accepted {
+            Ok(())
+        } else {
+            Err(Error::BadStateChange(format!(
+                "{:?} to {:?}",
+                prev_state, state
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use rand_core::{CryptoRng, OsRng, RngCore};
+
+    use crate::{
+        common::{DisclosedShare, DkgMode, PartyIds},
+        net::{Message, NonceRequest, Packet, Signable},
+        state_machine::{
+            coordinator::Coordinator, signer::SigningRound, Error, OperationResult, PublicKeys,
+        },
+        traits::{Aggregator, Signer},
+        util::encrypt_share,
+        v1, v2, Point, Scalar, G,
+    };
+
+    fn feed<RNG: RngCore + CryptoRng, S: Signer, A: Aggregator>(
+        coordinator: &mut Coordinator<A>,
+        rounds: &mut [SigningRound<S>],
+        packet: Packet,
+        rng: &mut RNG,
+    ) -> Option<OperationResult> {
+        feed_tampered(coordinator, rounds, packet, rng, |_| {}).expect("coordinator failed")
+    }
+
+    /// Feed packets through the network, letting `tamper` modify each one in flight
+    fn feed_tampered<RNG: RngCore + CryptoRng, S: Signer, A: Aggregator>(
+        coordinator: &mut Coordinator<A>,
+        rounds: &mut [SigningRound<S>],
+        packet: Packet,
+        rng: &mut RNG,
+        mut tamper: impl FnMut(&mut Packet),
+    ) -> Result<Option<OperationResult>, Error> {
+        let mut queue = VecDeque::from([packet]);
+        while let Some(mut packet) = queue.pop_front() {
+            tamper(&mut packet);
+            for round in rounds.iter_mut() {
+                queue.extend(round.process(&packet, rng).expect("signer failed"));
+            }
+            let (outbound, result) = coordinator.process(&packet)?;
+            queue.extend(outbound);
+            if result.is_some() {
+                return Ok(result);
+            }
+        }
+        Ok(None)
+    }
+
+    /// The number of keys in the group most tests use
+    const NUM_KEYS: u32 = 10;
+
+    /// The threshold of the group most tests use
+    const THRESHOLD: u32 = 7;
+
+    /// The key IDs of each signer in the group most tests use
+    fn key_ids() -> Vec<Vec<u32>> {
+        [
+            [0, 1, 2].to_vec(),
+            [3, 4].to_vec(),
+            [5, 6, 7].to_vec(),
+            [8, 9].to_vec(),
+        ]
+        .to_vec()
+    }
+
+    /// The number of keys in the small group which DKG failure tests use
+    const SMALL_NUM_KEYS: u32 = 6;
+
+    /// The threshold of the small group which DKG failure tests use
+    const SMALL_THRESHOLD: u32 = 4;
+
+    /// The key IDs of each signer in the small group which DKG failure tests use
+    fn small_key_ids() -> Vec<Vec<u32>> {
+        [[0, 1].to_vec(), [2, 3].to_vec(), [4, 5].to_vec()].to_vec()
+    }
+
+    fn setup<S: Signer, A: Aggregator>(
+        signers: Vec<S>,
+        total_keys: u32,
+        threshold: u32,
+    ) -> (Coordinator<A>, Vec<SigningRound<S>>, Vec<Scalar>) {
+        let mut rng = OsRng::default();
+        let total_signers = signers.len().try_into().unwrap();
+
+        let coordinator_private_key = Scalar::random(&mut rng);
+        let network_private_keys: Vec<Scalar> =
+            signers.iter().map(|_| Scalar::random(&mut rng)).collect();
+        let mut public_keys = PublicKeys {
+            coordinator: coordinator_private_key * G,
+            ..Default::default()
+        };
+        for (signer, private_key) in signers.iter().zip(&network_private_keys) {
+            let public_key: Point = private_key * G;
+            public_keys.signers.insert(signer.get_id(), public_key);
+            for key_id in signer.get_key_ids() {
+                public_keys.key_ids.insert(key_id, public_key);
+            }
+        }
+
+        let rounds: Vec<SigningRound<S>> = signers
+            .into_iter()
+            .zip(network_private_keys.clone())
+            .map(|(s, private_key)| {
+                SigningRound::new(
+                    threshold,
+                    total_signers,
+                    total_keys,
+                    s,
+                    private_key,
+                    public_keys.clone(),
+                )
+            })
+            .collect();
+        let coordinator = Coordinator::<A>::new(
+            total_signers,
+            total_keys,
+            threshold,
+            coordinator_private_key,
+            public_keys,
+        );
+
+        (coordinator, rounds, network_private_keys)
+    }
+
+    fn run_dkg_sign<S: Signer, A: Aggregator>(signers: Vec<S>, total_keys: u32, threshold: u32) {
+        let mut rng = OsRng::default();
+        let msg = "It was many and many a year ago".as_bytes();
+        let (mut coordinator, mut rounds, _) = setup::<S, A>(signers, total_keys, threshold);
+
+        for dkg_mode in [DkgMode::Plain, DkgMode::CommitReveal] {
+            coordinator.dkg_mode = dkg_mode;
+            for round in rounds.iter_mut() {
+                round.dkg_mode = dkg_mode;
+            }
+
+            let dkg_begin = coordinator.start_dkg_round().unwrap();
+            let group_key = match feed(&mut coordinator, &mut rounds, dkg_begin, &mut rng) {
+                Some(OperationResult::Dkg(group_key)) => group_key,
+                _ => panic!("DKG did not complete"),
+            };
+
+            for _ in 0..2 {
+                let nonce_request = coordinator.start_signing_round(msg).unwrap();
+                match feed(&mut coordinator, &mut rounds, nonce_request, &mut rng) {
+                    Some(OperationResult::Sign(sig)) => assert!(sig.verify_xonly(&group_key, msg)),
+                    _ => panic!("signing round did not complete"),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn dkg_sign_v1() {
+        let mut rng = OsRng::default();
+        let signers = v1::test_helpers::new_signers(&key_ids(), THRESHOLD, &mut rng);
+
+        run_dkg_sign::<v1::Signer, v1::SignatureAggregator>(signers, NUM_KEYS, THRESHOLD);
+    }
+
+    #[test]
+    fn dkg_sign_v2() {
+        let mut rng = OsRng::default();
+        let signers = v2::test_helpers::new_parties(&key_ids(), THRESHOLD, &mut rng);
+
+        run_dkg_sign::<v2::Party, v2::SignatureAggregator>(signers, NUM_KEYS, THRESHOLD);
+    }
+
+    #[test]
+    fn forged_packets_rejected() {
+        let mut rng = OsRng::default();
+        let signers =
+            v2::test_helpers::new_parties(&[[0, 1].to_vec(), [2, 3].to_vec()], 3, &mut rng);
+        let (mut coordinator, mut rounds, network_private_keys) =
+            setup::<v2::Party, v2::SignatureAggregator>(signers, 4, 3);
+
+        // a coordinator message which was not signed by the coordinator
+        let nonce_request = Message::NonceRequest(NonceRequest {
+            dkg_id: 0,
+            sign_id: 1,
+            sign_iter_id: 1,
+        });
+        let forged = Packet::new(nonce_request.clone(), &Scalar::random(&mut rng)).unwrap();
+        assert!(matches!(
+            rounds[0].process(&forged, &mut rng),
+            Err(Error::InvalidPacketSignature)
+        ));
+
+        // a signer message signed by a different signer
+        let nonce_request = Packet::new(nonce_request, &coordinator.message_private_key).unwrap();
+        let mut nonce_responses = rounds[1].process(&nonce_request, &mut rng).unwrap();
+        let mut forged = nonce_responses.pop().unwrap();
+        if let Message::NonceResponse(nonce_response) = &mut forged.msg {
+            nonce_response.signer_id = 0;
+        }
+        assert!(matches!(
+            coordinator.process(&forged),
+            Err(Error::InvalidPacketSignature)
+        ));
+
+        // a signer message which claims key IDs owned by another signer
+        let nonce_responses = rounds[1].process(&nonce_request, &mut rng).unwrap();
+        let mut forged = nonce_responses[0].clone();
+        if let Message::NonceResponse(nonce_response) = &mut forged.msg {
+            nonce_response.key_ids = vec![0, 1];
+        }
+        forged.sig = forged.msg.sign(&network_private_keys[1]).unwrap();
+        assert!(matches!(
+            coordinator.process(&forged),
+            Err(Error::InvalidPacketSignature)
+        ));
+
+        // signer messages which deal from a party owned by another signer
+        let dkg_begin = coordinator.start_dkg_round().unwrap();
+        let dkg_packets = rounds[1].process(&dkg_begin, &mut rng).unwrap();
+        let mut num_forged = 0;
+        for packet in &dkg_packets {
+            let mut forged = packet.clone();
+            match &mut forged.msg {
+                Message::DkgPublicShares(dkg_public_shares) => {
+                    let comm = dkg_public_shares.comms.remove(&1).unwrap();
+                    dkg_public_shares.comms.insert(0, comm);
+                }
+                Message::DkgPrivateShares(dkg_private_shares) => {
+                    let shares = dkg_private_shares.shares.remove(&1).unwrap();
+                    dkg_private_shares.shares.insert(0, shares);
+                }
+                _ => continue,
+            }
+            forged.sig = forged.msg.sign(&network_private_keys[1]).unwrap();
+            num_forged += 1;
+
+            assert!(packet.verify(&coordinator.public_keys, PartyIds::SignerIds));
+            // under v1 party IDs signer 1 deals from its key IDs, and key 1 belongs to signer 0
+            assert!(!packet.verify(&coordinator.public_keys, PartyIds::KeyIds));
+            assert!(matches!(
+                coordinator.process(&forged),
+                Err(Error::InvalidPacketSignature)
+            ));
+            assert!(matches!(
+                rounds[0].process(&forged, &mut rng),
+                Err(Error::InvalidPacketSignature)
+            ));
+        }
+        assert_eq!(num_forged, 2);
+    }
+
+    fn dkg_setup_v2() -> (
+        Coordinator<v2::SignatureAggregator>,
+        Vec<SigningRound<v2::Party>>,
+        Vec<Scalar>,
+    ) {
+        let mut rng = OsRng::default();
+        let signers = v2::test_helpers::new_parties(&small_key_ids(), SMALL_THRESHOLD, &mut rng);
+
+        setup::<v2::Party, v2::SignatureAggregator>(signers, SMALL_NUM_KEYS, SMALL_THRESHOLD)
+    }
+
+    /// Replace the encrypted share which signer 0 deals to key 2 with an encryption of `bad_share`
+    fn corrupt_private_share<RNG: RngCore + CryptoRng>(
+        packet: &mut Packet,
+        bad_share: &Scalar,
+        network_private_keys: &[Scalar],
+        rng: &mut RNG,
+    ) {
+        if let Message::DkgPrivateShares(dkg_private_shares) = &mut packet.msg {
+            if dkg_private_shares.signer_id == 0 {
+                let encrypted_share = encrypt_share(
+                    bad_share,
+                    dkg_private_shares.dkg_id,
+                    0,
+                    0,
+                    2,
+                    &network_private_keys[0],
+                    &(network_private_keys[1] * G),
+                    rng,
+                )
+                .unwrap();
+                dkg_private_shares
+                    .shares
+                    .get_mut(&0)
+                    .unwrap()
+                    .insert(2, encrypted_share);
+                packet.sig = packet.msg.sign(&network_private_keys[0]).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn dkg_complaint_resolved() {
+        let mut rng = OsRng::default();
+        let mut tamper_rng = OsRng::default();
+        let msg = "In this kingdom by the sea".as_bytes();
+        let (mut coordinator, mut rounds, network_private_keys) = dkg_setup_v2();
+        let bad_share = Scalar::random(&mut rng);
+
+        // the share was garbled in transit, so signer 0 resolves the complaint by disclosing the real one
+        let dkg_begin = coordinator.start_dkg_round().unwrap();
+        let group_key = match feed_tampered(
+            &mut coordinator,
+            &mut rounds,
+            dkg_begin,
+            &mut rng,
+            |packet| {
+                corrupt_private_share(packet, &bad_share, &network_private_keys, &mut tamper_rng)
+            },
+        ) {
+            Ok(Some(OperationResult::Dkg(group_key))) => group_key,
+            _ => panic!("DKG did not complete"),
+        };
+
+        for round in &rounds {
+            let verdict = round.verdict.as_ref().unwrap();
+            assert!(verdict.cheaters.is_empty());
+            assert_eq!(verdict.resolved.len(), 1);
+        }
+
+        let nonce_request = coordinator.start_signing_round(msg).unwrap();
+        match feed(&mut coordinator, &mut rounds, nonce_request, &mut rng) {
+            Some(OperationResult::Sign(sig)) => assert!(sig.verify_xonly(&group_key, msg)),
+            _ => panic!("signing round did not complete"),
+        }
+    }
+
+    #[test]
+    fn dkg_cheater_identified() {
+        let mut rng = OsRng::default();
+        let mut tamper_rng = OsRng::default();
+        let msg = "That a maiden there lived whom you may know".as_bytes();
+        let (mut coordinator, mut rounds, network_private_keys) = dkg_setup_v2();
+        let bad_share = Scalar::random(&mut rng);
+
+        // signer 0 deals a bad share and then discloses the same bad share
+        let dkg_begin = coordinator.start_dkg_round().unwrap();
+        let result = feed_tampered(
+            &mut coordinator,
+            &mut rounds,
+            dkg_begin,
+            &mut rng,
+            |packet| {
+                corrupt_private_share(packet, &bad_share, &network_private_keys, &mut tamper_rng);
+                if let Message::DkgDisclosure(dkg_disclosure) = &mut packet.msg {
+                    dkg_disclosure.shares = vec![DisclosedShare {
+                        party_id: 0,
+                        key_id: 2,
+                        share: bad_share,
+                    }];
+                    packet.sig = packet.msg.sign(&network_private_keys[0]).unwrap();
+                }
+            },
+        );
+
+        // the honest signers hold enough keys to finish DKG without the cheater
+        let group_key = match result {
+            Ok(Some(OperationResult::Dkg(group_key))) => group_key,
+            _ => panic!("DKG did not complete"),
+        };
+        let verdict = coordinator.verdict.as_ref().unwrap();
+        assert_eq!(verdict.cheaters, vec![0]);
+        assert_eq!(verdict.qualified, vec![1, 2]);
+        for round in &rounds[1..] {
+            assert_eq!(round.verdict.as_ref().unwrap().cheaters, vec![0]);
+        }
+
+        let nonce_request = coordinator.start_signing_round(msg).unwrap();
+        match feed(&mut coordinator, &mut rounds[1..], nonce_request, &mut rng) {
+            Some(OperationResult::Sign(sig)) => assert!(sig.verify_xonly(&group_key, msg)),
+            _ => panic!("signing round did not complete"),
+        }
+    }
+
+    /// Run DKG and signing with the last signer absent, ending DKG gathering once the others have responded
+    fn run_dkg_absent_signer<S: Signer, A: Aggregator>(
+        signers: Vec<S>,
+        total_keys: u32,
+        threshold: u32,
+    ) {
+        let mut rng = OsRng::default();
+        let msg = "By the name of Annabel Lee".as_bytes();
+        let (mut coordinator, mut rounds, _) = setup::<S, A>(signers, total_keys, threshold);
+        let present = rounds.len() - 1;
+
+        for dkg_mode in [DkgMode::Plain, DkgMode::CommitReveal] {
+            coordinator.dkg_mode = dkg_mode;
+            for round in rounds.iter_mut() {
+                round.dkg_mode = dkg_mode;
+            }
+
+            let dkg_begin = coordinator.start_dkg_round().unwrap();
+            assert!(feed(
+                &mut coordinator,
+                &mut rounds[..present],
+                dkg_begin,
+                &mut rng
+            )
+            .is_none());
+
+            let dkg_end_begin = coordinator.end_dkg_gather().unwrap();
+            let group_key = match feed(
+                &mut coordinator,
+                &mut rounds[..present],
+                dkg_end_begin,
+                &mut rng,
+            ) {
+                Some(OperationResult::Dkg(group_key)) => group_key,
+                _ => panic!("DKG did not complete"),
+            };
+
+            let nonce_request = coordinator.start_signing_round(msg).unwrap();
+            match feed(
+                &mut coordinator,
+                &mut rounds[..present],
+                nonce_request,
+                &mut rng,
+            ) {
+                Some(OperationResult::Sign(sig)) => assert!(sig.verify_xonly(&group_key, msg)),
+                _ => panic!("signing round did not complete"),
+            }
+        }
+    }
+
+    #[test]
+    fn dkg_absent_signer_v1() {
+        let mut rng = OsRng::default();
+        let signers = v1::test_helpers::new_signers(&small_key_ids(), SMALL_THRESHOLD, &mut rng);
+
+        run_dkg_absent_signer::<v1::Signer, v1::SignatureAggregator>(
+            signers,
+            SMALL_NUM_KEYS,
+            SMALL_THRESHOLD,
+        );
+    }
+
+    #[test]
+    fn dkg_absent_signer_v2() {
+        let mut rng = OsRng::default();
+        let signers = v2::test_helpers::new_parties(&small_key_ids(), SMALL_THRESHOLD, &mut rng);
+
+        run_dkg_absent_signer::<v2::Party, v2::SignatureAggregator>(
+            signers,
+            SMALL_NUM_KEYS,
+            SMALL_THRESHOLD,
+        );
+    }
+
+    #[test]
+    fn dkg_end_gather_needs_threshold() {
+        let mut rng = OsRng::default();
+        let (mut coordinator, mut rounds, _) = dkg_setup_v2();
+
+        let dkg_begin = coordinator.start_dkg_round().unwrap();
+        assert!(feed(&mut coordinator, &mut rounds[..1], dkg_begin, &mut rng).is_none());
+        assert!(coordinator.end_dkg_gather().is_err());
+    }
+
+    #[test]
+    fn dkg_bad_reveal() {
+        let mut rng = OsRng::default();
+        let (mut coordinator, mut rounds, network_private_keys) = dkg_setup_v2();
+        coordinator.dkg_mode = DkgMode::CommitReveal;
+        for round in rounds.iter_mut() {
+            round.dkg_mode = DkgMode::CommitReveal;
+        }
+
+        // signer 0 reveals public shares which differ from the ones it committed to
+        let mut queue = VecDeque::from([coordinator.start_dkg_round().unwrap()]);
+        let mut signer_errors = Vec::new();
+        let mut result = None;
+        while let Some(mut packet) = queue.pop_front() {
+            if let Message::DkgPublicShares(dkg_public_shares) = &mut packet.msg {
+                if dkg_public_shares.signer_id == 0 {
+                    dkg_public_shares.comms.values_mut().next().unwrap().A[1] += G;
+                    packet.sig = packet.msg.sign(&network_private_keys[0]).unwrap();
+                }
+            }
+            for (i, round) in rounds.iter_mut().enumerate() {
+                match round.process(&packet, &mut rng) {
+                    Ok(packets) => queue.extend(packets),
+                    Err(e) => signer_errors.push((i, e)),
+                }
+            }
+            match coordinator.process(&packet) {
+                Ok((outbound, _)) => queue.extend(outbound),
+                Err(e) => {
+                    result = Some(e);
+                    break;
+                }
+            }
+        }
+
+        assert!(matches!(result, Some(Error::DkgBadReveals(ids)) if ids == vec![0]));
+        assert!(!signer_errors.is_empty());
+        // signer 0 itself sees a reveal under its ID which conflicts with the one it sent
+        assert!(signer_errors.iter().all(|(i, e)| match i {
+            0 => matches!(e, Error::DkgEquivocation(0)),
+            _ => matches!(e, Error::DkgBadReveals(ids) if ids == &vec![0]),
+        }));
+    }
+
+    #[test]
+    fn dkg_recommit_rejected() {
+        let mut rng = OsRng::default();
+        let (mut coordinator, mut rounds, network_private_keys) = dkg_setup_v2();
+        coordinator.dkg_mode = DkgMode::CommitReveal;
+        for round in rounds.iter_mut() {
+            round.dkg_mode = DkgMode::CommitReveal;
+        }
+
+        // signer 0 commits to junk, then re-commits to its real public shares once an honest signer has revealed
+        let mut queue = VecDeque::from([coordinator.start_dkg_round().unwrap()]);
+        let mut committed = false;
+        let mut recommit = None;
+        let mut signer_errors = Vec::new();
+        let mut coordinator_errors = Vec::new();
+        while let Some(mut packet) = queue.pop_front() {
+            let original = packet.clone();
+            match &mut packet.msg {
+                Message::DkgCommitment(dkg_commitment)
+                    if dkg_commitment.signer_id == 0 && !committed =>
+                {
+                    committed = true;
+                    recommit = Some(original);
+                    dkg_commitment.digest = [0u8; 32];
+                    packet.sig = packet.msg.sign(&network_private_keys[0]).unwrap();
+                }
+                Message::DkgPublicShares(dkg_public_shares) if dkg_public_shares.signer_id != 0 => {
+                    if let Some(recommit) = recommit.take() {
+                        queue.push_front(recommit);
+                    }
+                }
+                _ => {}
+            }
+            for (i, round) in rounds.iter_mut().enumerate() {
+                match round.process(&packet, &mut rng) {
+                    Ok(packets) => queue.extend(packets),
+                    Err(e) => signer_errors.push((i, e)),
+                }
+            }
+            match coordinator.process(&packet) {
+                Ok((outbound, result)) => {
+                    assert!(result.is_none());
+                    queue.extend(outbound);
+                }
+                Err(e) => coordinator_errors.push(e),
+            }
+        }
+
+        // the re-commitment is rejected, so the real reveal fails to match the junk commitment
+        assert!(matches!(
+            coordinator_errors.as_slice(),
+            [Error::DkgEquivocation(0), Error::DkgBadReveals(ids)] if ids == &vec![0]
+        ));
+        for i in 1..3 {
+            let errors: Vec<&Error> = signer_errors
+                .iter()
+                .filter(|(j, _)| *j == i)
+                .map(|(_, e)| e)
+                .collect();
+            assert!(matches!(
+                errors.as_slice(),
+                [Error::DkgEquivocation(0), Error::DkgBadReveals(ids), ..] if ids == &vec![0]
+            ));
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use hashbrown::{HashMap, HashSet};
+use p256k1::ecdsa;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    common::{
+        DisclosedShare, DkgComplaint, PartyIds, PolyCommitment, SelectedSigners, Signature, Verdict,
+    },
+    compute,
+    errors::{AggregatorError, DkgError, EncryptionError, SignError},
+    net::{DkgDisclosure, DkgEnd, DkgStatus},
+    Point,
+};
+
+/// State machine for a signing round coordinator
+pub mod coordinator;
+
+/// State machine for a signer participating in DKG and signing rounds
+pub mod signer;
+
+/// A generic state machine
+pub trait StateMachine<S, E> {
+    /// Attempt to move the state machine to a new state
+    fn move_to(&mut self, state: S) -> Result<(), E>;
+    /// Check if the state machine can move to a new state
+    fn can_move_to(&self, state: &S) -> Result<(), E>;
+}
+
+#[derive(Error, Debug)]
+/// Errors which can happen while running the state machines
+pub enum Error {
+    #[error("bad state change: {0}")]
+    /// A bad state change was requested
+    BadStateChange(String),
+    #[error("bad poly commitment for id {0}")]
+    /// A poly commitment for this party/key ID was sent by more than one signer or was bound to another ID
+    BadPolyCommitment(u32),
+    #[error("packet signature failed to verify")]
+    /// A packet was not signed by the configured key for its sender
+    InvalidPacketSignature,
+    #[error("failed to sign packet {0:?}")]
+    /// An error while signing an outbound packet
+    PacketSigning(ecdsa::Error),
+    #[error("nonce mismatch in signature share request")]
+    /// The nonces in a signature share request did not match the ones this signer sent
+    NonceMismatch,
+    #[error("dkg errors {0:?}")]
+    /// DKG failed for the wrapped party/key IDs
+    Dkg(HashMap<u32, DkgError>),
+    #[error("dkg failed for signers {0:?}")]
+    /// One or more signers reported a DKG failure
+    DkgFailure(Vec<u32>),
+    #[error("dkg reveals from signers {0:?} did not match their commitments")]
+    /// The listed signers revealed DKG public shares which did not match their commitments
+    DkgBadReveals(Vec<u32>),
+    #[error("signer {0} sent a dkg message which conflicts with its first one")]
+    /// A signer sent a DKG commitment or public shares which differ from the first ones it sent in the round, so the first ones stand
+    DkgEquivocation(u32),
+    #[error("dkg commitment from signer {0} arrived after this signer revealed")]
+    /// A signer committed to DKG public shares after this signer had already revealed its own
+    DkgLateCommitment(u32),
+    #[error("dkg cheaters {:?}", .0.cheaters)]
+    /// The complaint round found parties which dealt bad shares, leaving too few keys qualified to finish DKG without them
+    DkgCheaters(Verdict),
+    #[error("encryption error {0:?}")]
+    /// An error while encrypting or decrypting private shares
+    Encryption(EncryptionError),
+    #[error("aggregator error {0:?}")]
+    /// An error during signature aggregation
+    Aggregator(AggregatorError),
+    #[error("sign error {0:?}")]
+    /// An error while making signature shares
+    Sign(SignError),
+}
+
+impl From<SignError> for Error {
+    fn from(e: SignError) -> Self {
+        Error::Sign(e)
+    }
+}
+
+impl From<AggregatorError> for Error {
+    fn from(e: AggregatorError) -> Self {
+        Error::Aggregator(e)
+    }
+}
+
+impl From<ecdsa::Error> for Error {
+    fn from(e: ecdsa::Error) -> Self {
+        Error::PacketSigning(e)
+    }
+}
+
+impl From<EncryptionError> for Error {
+    fn from(e: EncryptionError) -> Self {
+        Error::Encryption(e)
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+/// The long-term public keys of the coordinator and signers, which are used to sign packets and encrypt DKG private shares
+pub struct PublicKeys {
+    /// The public key of the coordinator
+    pub coordinator: Point,
+    /// The public keys of the signers, indexed by signer ID
+    pub signers: HashMap<u32, Point>,
+    /// The public keys of the signers, indexed by the key IDs they own
+    pub key_ids: HashMap<u32, Point>,
+}
+
+impl PublicKeys {
+    /// Get the IDs of every key in the group in order
+    pub fn group_key_ids(&self) -> Vec<u32> {
+        let mut key_ids: Vec<u32> = self.key_ids.keys().cloned().collect();
+        key_ids.sort();
+        key_ids
+    }
+
+    /// Get the IDs of the keys owned by `signer_id`
+    pub fn signer_key_ids(&self, signer_id: u32) -> HashSet<u32> {
+        match self.signers.get(&signer_id) {
+            Some(public_key) => self
+                .key_ids
+                .iter()
+                .filter(|(_, key_public_key)| *key_public_key == public_key)
+                .map(|(key_id, _)| *key_id)
+                .collect(),
+            None => HashSet::new(),
+        }
+    }
+
+    /// Get the key IDs of each of `party_ids`, where party IDs are assigned as `party_kind`
+    pub fn selected_parties(&self, party_ids: &[u32], party_kind: PartyIds) -> SelectedSigners {
+        party_ids
+            .iter()
+            .map(|party_id| {
+                let key_ids = match party_kind {
+                    PartyIds::KeyIds => HashSet::from([*party_id]),
+                    PartyIds::SignerIds => self.signer_key_ids(*party_id),
+                };
+                (*party_id, key_ids)
+            })
+            .collect()
+    }
+
+    /// Check whether `party_id` belongs to `signer_id`, where party IDs are assigned as `party_ids`
+    pub fn owns_party(&self, signer_id: u32, party_id: u32, party_ids: PartyIds) -> bool {
+        match party_ids {
+            PartyIds::KeyIds => match self.signers.get(&signer_id) {
+                Some(public_key) => self.key_ids.get(&party_id) == Some(public_key),
+                None => false,
+            },
+            PartyIds::SignerIds => party_id == signer_id,
+        }
+    }
+}
+
+/// Result of a completed DKG or signing operation
+pub enum OperationResult {
+    /// The aggregate group public key from a completed DKG round
+    Dkg(Point),
+    /// The aggregate signature from a completed signing round
+    Sign(Signature),
+}
+
+/// Merge the poly commitments from all signers, indexed by signer ID then party/key ID, checking that each is bound to its ID and sent by only one signer
+pub fn merge_poly_commitments(
+    comms: &HashMap<u32, HashMap<u32, PolyCommitment>>,
+) -> Result<HashMap<u32, PolyCommitment>, Error> {
+    let mut merged = HashMap::new();
+
+    for signer_comms in comms.values() {
+        for (id, comm) in signer_comms {
+            if comm.id.id != compute::id(*id) || merged.insert(*id, comm.clone()).is_some() {
+                return Err(Error::BadPolyCommitment(*id));
+            }
+        }
+    }
+
+    Ok(merged)
+}
+
+/// Check the digest of a DKG message from `signer_id` against the digest of the `first` one it sent in the round, if any, returning whether the message is new
+///
+/// Only the first message counts, so a repeat of it is ignored and a different one is rejected
+pub fn check_first_message(
+    first: Option<[u8; 32]>,
+    signer_id: u32,
+    digest: [u8; 32],
+) -> Result<bool, Error> {
+    match first {
+        None => Ok(true),
+        Some(first) if first == digest => Ok(false),
+        Some(_) => Err(Error::DkgEquivocation(signer_id)),
+    }
+}
+
+/// Collect the complaints from all DKG end messages in a consistent order
+pub fn collect_complaints<'a>(dkg_ends: impl Iterator<Item = &'a DkgEnd>) -> Vec<DkgComplaint> {
+    let mut complaints: Vec<DkgComplaint> = dkg_ends
+        .flat_map(|dkg_end| match &dkg_end.status {
+            DkgStatus::BadShares(complaints) => complaints.clone(),
+            _ => Vec::new(),
+        })
+        .collect();
+
+    complaints.sort();
+    complaints.dedup();
+    complaints
+}
+
+/// Find the signer which dealt the poly commitment for `party_id`, given all poly commitments indexed by signer ID then party ID
+pub fn party_owner(
+    comms: &HashMap<u32, HashMap<u32, PolyCommitment>>,
+    party_id: u32,
+) -> Option<u32> {
+    comms
+        .iter()
+        .find(|(_, signer_comms)| signer_comms.contains_key(&party_id))
+        .map(|(signer_id, _)| *signer_id)
+}
+
+/// Find the signers whose revealed poly commitments, indexed by signer ID then party ID, do not match the digests they committed to in DKG round `dkg_id`
+pub fn find_bad_reveals(
+    dkg_id: u64,
+    digests: &HashMap<u32, [u8; 32]>,
+    comms: &HashMap<u32, HashMap<u32, PolyCommitment>>,
+) -> Vec<u32> {
+    let mut bad_reveals: Vec<u32> = comms
+        .iter()
+        .filter(|(signer_id, signer_comms)| {
+            digests.get(*signer_id)
+                != Some(&compute::poly_commitments_digest(
+                    dkg_id,
+                    **signer_id,
+                    signer_comms,
+                ))
+        })
+        .map(|(signer_id, _)| *signer_id)
+        .collect();
+
+    bad_reveals.sort();
+    bad_reveals
+}
+
+#[allow(non_snake_case)]
+/// Judge `complaints` once every accused signer has sent a disclosure, only accepting disclosed shares from the signer which dealt them
+pub fn judge(
+    complaints: &[DkgComplaint],
+    comms: &HashMap<u32, HashMap<u32, PolyCommitment>>,
+    disclosures: &HashMap<u32, DkgDisclosure>,
+    A: &HashMap<u32, PolyCommitment>,
+) -> Option<Verdict> {
+    let mut disclosed_shares: Vec<DisclosedShare> = Vec::new();
+
+    for complaint in complaints {
+        if let Some(owner) = party_owner(comms, complaint.party_id) {
+            match disclosures.get(&owner) {
+                Some(disclosure) => disclosed_shares.extend(
+                    disclosure
+                        .shares
+                        .iter()
+                        .filter(|share| share.party_id == complaint.party_id)
+                        .cloned(),
+                ),
+                None => return None,
+            }
+        }
+    }
+
+    Some(Verdict::new(complaints, &disclosed_shares, A))
+}
@@ -0,0 +1,727 @@
+use hashbrown::HashMap;
+use p256k1::scalar::Scalar;
+use rand_core::{CryptoRng, RngCore};
+
+use crate::{
+    common::{
+        DisclosedShare, DkgComplaint, DkgMode, PolyCommitment, PublicNonce, SessionId, Verdict,
+    },
+    compute,
+    errors::DkgError,
+    net::{
+        DkgBegin, DkgCommitment, DkgDisclosure, DkgEnd, DkgEndBegin, DkgPrivateShares,
+        DkgPublicShares, DkgStatus, Message, NonceRequest, NonceResponse, Packet,
+        SignatureShareRequest, SignatureShareResponse,
+    },
+    state_machine::{
+        check_first_message, collect_complaints, find_bad_reveals, judge, merge_poly_commitments,
+        Error, PublicKeys, StateMachine,
+    },
+    traits::Signer as SignerTrait,
+    util::decrypt_share,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Signer states
+pub enum State {
+    /// The signer is idle
+    Idle,
+    /// The signer is gathering DKG public and private shares
+    DkgGather,
+    /// The signer is gathering DKG end messages, which may contain complaints
+    DkgEndGather,
+    /// The signer is gathering disclosures from signers which were complained about
+    DkgDisclosureGather,
+}
+
+/// A state machine which drives a `traits::Signer` through DKG and signing rounds
+pub struct SigningRound<Signer: SignerTrait> {
+    /// The current DKG round ID
+    pub dkg_id: u64,
+    /// The current signing round ID
+    pub sign_id: u64,
+    /// The current signing round iteration ID
+    pub sign_iter_id: u64,
+    /// The threshold of keys needed to construct a valid signature
+    pub threshold: u32,
+    /// The total number of signers
+    pub total_signers: u32,
+    /// The total number of keys
+    pub total_keys: u32,
+    /// The wrapped signer
+    pub signer: Signer,
+    /// The current state
+    pub state: State,
+    /// The long-term public keys of all signers
+    pub public_keys: PublicKeys,
+    /// The verdict of the last DKG complaint round, if there were any complaints
+    pub verdict: Option<Verdict>,
+    /// How DKG public shares are published, which every signer and the coordinator must agree on
+    pub dkg_mode: DkgMode,
+    network_private_key: Scalar,
+    dkg_signer_ids: Option<Vec<u32>>,
+    digests: HashMap<u32, [u8; 32]>,
+    reveal: Vec<Message>,
+    commitments: HashMap<u32, HashMap<u32, PolyCommitment>>,
+    shares: HashMap<u32, HashMap<u32, HashMap<u32, Vec<u8>>>>,
+    polys: HashMap<u32, PolyCommitment>,
+    private_shares: HashMap<u32, HashMap<u32, Scalar>>,
+    dkg_end_messages: HashMap<u32, DkgEnd>,
+    dkg_disclosures: HashMap<u32, DkgDisclosure>,
+    public_nonces: Vec<PublicNonce>,
+    nonce_session: SessionId,
+}
+
+impl<Signer: SignerTrait> SigningRound<Signer> {
+    /// Construct a SigningRound which wraps `signer`, using `network_private_key` to exchange encrypted DKG private shares with the holders of `public_keys`
+    pub fn new(
+        threshold: u32,
+        total_signers: u32,
+        total_keys: u32,
+        signer: Signer,
+        network_private_key: Scalar,
+        public_keys: PublicKeys,
+    ) -> Self {
+        Self {
+            dkg_id: 0,
+            sign_id: 0,
+            sign_iter_id: 0,
+            threshold,
+            total_signers,
+            total_keys,
+            signer,
+            state: State::Idle,
+            public_keys,
+            verdict: None,
+            dkg_mode: DkgMode::default(),
+            network_private_key,
+            dkg_signer_ids: None,
+            digests: HashMap::new(),
+            reveal: Vec::new(),
+            commitments: HashMap::new(),
+            shares: HashMap::new(),
+            polys: HashMap::new(),
+            private_shares: HashMap::new(),
+            dkg_end_messages: HashMap::new(),
+            dkg_disclosures: HashMap::new(),
+            public_nonces: Vec::new(),
+            nonce_session: 0,
+        }
+    }
+
+    /// Verify and process an inbound packet, returning any outbound packets signed with this signer's network key
+    pub fn process<RNG: RngCore + CryptoRng>(
+        &mut self,
+        packet: &Packet,
+        rng: &mut RNG,
+    ) -> Result<Vec<Packet>, Error> {
+        if !packet.verify(&self.public_keys, Signer::PARTY_IDS) {
+            return Err(Error::InvalidPacketSignature);
+        }
+
+        let mut packets = Vec::new();
+        for message in self.process_message(&packet.msg, rng)? {
+            packets.push(Packet::new(message, &self.network_private_key)?);
+        }
+
+        Ok(packets)
+    }
+
+    fn process_message<RNG: RngCore + CryptoRng>(
+        &mut self,
+        message: &Message,
+        rng: &mut RNG,
+    ) -> Result<Vec<Message>, Error> {
+        match message {
+            Message::DkgBegin(dkg_begin) => self.dkg_begin(dkg_begin, rng),
+            Message::DkgCommitment(dkg_commitment) => self.dkg_commitment(dkg_commitment),
+            Message::DkgPublicShares(dkg_public_shares) => {
+                self.dkg_public_shares(dkg_public_shares)
+            }
+            Message::DkgPrivateShares(dkg_private_shares) => {
+                self.dkg_private_shares(dkg_private_shares)
+            }
+            Message::DkgEndBegin(dkg_end_begin) => self.dkg_end_begin(dkg_end_begin),
+            Message::DkgEnd(dkg_end) => self.dkg_end(dkg_end),
+            Message::DkgDisclosure(dkg_disclosure) => self.dkg_disclosure(dkg_disclosure),
+            Message::NonceRequest(nonce_request) => self.nonce_request(nonce_request, rng),
+            Message::SignatureShareRequest(sign_request) => {
+                self.signature_share_request(sign_request)
+            }
+            _ => Ok(vec![]),
+        }
+    }
+
+    fn dkg_begin<RNG: RngCore + CryptoRng>(
+        &mut self,
+        dkg_begin: &DkgBegin,
+        rng: &mut RNG,
+    ) -> Result<Vec<Message>, Error> {
+        self.move_to(State::DkgGather)?;
+        self.dkg_id = dkg_begin.dkg_id;
+        self.dkg_signer_ids = None;
+        self.digests.clear();
+        self.reveal.clear();
+        self.commitments.clear();
+        self.shares.clear();
+        self.polys.clear();
+        self.private_shares.clear();
+        self.dkg_end_messages.clear();
+        self.dkg_disclosures.clear();
+        self.verdict = None;
+        self.signer.set_dkg_id(self.dkg_id);
+        self.signer
+            .set_group_key_ids(&self.public_keys.group_key_ids());
+        self.signer.reset_polys(rng);
+
+        let dkg_public_shares = DkgPublicShares {
+            dkg_id: self.dkg_id,
+            signer_id: self.signer.get_id(),
+            comms: self.signer.get_poly_commitments(rng),
+        };
+        let dkg_private_shares = DkgPrivateShares {
+            dkg_id: self.dkg_id,
+            signer_id: self.signer.get_id(),
+            shares: self.signer.get_encrypted_shares(
+                self.dkg_id,
+                &self.network_private_key,
+                &self.public_keys.key_ids,
+                rng,
+            )?,
+        };
+
+        self.commitments
+            .insert(dkg_public_shares.signer_id, dkg_public_shares.comms.clone());
+        self.shares.insert(
+            dkg_private_shares.signer_id,
+            dkg_private_shares.shares.clone(),
+        );
+
+        let mut msgs = vec![
+            Message::DkgPublicShares(dkg_public_shares.clone()),
+            Message::DkgPrivateShares(dkg_private_shares),
+        ];
+        if self.dkg_mode == DkgMode::CommitReveal {
+            // the public shares are only revealed once every signer has committed to its own
+            let dkg_commitment = DkgCommitment {
+                dkg_id: self.dkg_id,
+                signer_id: dkg_public_shares.signer_id,
+                digest: compute::poly_commitments_digest(
+                    self.dkg_id,
+                    dkg_public_shares.signer_id,
+                    &dkg_public_shares.comms,
+                ),
+            };
+            self.digests
+                .insert(dkg_commitment.signer_id, dkg_commitment.digest);
+            self.reveal = msgs;
+            msgs = vec![Message::DkgCommitment(dkg_commitment)];
+            msgs.extend(self.try_dkg_reveal()?);
+            return Ok(msgs);
+        }
+        msgs.extend(self.try_dkg_end()?);
+
+        Ok(msgs)
+    }
+
+    fn dkg_commitment(&mut self, dkg_commitment: &DkgCommitment) -> Result<Vec<Message>, Error> {
+        if self.state != State::DkgGather
+            || self.dkg_mode != DkgMode::CommitReveal
+            || dkg_commitment.dkg_id != self.dkg_id
+            || !self.in_dkg(dkg_commitment.signer_id)
+        {
+            return Ok(vec![]);
+        }
+
+        // a signer cannot re-commit once it has seen the public shares of others
+        let signer_id = dkg_commitment.signer_id;
+        let first = self.digests.get(&signer_id).copied();
+        if !check_first_message(first, signer_id, dkg_commitment.digest)? {
+            return Ok(vec![]);
+        }
+        if self.reveal.is_empty() {
+            return Err(Error::DkgLateCommitment(signer_id));
+        }
+
+        self.digests.insert(signer_id, dkg_commitment.digest);
+
+        self.try_dkg_reveal()
+    }
+
+    fn try_dkg_reveal(&mut self) -> Result<Vec<Message>, Error> {
+        if self.digests.len() < self.dkg_signers() || self.reveal.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut msgs = std::mem::take(&mut self.reveal);
+        msgs.extend(self.try_dkg_end()?);
+
+        Ok(msgs)
+    }
+
+    fn dkg_public_shares(
+        &mut self,
+        dkg_public_shares: &DkgPublicShares,
+    ) -> Result<Vec<Message>, Error> {
+        if self.state != State::DkgGather
+            || dkg_public_shares.dkg_id != self.dkg_id
+            || !self.in_dkg(dkg_public_shares.signer_id)
+        {
+            return Ok(vec![]);
+        }
+
+        let signer_id = dkg_public_shares.signer_id;
+        let first = self
+            .commitments
+            .get(&signer_id)
+            .map(|comms| compute::poly_commitments_digest(self.dkg_id, signer_id, comms));
+        let digest =
+            compute::poly_commitments_digest(self.dkg_id, signer_id, &dkg_public_shares.comms);
+        if !check_first_message(first, signer_id, digest)? {
+            return Ok(vec![]);
+        }
+
+        self.commitments
+            .insert(signer_id, dkg_public_shares.comms.clone());
+
+        self.try_dkg_end()
+    }
+
+    fn dkg_private_shares(
+        &mut self,
+        dkg_private_shares: &DkgPrivateShares,
+    ) -> Result<Vec<Message>, Error> {
+        if self.state != State::DkgGather
+            || dkg_private_shares.dkg_id != self.dkg_id
+            || !self.in_dkg(dkg_private_shares.signer_id)
+        {
+            return Ok(vec![]);
+        }
+
+        self.shares.insert(
+            dkg_private_shares.signer_id,
+            dkg_private_shares.shares.clone(),
+        );
+
+        self.try_dkg_end()
+    }
+
+    fn dkg_end_begin(&mut self, dkg_end_begin: &DkgEndBegin) -> Result<Vec<Message>, Error> {
+        if self.state != State::DkgGather || dkg_end_begin.dkg_id != self.dkg_id {
+            return Ok(vec![]);
+        }
+
+        // stop waiting for absent signers, and ignore any which were left out
+        let mut signer_ids = dkg_end_begin.signer_ids.clone();
+        signer_ids.sort();
+        signer_ids.dedup();
+        self.digests.retain(|id, _| signer_ids.contains(id));
+        self.commitments.retain(|id, _| signer_ids.contains(id));
+        self.shares.retain(|id, _| signer_ids.contains(id));
+        self.dkg_end_messages
+            .retain(|id, _| signer_ids.contains(id));
+        self.dkg_disclosures.retain(|id, _| signer_ids.contains(id));
+        self.dkg_signer_ids = Some(signer_ids);
+
+        if self.dkg_mode == DkgMode::CommitReveal && !self.reveal.is_empty() {
+            return self.try_dkg_reveal();
+        }
+        self.try_dkg_end()
+    }
+
+    /// Check whether `signer_id` takes part in the current DKG round, which is every signer unless the coordinator ended gathering early
+    fn in_dkg(&self, signer_id: u32) -> bool {
+        match &self.dkg_signer_ids {
+            Some(signer_ids) => signer_ids.contains(&signer_id),
+            None => true,
+        }
+    }
+
+    /// Get the number of signers which the current DKG round is waiting on
+    fn dkg_signers(&self) -> usize {
+        match &self.dkg_signer_ids {
+            Some(signer_ids) => signer_ids.len(),
+            None => usize::try_from(self.total_signers).unwrap(),
+        }
+    }
+
+    fn try_dkg_end(&mut self) -> Result<Vec<Message>, Error> {
+        let dkg_signers = self.dkg_signers();
+        if self.commitments.len() < dkg_signers || self.shares.len() < dkg_signers {
+            return Ok(vec![]);
+        }
+        if self.dkg_mode == DkgMode::CommitReveal {
+            // this signer must have seen every commitment and revealed its own public shares
+            if self.digests.len() < dkg_signers || !self.reveal.is_empty() {
+                return Ok(vec![]);
+            }
+            let bad_reveals = find_bad_reveals(self.dkg_id, &self.digests, &self.commitments);
+            if !bad_reveals.is_empty() {
+                return Err(Error::DkgBadReveals(bad_reveals));
+            }
+        }
+
+        let status = match merge_poly_commitments(&self.commitments) {
+            Ok(polys) => {
+                self.polys = polys;
+                self.private_shares = self.decrypt_shares();
+
+                let complaints = self.find_bad_shares();
+                if !complaints.is_empty() {
+                    DkgStatus::BadShares(complaints)
+                } else {
+                    match self.compute_dkg_secrets(&[]) {
+                        Ok(()) => DkgStatus::Success,
+                        Err(dkg_errors) => DkgStatus::Failure(format!("{:?}", dkg_errors)),
+                    }
+                }
+            }
+            Err(e) => DkgStatus::Failure(format!("{:?}", e)),
+        };
+
+        self.move_to(State::DkgEndGather)?;
+
+        let dkg_end = DkgEnd {
+            dkg_id: self.dkg_id,
+            signer_id: self.signer.get_id(),
+            status,
+        };
+        if self.in_dkg(dkg_end.signer_id) {
+            self.dkg_end_messages
+                .insert(dkg_end.signer_id, dkg_end.clone());
+        }
+
+        let mut msgs = vec![Message::DkgEnd(dkg_end)];
+        msgs.extend(self.try_dkg_disclosure()?);
+
+        Ok(msgs)
+    }
+
+    /// Compute this signer's secrets from the gathered private shares and poly commitments, leaving out the parties of any `cheaters`
+    ///
+    /// DKG finishes over a qualified set of parties if the coordinator ended gathering early or any party cheated
+    fn compute_dkg_secrets(&mut self, cheaters: &[u32]) -> Result<(), HashMap<u32, DkgError>> {
+        if self.dkg_signer_ids.is_none() && cheaters.is_empty() {
+            return self
+                .signer
+                .compute_secrets(&self.private_shares, &self.polys);
+        }
+
+        let polys: HashMap<u32, PolyCommitment> = self
+            .polys
+            .iter()
+            .filter(|(party_id, _)| !cheaters.contains(party_id))
+            .map(|(party_id, poly)| (*party_id, poly.clone()))
+            .collect();
+        let party_ids: Vec<u32> = polys.keys().cloned().collect();
+        let qualified = self
+            .public_keys
+            .selected_parties(&party_ids, Signer::PARTY_IDS);
+
+        self.signer
+            .compute_secrets_qualified(&qualified, &self.private_shares, &polys)
+    }
+
+    /// Decrypt the private shares sent to this signer's keys, skipping any which fail to decrypt or come from a party which the sending signer does not own
+    fn decrypt_shares(&self) -> HashMap<u32, HashMap<u32, Scalar>> {
+        let key_ids = self.signer.get_key_ids();
+        let mut private_shares = HashMap::new();
+
+        for (signer_id, signer_shares) in &self.shares {
+            let Some(public_key) = self.public_keys.signers.get(signer_id) else {
+                continue;
+            };
+            for (party_id, party_shares) in signer_shares {
+                if !self
+                    .public_keys
+                    .owns_party(*signer_id, *party_id, Signer::PARTY_IDS)
+                {
+                    continue;
+                }
+
+                let mut key_shares = HashMap::new();
+                for key_id in &key_ids {
+                    if let Some(Ok(share)) = party_shares.get(key_id).map(|data| {
+                        decrypt_share(
+                            data,
+                            self.dkg_id,
+                            *signer_id,
+                            *party_id,
+                            *key_id,
+                            &self.network_private_key,
+                            public_key,
+                        )
+                    }) {
+                        key_shares.insert(*key_id, share);
+                    }
+                }
+                private_shares.insert(*party_id, key_shares);
+            }
+        }
+
+        private_shares
+    }
+
+    /// Complain about every share for this signer's keys which is missing or does not match its party's poly commitment
+    fn find_bad_shares(&self) -> Vec<DkgComplaint> {
+        let key_ids = self.signer.get_key_ids();
+        let mut complaints = Vec::new();
+
+        for (party_id, poly) in &self.polys {
+            for key_id in &key_ids {
+                let share = self
+                    .private_shares
+                    .get(party_id)
+                    .and_then(|key_shares| key_shares.get(key_id));
+                if !matches!(share, Some(share) if poly.verify_share(*key_id, share)) {
+                    complaints.push(DkgComplaint {
+                        party_id: *party_id,
+                        key_id: *key_id,
+                    });
+                }
+            }
+        }
+
+        complaints.sort();
+        complaints
+    }
+
+    fn dkg_end(&mut self, dkg_end: &DkgEnd) -> Result<Vec<Message>, Error> {
+        // other signers may finish gathering shares before this one does
+        if !matches!(self.state, State::DkgGather | State::DkgEndGather)
+            || dkg_end.dkg_id != self.dkg_id
+            || !self.in_dkg(dkg_end.signer_id)
+        {
+            return Ok(vec![]);
+        }
+
+        self.dkg_end_messages
+            .insert(dkg_end.signer_id, dkg_end.clone());
+
+        self.try_dkg_disclosure()
+    }
+
+    fn try_dkg_disclosure(&mut self) -> Result<Vec<Message>, Error> {
+        if self.state != State::DkgEndGather || self.dkg_end_messages.len() < self.dkg_signers() {
+            return Ok(vec![]);
+        }
+
+        let complaints = collect_complaints(self.dkg_end_messages.values());
+        if complaints.is_empty() {
+            self.move_to(State::Idle)?;
+            return Ok(vec![]);
+        }
+
+        self.move_to(State::DkgDisclosureGather)?;
+
+        let shares = self.signer.get_shares();
+        let disclosed_shares: Vec<DisclosedShare> = complaints
+            .iter()
+            .filter_map(|complaint| {
+                shares
+                    .get(&complaint.party_id)
+                    .and_then(|party_shares| party_shares.get(&complaint.key_id))
+                    .map(|share| DisclosedShare {
+                        party_id: complaint.party_id,
+                        key_id: complaint.key_id,
+                        share: *share,
+                    })
+            })
+            .collect();
+
+        let mut msgs = Vec::new();
+        if !disclosed_shares.is_empty() {
+            let dkg_disclosure = DkgDisclosure {
+                dkg_id: self.dkg_id,
+                signer_id: self.signer.get_id(),
+                shares: disclosed_shares,
+            };
+            if self.in_dkg(dkg_disclosure.signer_id) {
+                self.dkg_disclosures
+                    .insert(dkg_disclosure.signer_id, dkg_disclosure.clone());
+            }
+            msgs.push(Message::DkgDisclosure(dkg_disclosure));
+        }
+        msgs.extend(self.try_dkg_verdict()?);
+
+        Ok(msgs)
+    }
+
+    fn dkg_disclosure(&mut self, dkg_disclosure: &DkgDisclosure) -> Result<Vec<Message>, Error> {
+        // accused signers may disclose before this signer has gathered every DKG end message
+        if !matches!(
+            self.state,
+            State::DkgGather | State::DkgEndGather | State::DkgDisclosureGather
+        ) || dkg_disclosure.dkg_id != self.dkg_id
+            || !self.in_dkg(dkg_disclosure.signer_id)
+        {
+            return Ok(vec![]);
+        }
+
+        self.dkg_disclosures
+            .insert(dkg_disclosure.signer_id, dkg_disclosure.clone());
+
+        self.try_dkg_verdict()
+    }
+
+    fn try_dkg_verdict(&mut self) -> Result<Vec<Message>, Error> {
+        if self.state != State::DkgDisclosureGather {
+            return Ok(vec![]);
+        }
+
+        let complaints = collect_complaints(self.dkg_end_messages.values());
+        let Some(verdict) = judge(
+            &complaints,
+            &self.commitments,
+            &self.dkg_disclosures,
+            &self.polys,
+        ) else {
+            return Ok(vec![]);
+        };
+
+        self.move_to(State::Idle)?;
+
+        // the disclosed shares replace the bad ones, and DKG finishes without any parties which cheated
+        let signer_id = self.signer.get_id();
+        let complained = matches!(
+            self.dkg_end_messages.get(&signer_id),
+            Some(DkgEnd {
+                status: DkgStatus::BadShares(_),
+                ..
+            })
+        );
+        if complained || !verdict.cheaters.is_empty() {
+            let key_ids = self.signer.get_key_ids();
+            for disclosed_share in &verdict.resolved {
+                if key_ids.contains(&disclosed_share.key_id) {
+                    self.private_shares
+                        .entry(disclosed_share.party_id)
+                        .or_default()
+                        .insert(disclosed_share.key_id, disclosed_share.share);
+                }
+            }
+
+            self.compute_dkg_secrets(&verdict.cheaters)
+                .map_err(Error::Dkg)?;
+        }
+        self.verdict = Some(verdict);
+
+        Ok(vec![])
+    }
+
+    fn nonce_request<RNG: RngCore + CryptoRng>(
+        &mut self,
+        nonce_request: &NonceRequest,
+        rng: &mut RNG,
+    ) -> Result<Vec<Message>, Error> {
+        if nonce_request.dkg_id != self.dkg_id {
+            return Ok(vec![]);
+        }
+
+        self.sign_id = nonce_request.sign_id;
+        self.sign_iter_id = nonce_request.sign_iter_id;
+        // the coordinator's sign ID is the session ID which every signer binds into its signature share
+        self.nonce_session = self.sign_id;
+        self.public_nonces = self.signer.gen_session_nonces(self.sign_id, rng);
+
+        Ok(vec![Message::NonceResponse(NonceResponse {
+            dkg_id: self.dkg_id,
+            sign_id: self.sign_id,
+            sign_iter_id: self.sign_iter_id,
+            signer_id: self.signer.get_id(),
+            key_ids: self.signer.get_key_ids(),
+            nonces: self.public_nonces.clone(),
+        })])
+    }
+
+    fn signature_share_request(
+        &mut self,
+        sign_request: &SignatureShareRequest,
+    ) -> Result<Vec<Message>, Error> {
+        if sign_request.dkg_id != self.dkg_id
+            || sign_request.sign_id != self.sign_id
+            || sign_request.sign_iter_id != self.sign_iter_id
+        {
+            return Ok(vec![]);
+        }
+
+        let signer_id = self.signer.get_id();
+        let Some(nonce_response) = sign_request
+            .nonce_responses
+            .iter()
+            .find(|nr| nr.signer_id == signer_id)
+        else {
+            // this signer was not selected for the signing round
+            return Ok(vec![]);
+        };
+        if self.public_nonces.is_empty() || nonce_response.nonces != self.public_nonces {
+            return Err(Error::NonceMismatch);
+        }
+
+        let signer_ids: Vec<u32> = sign_request
+            .nonce_responses
+            .iter()
+            .map(|nr| nr.signer_id)
+            .collect();
+        let key_ids: Vec<u32> = sign_request
+            .nonce_responses
+            .iter()
+            .flat_map(|nr| nr.key_ids.clone())
+            .collect();
+        let nonces: Vec<PublicNonce> = sign_request
+            .nonce_responses
+            .iter()
+            .flat_map(|nr| nr.nonces.clone())
+            .collect();
+
+        let signature_shares = self.signer.sign(
+            self.nonce_session,
+            &sign_request.message,
+            &signer_ids,
+            &key_ids,
+            &nonces,
+        );
+
+        // each set of public nonces can only be used for a single signature
+        self.public_nonces.clear();
+        let signature_shares = signature_shares?;
+
+        Ok(vec![Message::SignatureShareResponse(
+            SignatureShareResponse {
+                dkg_id: self.dkg_id,
+                sign_id: self.sign_id,
+                sign_iter_id: self.sign_iter_id,
+                signer_id,
+                signature_shares,
+            },
+        )])
+    }
+}
+
+impl<Signer: SignerTrait> StateMachine<State, Error> for SigningRound<Signer> {
+    fn move_to(&mut self, state: State) -> Result<(), Error> {
+        self.can_move_to(&state)?;
+        self.state = state;
+        Ok(())
+    }
+
+    fn can_move_to(&self, state: &State) -> Result<(), Error> {
+        let prev_state = &self.state;
+        let accepted = match state {
+            State::Idle => {
+                prev_state == &State::DkgEndGather || prev_state == &State::DkgDisclosureGather
+            }
+            State::DkgGather => true,
+            State::DkgEndGather => prev_state == &State::DkgGather,
+            State::DkgDisclosureGather => prev_state == &State::DkgEndGather,
+        };
+        if accepted {
+            Ok(())
+        } else {
+            Err(Error::BadStateChange(format!(
+                "{:?} to {:?}",
+                prev_state, state
+            )))
+        }
+    }
+}
@@ -3,20 +3,32 @@ use p256k1::{point::Point, scalar::Scalar};
 use rand_core::{CryptoRng, RngCore};
 
 use crate::{
-    common::{PolyCommitment, PublicNonce, SignatureShare},
-    errors::DkgError,
+    common::{
+        Nonce, PartyIds, PolyCommitment, ProtocolVersion, PublicNonce, SelectedSigners, SessionId,
+        Signature, SignatureShare,
+    },
+    errors::{AggregatorError, DkgError, EncryptionError, NonceStoreError, SignError},
+    schnorr::DkgContext,
+    state_machine::PublicKeys,
+    util::{decrypt_share, encrypt_share},
 };
 
 /// A trait which provides a common interface for `v1` and `v2`
 pub trait Signer {
+    /// How the party IDs of this signer are assigned
+    const PARTY_IDS: PartyIds;
+
     /// Get the signer ID for this signer
     fn get_id(&self) -> u32;
 
     /// Get all key IDs for this signer
     fn get_key_ids(&self) -> Vec<u32>;
 
-    /// Get all poly commitments for this signer
-    fn get_poly_commitments<RNG: RngCore + CryptoRng>(&self, rng: &mut RNG) -> Vec<PolyCommitment>;
+    /// Get all poly commitments for this signer, indexed by party ID
+    fn get_poly_commitments<RNG: RngCore + CryptoRng>(
+        &self,
+        rng: &mut RNG,
+    ) -> HashMap<u32, PolyCommitment>;
 
     /// Reset all poly commitments for this signer
     fn reset_polys<RNG: RngCore + CryptoRng>(&mut self, rng: &mut RNG);
@@ -24,17 +36,168 @@ pub trait Signer {
     /// Get all private shares for this signer
     fn get_shares(&self) -> HashMap<u32, HashMap<u32, Scalar>>;
 
-    /// Compute all secrets for this signer
+    /// Compute all secrets for this signer from the shares and poly commitments, both indexed by sending party ID
     fn compute_secrets(
         &mut self,
         shares: &HashMap<u32, HashMap<u32, Scalar>>,
-        polys: &[PolyCommitment],
+        polys: &HashMap<u32, PolyCommitment>,
+    ) -> Result<(), HashMap<u32, DkgError>>;
+
+    /// Compute all secrets for this signer from the shares and poly commitments of the `qualified` set of parties, both indexed by party ID, ignoring shares from any other party
+    ///
+    /// The qualified set must hold at least as many keys as the threshold
+    fn compute_secrets_qualified(
+        &mut self,
+        qualified: &SelectedSigners,
+        shares: &HashMap<u32, HashMap<u32, Scalar>>,
+        polys: &HashMap<u32, PolyCommitment>,
     ) -> Result<(), HashMap<u32, DkgError>>;
 
-    /// Generate all nonces for this signer
-    fn gen_nonces<RNG: RngCore + CryptoRng>(&mut self, rng: &mut RNG) -> Vec<PublicNonce>;
+    /// Reset all polynomials for this signer to ones with a zero constant term, for a share refresh round
+    fn reset_refresh_polys<RNG: RngCore + CryptoRng>(&mut self, rng: &mut RNG);
+
+    /// Add the refresh shares to all secrets for this signer, leaving the group key unchanged
+    fn refresh_secrets(
+        &mut self,
+        shares: &HashMap<u32, HashMap<u32, Scalar>>,
+        polys: &HashMap<u32, PolyCommitment>,
+    ) -> Result<(), HashMap<u32, DkgError>>;
+
+    /// Get all private shares for this signer in DKG round `dkg_id`, each encrypted with `private_key` to the public key of the signer which owns the receiving key ID
+    fn get_encrypted_shares<RNG: RngCore + CryptoRng>(
+        &self,
+        dkg_id: u64,
+        private_key: &Scalar,
+        key_public_keys: &HashMap<u32, Point>,
+        rng: &mut RNG,
+    ) -> Result<HashMap<u32, HashMap<u32, Vec<u8>>>, EncryptionError> {
+        let signer_id = self.get_id();
+        let mut encrypted_shares = HashMap::new();
+        for (party_id, party_shares) in self.get_shares() {
+            let mut encrypted_party_shares = HashMap::new();
+            for (key_id, share) in party_shares {
+                let public_key = key_public_keys
+                    .get(&key_id)
+                    .ok_or(EncryptionError::MissingPublicKey(key_id))?;
+                let encrypted_share = encrypt_share(
+                    &share,
+                    dkg_id,
+                    signer_id,
+                    party_id,
+                    key_id,
+                    private_key,
+                    public_key,
+                    rng,
+                )?;
+                encrypted_party_shares.insert(key_id, encrypted_share);
+            }
+            encrypted_shares.insert(party_id, encrypted_party_shares);
+        }
+        Ok(encrypted_shares)
+    }
 
-    /// Compute intermediate values
+    /// Decrypt the shares sent to this signer's keys in DKG round `dkg_id`, which are indexed by sending signer ID, then verify them and compute all secrets
+    ///
+    /// Shares are only accepted from parties which belong to the sending signer in `public_keys`
+    fn compute_secrets_encrypted(
+        &mut self,
+        dkg_id: u64,
+        encrypted_shares: &HashMap<u32, HashMap<u32, HashMap<u32, Vec<u8>>>>,
+        private_key: &Scalar,
+        public_keys: &PublicKeys,
+        polys: &HashMap<u32, PolyCommitment>,
+    ) -> Result<(), HashMap<u32, DkgError>> {
+        let key_ids = self.get_key_ids();
+        let mut private_shares = HashMap::new();
+        let mut bad_signers = Vec::new();
+
+        for (signer_id, signer_shares) in encrypted_shares {
+            let public_key = match public_keys.signers.get(signer_id) {
+                Some(public_key) => public_key,
+                None => {
+                    bad_signers.push(*signer_id);
+                    continue;
+                }
+            };
+
+            for (party_id, party_shares) in signer_shares {
+                if !public_keys.owns_party(*signer_id, *party_id, Self::PARTY_IDS) {
+                    bad_signers.push(*signer_id);
+                    continue;
+                }
+
+                let mut shares = HashMap::new();
+                for key_id in &key_ids {
+                    let share = party_shares.get(key_id).and_then(|encrypted_share| {
+                        decrypt_share(
+                            encrypted_share,
+                            dkg_id,
+                            *signer_id,
+                            *party_id,
+                            *key_id,
+                            private_key,
+                            public_key,
+                        )
+                        .ok()
+                    });
+                    match share {
+                        Some(share) => {
+                            shares.insert(*key_id, share);
+                        }
+                        None => bad_signers.push(*signer_id),
+                    }
+                }
+                private_shares.insert(*party_id, shares);
+            }
+        }
+
+        if !bad_signers.is_empty() {
+            bad_signers.sort();
+            bad_signers.dedup();
+
+            let mut dkg_errors = HashMap::new();
+            dkg_errors.insert(self.get_id(), DkgError::BadEncryptedShares(bad_signers));
+            return Err(dkg_errors);
+        }
+
+        self.compute_secrets(&private_shares, polys)
+    }
+
+    /// Generate all nonces for this signer for the signing session `session_id`, which every signer in the session and the aggregator must share under `ProtocolVersion::V1`
+    fn gen_session_nonces<RNG: RngCore + CryptoRng>(
+        &mut self,
+        session_id: SessionId,
+        rng: &mut RNG,
+    ) -> Vec<PublicNonce>;
+
+    /// Generate all nonces for this signer for the signing session `session_id` and durably record them as issued in `store`
+    fn gen_session_nonces_with_store<S: NonceStore, RNG: RngCore + CryptoRng>(
+        &mut self,
+        session_id: SessionId,
+        store: &mut S,
+        rng: &mut RNG,
+    ) -> Result<Vec<PublicNonce>, NonceStoreError>;
+
+    /// Generate and store `count` preprocessed nonces for this signer, returning each index with the public nonces for it
+    fn preprocess_nonces<RNG: RngCore + CryptoRng>(
+        &mut self,
+        count: u32,
+        rng: &mut RNG,
+    ) -> Vec<(u32, Vec<PublicNonce>)>;
+
+    /// Get each index of this signer's preprocessed nonces which have not yet been used, with the public nonces for it
+    fn preprocessed_nonces(&self) -> Vec<(u32, Vec<PublicNonce>)>;
+
+    /// Set the signing protocol version, which every signer and the aggregator must agree on
+    fn set_protocol_version(&mut self, version: ProtocolVersion);
+
+    /// Set the ID of the current DKG round, which this signer's proofs of possession commit to under `ProtocolVersion::V1`
+    fn set_dkg_id(&mut self, dkg_id: u64);
+
+    /// Set the IDs of every key in the group, which this signer deals shares to
+    fn set_group_key_ids(&mut self, key_ids: &[u32]);
+
+    /// Compute intermediate values using the `ProtocolVersion::V0` binding factor
     fn compute_intermediate(
         msg: &[u8],
         signer_ids: &[u32],
@@ -42,12 +205,158 @@ pub trait Signer {
         nonces: &[PublicNonce],
     ) -> (Vec<Point>, Point);
 
-    /// Sign `msg` using all this signer's keys
+    /// Sign `msg` using all this signer's keys and its nonces for `session_id`
+    ///
+    /// This consumes the signer's nonces for `session_id`, so each session can only sign once
     fn sign(
-        &self,
+        &mut self,
+        session_id: SessionId,
+        msg: &[u8],
+        signer_ids: &[u32],
+        key_ids: &[u32],
+        nonces: &[PublicNonce],
+    ) -> Result<Vec<SignatureShare>, SignError>;
+
+    /// Sign `msg` for the BIP-341 output key of the group key and an optional script `merkle_root`, using the passed signer and key IDs and nonces, and this signer's nonces for `session_id`
+    fn sign_taproot(
+        &mut self,
+        session_id: SessionId,
         msg: &[u8],
         signer_ids: &[u32],
         key_ids: &[u32],
         nonces: &[PublicNonce],
-    ) -> Vec<SignatureShare>;
+        merkle_root: Option<[u8; 32]>,
+    ) -> Result<Vec<SignatureShare>, SignError>;
+
+    /// Sign `msg` like `sign`, but with this signer's nonces issued in `store` for `session_id`, which are durably marked as consumed before signing
+    fn sign_with_store<S: NonceStore>(
+        &mut self,
+        store: &mut S,
+        session_id: SessionId,
+        msg: &[u8],
+        signer_ids: &[u32],
+        key_ids: &[u32],
+        nonces: &[PublicNonce],
+    ) -> Result<Vec<SignatureShare>, SignError>;
+
+    /// Sign `msg` like `sign_taproot`, but with this signer's nonces issued in `store` for `session_id`, which are durably marked as consumed before signing
+    #[allow(clippy::too_many_arguments)]
+    fn sign_taproot_with_store<S: NonceStore>(
+        &mut self,
+        store: &mut S,
+        session_id: SessionId,
+        msg: &[u8],
+        signer_ids: &[u32],
+        key_ids: &[u32],
+        nonces: &[PublicNonce],
+        merkle_root: Option<[u8; 32]>,
+    ) -> Result<Vec<SignatureShare>, SignError>;
+
+    /// Sign `msg` like `sign` in the signing session `session_id`, but with this signer's preprocessed nonces at `index`, which can never be used again
+    ///
+    /// The index only selects the nonces; `session_id` is what the aggregator must check the signature shares against, so every signer must pass the same one under `ProtocolVersion::V1`
+    fn sign_preprocessed(
+        &mut self,
+        index: u32,
+        session_id: SessionId,
+        msg: &[u8],
+        signer_ids: &[u32],
+        key_ids: &[u32],
+        nonces: &[PublicNonce],
+    ) -> Result<Vec<SignatureShare>, SignError>;
+
+    /// Sign `msg` like `sign_taproot` in the signing session `session_id`, but with this signer's preprocessed nonces at `index`, which can never be used again
+    #[allow(clippy::too_many_arguments)]
+    fn sign_taproot_preprocessed(
+        &mut self,
+        index: u32,
+        session_id: SessionId,
+        msg: &[u8],
+        signer_ids: &[u32],
+        key_ids: &[u32],
+        nonces: &[PublicNonce],
+        merkle_root: Option<[u8; 32]>,
+    ) -> Result<Vec<SignatureShare>, SignError>;
+}
+
+/// A trait which provides a common interface for the `v1` and `v2` signature aggregators
+pub trait Aggregator: Sized {
+    /// How the party IDs of the signers are assigned
+    const PARTY_IDS: PartyIds;
+
+    /// Construct an Aggregator with the passed parameters and polynomial commitments, indexed by party ID
+    #[allow(non_snake_case)]
+    fn new(
+        num_keys: u32,
+        threshold: u32,
+        A: &HashMap<u32, PolyCommitment>,
+    ) -> Result<Self, AggregatorError>;
+
+    /// Construct an Aggregator for the group of the DKG round `ctx`, whose keys are `key_ids`, from the polynomial commitments made in it, indexed by party ID
+    #[allow(non_snake_case)]
+    fn new_with_context(
+        ctx: &DkgContext,
+        key_ids: &[u32],
+        A: &HashMap<u32, PolyCommitment>,
+    ) -> Result<Self, AggregatorError>;
+
+    /// Construct an Aggregator for the group of the DKG round `ctx`, whose keys are `key_ids`, from the `qualified` set of parties and their polynomial commitments, indexed by party ID
+    #[allow(non_snake_case)]
+    fn new_qualified(
+        ctx: &DkgContext,
+        key_ids: &[u32],
+        qualified: &SelectedSigners,
+        A: &HashMap<u32, PolyCommitment>,
+    ) -> Result<Self, AggregatorError>;
+
+    /// Get the aggregate group polynomial; poly[0] is the group public key
+    fn get_poly(&self) -> &[Point];
+
+    /// Get the public key of each key ID, indexed by key ID
+    fn get_public_keys(&self) -> &HashMap<u32, Point>;
+
+    /// Add the refresh poly commitments, indexed by party ID, to the group polynomial, leaving the group public key unchanged
+    #[allow(non_snake_case)]
+    fn refresh(&mut self, A: &HashMap<u32, PolyCommitment>) -> Result<(), AggregatorError>;
+
+    /// Set the signing protocol version, which every signer must agree on
+    fn set_protocol_version(&mut self, version: ProtocolVersion);
+
+    /// Set the IDs of every key in the group, whose public keys signature shares are checked against
+    fn set_group_key_ids(&mut self, key_ids: &[u32]);
+
+    /// Check and aggregate the signature shares from the signers using `key_ids` in signing session `session_id`
+    fn sign(
+        &mut self,
+        session_id: SessionId,
+        msg: &[u8],
+        nonces: &[PublicNonce],
+        sig_shares: &[SignatureShare],
+        key_ids: &[u32],
+    ) -> Result<Signature, AggregatorError>;
+
+    /// Check and aggregate the signature shares from the signers using `key_ids` in signing session `session_id`, for the BIP-341 output key of the group key and an optional script `merkle_root`
+    fn sign_taproot(
+        &mut self,
+        session_id: SessionId,
+        msg: &[u8],
+        nonces: &[PublicNonce],
+        sig_shares: &[SignatureShare],
+        key_ids: &[u32],
+        merkle_root: Option<[u8; 32]>,
+    ) -> Result<Signature, AggregatorError>;
+}
+
+/// A durable record of the nonces issued to parties and consumed by them, so that no nonce is used twice even if a party restarts mid-round
+pub trait NonceStore {
+    /// Durably record `nonce` as issued to `party_id` for `session_id`; this must return before the public nonce leaves the process
+    fn issue(
+        &mut self,
+        party_id: u32,
+        session_id: SessionId,
+        nonce: &Nonce,
+    ) -> Result<(), NonceStoreError>;
+
+    /// Durably mark the nonce issued to `party_id` for `session_id` as consumed and return it; this must return before any signature share made with it exists
+    fn consume(&mut self, party_id: u32, session_id: SessionId) -> Result<Nonce, NonceStoreError>;
 }
@@ -1,6 +1,16 @@
-use p256k1::scalar::Scalar;
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use p256k1::{point::Point, scalar::Scalar};
+use rand_core::{CryptoRng, RngCore};
 use sha2::{Digest, Sha256};
 
+use crate::errors::EncryptionError;
+
+/// Size of the AES-GCM nonce which is prepended to each ciphertext
+pub const AES_GCM_NONCE_SIZE: usize = 12;
+
 #[allow(dead_code)]
 /// Digest the hasher to a Scalar
 pub fn hash_to_scalar(hasher: &mut Sha256) -> Scalar {
@@ -11,3 +21,186 @@ pub fn hash_to_scalar(hasher: &mut Sha256) -> Scalar {
 
     Scalar::from(hash_bytes)
 }
+
+/// Derive a symmetric key from the ECDH shared secret of `private_key` and `public_key`
+pub fn make_shared_secret(private_key: &Scalar, public_key: &Point) -> [u8; 32] {
+    let shared_point = private_key * public_key;
+    let mut hasher = Sha256::new();
+
+    hasher.update("WSTS/shared_secret".as_bytes());
+    hasher.update(shared_point.compress().as_bytes());
+
+    let hash = hasher.finalize();
+    let mut key: [u8; 32] = [0; 32];
+    key.clone_from_slice(hash.as_slice());
+
+    key
+}
+
+/// Encrypt `data` and authenticate it along with `aad` using AES-256-GCM, returning the nonce prepended to the ciphertext
+pub fn encrypt<RNG: RngCore + CryptoRng>(
+    key: &[u8; 32],
+    data: &[u8],
+    aad: &[u8],
+    rng: &mut RNG,
+) -> Result<Vec<u8>, EncryptionError> {
+    let mut nonce_bytes = [0u8; AES_GCM_NONCE_SIZE];
+    rng.fill_bytes(&mut nonce_bytes);
+
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| EncryptionError::BadKeyLen)?;
+    let nonce = Nonce::from_slice(&nonce_bytes);
+    let cipher_text = cipher
+        .encrypt(nonce, Payload { msg: data, aad })
+        .map_err(|_| EncryptionError::EncryptionFailed)?;
+
+    let mut bytes = Vec::with_capacity(AES_GCM_NONCE_SIZE + cipher_text.len());
+    bytes.extend_from_slice(&nonce_bytes);
+    bytes.extend_from_slice(&cipher_text);
+
+    Ok(bytes)
+}
+
+/// Decrypt and authenticate `data` along with `aad`, where `data` was produced by `encrypt`
+pub fn decrypt(key: &[u8; 32], data: &[u8], aad: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+    if data.len() < AES_GCM_NONCE_SIZE {
+        return Err(EncryptionError::CiphertextTooShort);
+    }
+
+    let (nonce_bytes, cipher_text) = data.split_at(AES_GCM_NONCE_SIZE);
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| EncryptionError::BadKeyLen)?;
+    let nonce = Nonce::from_slice(nonce_bytes);
+
+    cipher
+        .decrypt(
+            nonce,
+            Payload {
+                msg: cipher_text,
+                aad,
+            },
+        )
+        .map_err(|_| EncryptionError::DecryptionFailed)
+}
+
+/// Compute the associated data which binds an encrypted share to its DKG round, sending signer and party, and receiving key
+fn share_aad(dkg_id: u64, signer_id: u32, party_id: u32, key_id: u32) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(20);
+    aad.extend_from_slice(&dkg_id.to_be_bytes());
+    aad.extend_from_slice(&signer_id.to_be_bytes());
+    aad.extend_from_slice(&party_id.to_be_bytes());
+    aad.extend_from_slice(&key_id.to_be_bytes());
+    aad
+}
+
+#[allow(clippy::too_many_arguments)]
+/// Encrypt a private share in DKG round `dkg_id` from `party_id` of `signer_id` to the owner of `key_id`
+pub fn encrypt_share<RNG: RngCore + CryptoRng>(
+    share: &Scalar,
+    dkg_id: u64,
+    signer_id: u32,
+    party_id: u32,
+    key_id: u32,
+    private_key: &Scalar,
+    public_key: &Point,
+    rng: &mut RNG,
+) -> Result<Vec<u8>, EncryptionError> {
+    let key = make_shared_secret(private_key, public_key);
+
+    encrypt(
+        &key,
+        &share.to_bytes(),
+        &share_aad(dkg_id, signer_id, party_id, key_id),
+        rng,
+    )
+}
+
+/// Decrypt a private share in DKG round `dkg_id` from `party_id` of `signer_id` to the owner of `key_id`
+pub fn decrypt_share(
+    data: &[u8],
+    dkg_id: u64,
+    signer_id: u32,
+    party_id: u32,
+    key_id: u32,
+    private_key: &Scalar,
+    public_key: &Point,
+) -> Result<Scalar, EncryptionError> {
+    let key = make_shared_secret(private_key, public_key);
+    let plain_text = decrypt(&key, data, &share_aad(dkg_id, signer_id, party_id, key_id))?;
+
+    let bytes: [u8; 32] = plain_text
+        .as_slice()
+        .try_into()
+        .map_err(|_| EncryptionError::BadPlaintextLen(plain_text.len()))?;
+
+    Ok(Scalar::from(bytes))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decrypt_share, encrypt_share};
+    use crate::{Scalar, G};
+
+    use rand_core::OsRng;
+
+    #[test]
+    fn encrypt_decrypt_share() {
+        let mut rng = OsRng::default();
+        let share = Scalar::random(&mut rng);
+        let sender_private_key = Scalar::random(&mut rng);
+        let receiver_private_key = Scalar::random(&mut rng);
+        let sender_public_key = sender_private_key * G;
+        let receiver_public_key = receiver_private_key * G;
+
+        let encrypted_share = encrypt_share(
+            &share,
+            7,
+            4,
+            1,
+            2,
+            &sender_private_key,
+            &receiver_public_key,
+            &mut rng,
+        )
+        .unwrap();
+
+        let decrypted_share = decrypt_share(
+            &encrypted_share,
+            7,
+            4,
+            1,
+            2,
+            &receiver_private_key,
+            &sender_public_key,
+        )
+        .unwrap();
+        assert_eq!(share, decrypted_share);
+
+        // the share is bound to the DKG round, sending signer and party, and receiving key
+        for (dkg_id, signer_id, party_id, key_id) in
+            [(8, 4, 1, 2), (7, 5, 1, 2), (7, 4, 0, 2), (7, 4, 1, 3)]
+        {
+            assert!(decrypt_share(
+                &encrypted_share,
+                dkg_id,
+                signer_id,
+                party_id,
+                key_id,
+                &receiver_private_key,
+                &sender_public_key
+            )
+            .is_err());
+        }
+
+        // only the receiver can decrypt the share
+        let eavesdropper_private_key = Scalar::random(&mut rng);
+        assert!(decrypt_share(
+            &encrypted_share,
+            7,
+            4,
+            1,
+            2,
+            &eavesdropper_private_key,
+            &sender_public_key
+        )
+        .is_err());
+    }
+}
@@ -4,26 +4,45 @@ use p256k1::{
     point::{Point, G},
     scalar::Scalar,
 };
-use polynomial::Polynomial;
 use rand_core::{CryptoRng, RngCore};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
-use crate::common::{Nonce, PolyCommitment, PublicNonce, Signature, SignatureShare};
+use crate::common::{
+    Nonce, NonceBatch, NonceSessions, PartyIds, PolyCommitment, ProtocolVersion, PublicNonce,
+    SecretPoly, SecretScalar, SelectedSigners, SessionId, Signature, SignatureShare,
+};
 use crate::compute;
-use crate::errors::{AggregatorError, DkgError};
-use crate::schnorr::ID;
+use crate::errors::{AggregatorError, DkgError, NonceStoreError, SignError};
+use crate::reshare;
+use crate::schnorr::{DkgContext, ID};
+use crate::traits::NonceStore;
 use crate::vss::VSS;
 
 #[derive(Debug, Deserialize, Serialize)]
 /// The saved state required to construct a party
 pub struct PartyState {
     /// The party's private key
-    pub private_key: Scalar,
+    pub private_key: SecretScalar,
     /// The party's private polynomial
-    pub polynomial: Polynomial<Scalar>,
+    pub polynomial: SecretPoly,
+    /// The party's preprocessed nonces which have not yet been used, so only the latest saved state may be loaded, as `NonceBatch` explains
+    #[serde(default)]
+    pub preprocessed: NonceBatch,
+    /// The signing protocol version of the party's group
+    #[serde(default)]
+    pub protocol_version: ProtocolVersion,
+    /// The ID of the party's current DKG round
+    #[serde(default)]
+    pub dkg_id: u64,
+    /// The IDs of every key in the party's group, which are `0..n` if empty
+    #[serde(default)]
+    pub group_key_ids: Vec<u32>,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug)]
 #[allow(non_snake_case)]
 /// A FROST party, which encapsulates a single polynomial, nonce, and key
 pub struct Party {
@@ -32,14 +51,38 @@ pub struct Party {
     /// The public key
     pub public_key: Point,
     /// The polynomial used for Lagrange interpolation
-    pub f: Polynomial<Scalar>,
+    pub f: SecretPoly,
     n: u32,
-    private_key: Scalar,
+    group_key_ids: Vec<u32>,
+    private_key: SecretScalar,
     /// The aggregate group public key
     pub group_key: Point,
-    nonce: Nonce,
+    nonces: NonceSessions,
+    preprocessed: NonceBatch,
+    protocol_version: ProtocolVersion,
+    dkg_id: u64,
+    lagrange: compute::LagrangeCache,
+}
+
+/// Parties are equal if their state is, regardless of what their Lagrange caches hold
+impl PartialEq for Party {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+            && self.public_key == other.public_key
+            && self.f == other.f
+            && self.n == other.n
+            && self.group_key_ids == other.group_key_ids
+            && self.private_key == other.private_key
+            && self.group_key == other.group_key
+            && self.nonces == other.nonces
+            && self.preprocessed == other.preprocessed
+            && self.protocol_version == other.protocol_version
+            && self.dkg_id == other.dkg_id
+    }
 }
 
+impl Eq for Party {}
+
 impl Party {
     #[allow(non_snake_case)]
     /// Construct a random Party with the passed ID and parameters
@@ -47,47 +90,204 @@ impl Party {
         Self {
             id,
             n,
+            group_key_ids: (0..n).collect(),
             f: VSS::random_poly(t - 1, rng),
-            private_key: Scalar::zero(),
+            private_key: SecretScalar::zero(),
             public_key: Point::zero(),
             group_key: Point::zero(),
-            nonce: Nonce::zero(),
+            nonces: NonceSessions::default(),
+            preprocessed: NonceBatch::default(),
+            protocol_version: ProtocolVersion::default(),
+            dkg_id: 0,
+            lagrange: compute::LagrangeCache::default(),
         }
     }
 
     /// Load a party from `state`
     pub fn load(id: u32, n: u32, group_key: &Point, state: &PartyState) -> Self {
+        let group_key_ids = if state.group_key_ids.is_empty() {
+            (0..n).collect()
+        } else {
+            state.group_key_ids.clone()
+        };
+
         Self {
             id,
             n,
+            group_key_ids,
             f: state.polynomial.clone(),
-            private_key: state.private_key,
-            public_key: &state.private_key * G,
+            private_key: state.private_key.clone(),
+            public_key: *state.private_key * G,
             group_key: *group_key,
-            nonce: Nonce::zero(),
+            nonces: NonceSessions::default(),
+            preprocessed: state.preprocessed.clone(),
+            protocol_version: state.protocol_version,
+            dkg_id: state.dkg_id,
+            lagrange: compute::LagrangeCache::default(),
         }
     }
 
+    #[allow(non_snake_case)]
+    /// Construct a Party for the new committee of the resharing round `ctx`, whose keys are `group_key_ids`, from the sub-shares which the old key holders dealt to key `id`, indexed by old key ID
+    pub fn reshare<RNG: RngCore + CryptoRng>(
+        id: u32,
+        group_key_ids: &[u32],
+        ctx: &DkgContext,
+        shares: &HashMap<u32, Scalar>,
+        A: &HashMap<u32, PolyCommitment>,
+        old_poly: &[Point],
+        rng: &mut RNG,
+    ) -> Result<Self, DkgError> {
+        let poly = reshare::group_poly(A, old_poly, ctx)?;
+        let private_key = reshare::combine_shares(id, shares, A)?;
+
+        Ok(Self {
+            id,
+            n: ctx.num_keys,
+            group_key_ids: group_key_ids.to_vec(),
+            f: VSS::random_poly(ctx.threshold - 1, rng),
+            private_key: private_key.into(),
+            public_key: private_key * G,
+            group_key: poly[0],
+            nonces: NonceSessions::default(),
+            preprocessed: NonceBatch::default(),
+            protocol_version: ctx.version,
+            dkg_id: ctx.dkg_id,
+            lagrange: compute::LagrangeCache::default(),
+        })
+    }
+
+    /// Deal sub-shares of this party's private key to the new keys `key_ids` in the resharing round `ctx`, any `ctx.threshold` of which can reconstruct it
+    pub fn get_reshare<RNG: RngCore + CryptoRng>(
+        &self,
+        ctx: &DkgContext,
+        key_ids: &[u32],
+        rng: &mut RNG,
+    ) -> (PolyCommitment, HashMap<u32, Scalar>) {
+        reshare::deal(self.id, &self.private_key, ctx, key_ids, rng)
+    }
+
     /// Save the state required to reconstruct the party
     pub fn save(&self) -> PartyState {
         PartyState {
-            private_key: self.private_key,
+            private_key: self.private_key.clone(),
             polynomial: self.f.clone(),
+            preprocessed: self.preprocessed.clone(),
+            protocol_version: self.protocol_version,
+            dkg_id: self.dkg_id,
+            group_key_ids: self.group_key_ids.clone(),
         }
     }
 
-    /// Generate and store a private nonce for a signing round
-    pub fn gen_nonce<RNG: RngCore + CryptoRng>(&mut self, rng: &mut RNG) -> PublicNonce {
-        self.nonce = Nonce::random(rng);
+    /// Get the signing protocol version of this party's group
+    pub fn protocol_version(&self) -> ProtocolVersion {
+        self.protocol_version
+    }
+
+    /// Set the signing protocol version of this party's group, which every signer and the aggregator must agree on
+    pub fn set_protocol_version(&mut self, version: ProtocolVersion) {
+        self.protocol_version = version;
+    }
+
+    /// Get the context of this party's current DKG round, which its proofs of possession commit to
+    pub fn dkg_context(&self) -> DkgContext {
+        DkgContext::new(
+            self.protocol_version,
+            self.dkg_id,
+            self.n,
+            self.f.data().len().try_into().unwrap(),
+        )
+    }
+
+    /// Set the ID of this party's current DKG round, which must be unique for each group and round
+    pub fn set_dkg_id(&mut self, dkg_id: u64) {
+        self.dkg_id = dkg_id;
+    }
+
+    /// Get the IDs of every key in this party's group
+    pub fn group_key_ids(&self) -> &[u32] {
+        &self.group_key_ids
+    }
+
+    /// Set the IDs of every key in this party's group, which it deals shares to and expects shares from
+    pub fn set_group_key_ids(&mut self, key_ids: &[u32]) {
+        self.group_key_ids = key_ids.to_vec();
+    }
+
+    /// Generate and store a hedged private nonce for the signing session `session_id`, replacing any pending nonce for it
+    ///
+    /// Every party in the session and the aggregator must use the same `session_id`, since it is bound into the binding factor under `ProtocolVersion::V1`
+    pub fn gen_session_nonce<RNG: RngCore + CryptoRng>(
+        &mut self,
+        session_id: SessionId,
+        rng: &mut RNG,
+    ) -> PublicNonce {
+        let nonce = self.hedged_nonce(
+            &[b"session".as_slice(), &session_id.to_be_bytes()].concat(),
+            rng,
+        );
+        let public_nonce = PublicNonce::from(&nonce);
+        self.nonces.insert(session_id, nonce);
+
+        public_nonce
+    }
+
+    /// Generate and store a private nonce which depends only on `rng` for the signing session `session_id`, replacing any pending nonce for it
+    pub fn gen_random_nonce<RNG: RngCore + CryptoRng>(
+        &mut self,
+        session_id: SessionId,
+        rng: &mut RNG,
+    ) -> PublicNonce {
+        let nonce = Nonce::random(rng);
+        let public_nonce = PublicNonce::from(&nonce);
+        self.nonces.insert(session_id, nonce);
+
+        public_nonce
+    }
+
+    /// Generate a private nonce for the signing session `session_id` and durably record it as issued in `store`
+    ///
+    /// The nonce is only kept in `store`, so a party which restarts before signing can still sign with it using `sign_with_store`
+    pub fn gen_session_nonce_with_store<S: NonceStore, RNG: RngCore + CryptoRng>(
+        &self,
+        session_id: SessionId,
+        store: &mut S,
+        rng: &mut RNG,
+    ) -> Result<PublicNonce, NonceStoreError> {
+        let nonce = self.hedged_nonce(
+            &[b"session".as_slice(), &session_id.to_be_bytes()].concat(),
+            rng,
+        );
+        store.issue(self.id, session_id, &nonce)?;
+
+        Ok(PublicNonce::from(&nonce))
+    }
+
+    /// Generate and store `count` preprocessed nonces, returning their indices and public nonces so they can be published ahead of signing
+    pub fn preprocess_nonces<RNG: RngCore + CryptoRng>(
+        &mut self,
+        count: u32,
+        rng: &mut RNG,
+    ) -> Vec<(u32, PublicNonce)> {
+        (0..count)
+            .map(|_| {
+                let index = self.preprocessed.next_index().to_be_bytes();
+                let nonce = self.hedged_nonce(&[b"preprocessed".as_slice(), &index].concat(), rng);
+                self.preprocessed.push(nonce)
+            })
+            .collect()
+    }
 
-        PublicNonce::from(&self.nonce)
+    /// Get the indices and public nonces of the preprocessed nonces which have not yet been used
+    pub fn preprocessed_nonces(&self) -> Vec<(u32, PublicNonce)> {
+        self.preprocessed.public_nonces()
     }
 
     #[allow(non_snake_case)]
     /// Get a public commitment to the private polynomial
     pub fn get_poly_commitment<RNG: RngCore + CryptoRng>(&self, rng: &mut RNG) -> PolyCommitment {
         PolyCommitment {
-            id: ID::new(&self.id(), &self.f.data()[0], rng),
+            id: ID::new(&self.id(), &self.f.data()[0], &self.dkg_context(), rng),
             A: (0..self.f.data().len())
                 .map(|i| &self.f.data()[i] * G)
                 .collect(),
@@ -100,62 +300,168 @@ impl Party {
         self.f = VSS::random_poly(t.try_into().unwrap(), rng);
     }
 
-    /// Get the shares of this party's private polynomial for all parties
+    /// Make a new polynomial with a zero constant term, for a share refresh round
+    pub fn reset_refresh_poly<RNG: RngCore + CryptoRng>(&mut self, rng: &mut RNG) {
+        let t = self.f.data().len() - 1;
+        self.f = VSS::random_zero_poly(t.try_into().unwrap(), rng);
+    }
+
+    /// Get the shares of this party's private polynomial for every key in the group
     pub fn get_shares(&self) -> HashMap<u32, Scalar> {
-        let mut shares = HashMap::new();
-        for i in 0..self.n {
-            shares.insert(i, self.f.eval(compute::id(i)));
-        }
-        shares
+        into_par_iter!(0..self.group_key_ids.len())
+            .map(|i| {
+                let key_id = self.group_key_ids[i];
+                (key_id, self.f.eval(compute::id(key_id)))
+            })
+            .collect()
     }
 
     #[allow(non_snake_case)]
-    /// Compute this party's share of the group secret key
+    /// Compute this party's share of the group secret key from the shares and poly commitments `A` of every key in the group, both indexed by party ID
     pub fn compute_secret(
         &mut self,
         shares: HashMap<u32, Scalar>,
-        A: &[PolyCommitment],
+        A: &HashMap<u32, PolyCommitment>,
     ) -> Result<(), DkgError> {
-        let mut missing_shares = Vec::new();
-        for i in 0..self.n {
-            if shares.get(&i).is_none() {
-                missing_shares.push(i);
-            }
+        let missing_shares: Vec<u32> = self
+            .group_key_ids
+            .iter()
+            .cloned()
+            .filter(|i| shares.get(i).is_none())
+            .collect();
+        if !missing_shares.is_empty() {
+            return Err(DkgError::MissingShares(missing_shares));
+        }
+
+        // every key in the group must deal, and nothing else may
+        let mut bad_ids: Vec<u32> = self
+            .group_key_ids
+            .iter()
+            .cloned()
+            .filter(|i| !A.contains_key(i))
+            .chain(
+                A.keys()
+                    .cloned()
+                    .filter(|i| !self.group_key_ids.contains(i)),
+            )
+            .collect();
+        if !bad_ids.is_empty() {
+            bad_ids.sort();
+            return Err(DkgError::BadIds(bad_ids));
+        }
+
+        self.compute_secret_qualified(shares, A)
+    }
+
+    #[allow(non_snake_case)]
+    /// Compute this party's share of the group secret key from the qualified set of dealers whose poly commitments `A` are indexed by party ID, ignoring shares from any other party
+    ///
+    /// The qualified set must have at least as many parties as the threshold, and every qualified party must have sent a share
+    pub fn compute_secret_qualified(
+        &mut self,
+        shares: HashMap<u32, Scalar>,
+        A: &HashMap<u32, PolyCommitment>,
+    ) -> Result<(), DkgError> {
+        let threshold = self.f.data().len();
+        if A.len() < threshold {
+            return Err(DkgError::NotEnoughDealers(A.len(), threshold));
         }
+
+        let mut dealers: Vec<u32> = A.keys().cloned().collect();
+        dealers.sort();
+
+        let missing_shares: Vec<u32> = dealers
+            .iter()
+            .cloned()
+            .filter(|i| shares.get(i).is_none())
+            .collect();
         if !missing_shares.is_empty() {
             return Err(DkgError::MissingShares(missing_shares));
         }
 
-        self.private_key = Scalar::zero();
+        self.private_key = SecretScalar::zero();
         self.group_key = Point::zero();
 
-        let bad_ids: Vec<u32> = shares
-            .keys()
+        let ctx = self.dkg_context();
+        let bad_ids: Vec<u32> = dealers
+            .iter()
             .cloned()
-            .filter(|i| !A[usize::try_from(*i).unwrap()].verify())
+            .filter(|i| A[i].id.id != compute::id(*i) || !A[i].verify(&ctx))
             .collect();
         if !bad_ids.is_empty() {
             return Err(DkgError::BadIds(bad_ids));
         }
 
-        let mut bad_shares = Vec::new();
-        for (i, s) in shares.iter() {
-            let Ai = &A[usize::try_from(*i).unwrap()];
-            if s * G != compute::poly(&self.id(), &Ai.A)? {
-                bad_shares.push(*i);
-            }
-        }
+        let key_shares: Vec<(u32, u32, Scalar)> =
+            dealers.iter().map(|i| (self.id, *i, shares[i])).collect();
+        let mut bad_shares: Vec<u32> = compute::verify_shares(&key_shares, A)?
+            .into_iter()
+            .map(|i| key_shares[i].1)
+            .collect();
         if !bad_shares.is_empty() {
+            bad_shares.sort();
+            bad_shares.dedup();
             return Err(DkgError::BadShares(bad_shares));
         }
 
-        for (i, s) in shares.iter() {
-            let Ai = &A[usize::try_from(*i).unwrap()];
+        for i in &dealers {
+            *self.private_key += shares[i];
+            self.group_key += A[i].A[0];
+        }
+        self.public_key = *self.private_key * G;
+
+        Ok(())
+    }
+
+    #[allow(non_snake_case)]
+    /// Add refresh shares to this party's share of the group secret key, leaving the group key unchanged
+    pub fn refresh_secret(
+        &mut self,
+        shares: HashMap<u32, Scalar>,
+        A: &HashMap<u32, PolyCommitment>,
+    ) -> Result<(), DkgError> {
+        // refresh shares are only added to a key which DKG already computed
+        if self.private_key.is_zero() {
+            return Err(DkgError::NoPrivateKey(self.id));
+        }
+
+        let missing_shares: Vec<u32> = self
+            .group_key_ids
+            .iter()
+            .cloned()
+            .filter(|i| shares.get(i).is_none())
+            .collect();
+        if !missing_shares.is_empty() {
+            return Err(DkgError::MissingShares(missing_shares));
+        }
+
+        let ctx = self.dkg_context();
+        let mut bad_ids: Vec<u32> = shares
+            .keys()
+            .cloned()
+            .filter(|i| !matches!(A.get(i), Some(A_i) if A_i.verify_refresh(&ctx)))
+            .collect();
+        if !bad_ids.is_empty() {
+            bad_ids.sort();
+            return Err(DkgError::BadRefreshCommitments(bad_ids));
+        }
+
+        let key_shares: Vec<(u32, u32, Scalar)> =
+            shares.iter().map(|(i, s)| (self.id, *i, *s)).collect();
+        let mut bad_shares: Vec<u32> = compute::verify_shares(&key_shares, A)?
+            .into_iter()
+            .map(|i| key_shares[i].1)
+            .collect();
+        if !bad_shares.is_empty() {
+            bad_shares.sort();
+            bad_shares.dedup();
+            return Err(DkgError::BadShares(bad_shares));
+        }
 
-            self.private_key += s;
-            self.group_key += Ai.A[0];
+        for s in shares.values() {
+            *self.private_key += s;
         }
-        self.public_key = self.private_key * G;
+        self.public_key = *self.private_key * G;
 
         Ok(())
     }
@@ -167,17 +473,179 @@ impl Party {
 
     #[allow(non_snake_case)]
     /// Sign `msg` with this party's share of the group private key, using the set of `sigers` and corresponding `nonces`
-    pub fn sign(&self, msg: &[u8], signers: &[u32], nonces: &[PublicNonce]) -> SignatureShare {
-        let (_R_vec, R) = compute::intermediate(msg, signers, nonces);
-        let mut z = &self.nonce.d + &self.nonce.e * compute::binding(&self.id(), nonces, msg);
-        z += compute::challenge(&self.group_key, &R, msg)
-            * &self.private_key
-            * compute::lambda(self.id, signers);
-
-        SignatureShare {
+    ///
+    /// This consumes the party's nonce for `session_id`, so each session can only sign once
+    pub fn sign(
+        &mut self,
+        session_id: SessionId,
+        msg: &[u8],
+        signers: &[u32],
+        nonces: &[PublicNonce],
+    ) -> Result<SignatureShare, SignError> {
+        let nonce = self.take_nonce(session_id)?;
+        // an odd group key is negated, along with every private key
+        let (key, a, _b) = compute::tweaked_key(&self.group_key, &Scalar::zero());
+
+        self.sign_with_key(nonce, session_id, msg, signers, nonces, &key, &a)
+    }
+
+    /// Sign `msg` for the BIP-341 output key of the group key and an optional script `merkle_root`, using the set of `signers` and corresponding `nonces`
+    pub fn sign_taproot(
+        &mut self,
+        session_id: SessionId,
+        msg: &[u8],
+        signers: &[u32],
+        nonces: &[PublicNonce],
+        merkle_root: Option<[u8; 32]>,
+    ) -> Result<SignatureShare, SignError> {
+        let nonce = self.take_nonce(session_id)?;
+        let tweak = compute::tweak(&self.group_key, merkle_root);
+        let (key, a, _b) = compute::tweaked_key(&self.group_key, &tweak);
+
+        self.sign_with_key(nonce, session_id, msg, signers, nonces, &key, &a)
+    }
+
+    /// Sign `msg` like `sign` in the signing session `session_id`, but with the preprocessed nonce at `index`, which can never be used again
+    ///
+    /// The index only selects the nonce; `session_id` is what the aggregator must check the signature shares against
+    pub fn sign_preprocessed(
+        &mut self,
+        index: u32,
+        session_id: SessionId,
+        msg: &[u8],
+        signers: &[u32],
+        nonces: &[PublicNonce],
+    ) -> Result<SignatureShare, SignError> {
+        let nonce = self.take_preprocessed_nonce(index)?;
+        let (key, a, _b) = compute::tweaked_key(&self.group_key, &Scalar::zero());
+
+        self.sign_with_key(nonce, session_id, msg, signers, nonces, &key, &a)
+    }
+
+    /// Sign `msg` like `sign_taproot` in the signing session `session_id`, but with the preprocessed nonce at `index`, which can never be used again
+    pub fn sign_taproot_preprocessed(
+        &mut self,
+        index: u32,
+        session_id: SessionId,
+        msg: &[u8],
+        signers: &[u32],
+        nonces: &[PublicNonce],
+        merkle_root: Option<[u8; 32]>,
+    ) -> Result<SignatureShare, SignError> {
+        let nonce = self.take_preprocessed_nonce(index)?;
+        let tweak = compute::tweak(&self.group_key, merkle_root);
+        let (key, a, _b) = compute::tweaked_key(&self.group_key, &tweak);
+
+        self.sign_with_key(nonce, session_id, msg, signers, nonces, &key, &a)
+    }
+
+    /// Sign `msg` like `sign`, but with the nonce issued in `store` for `session_id`, which is durably marked as consumed before signing
+    pub fn sign_with_store<S: NonceStore>(
+        &self,
+        store: &mut S,
+        session_id: SessionId,
+        msg: &[u8],
+        signers: &[u32],
+        nonces: &[PublicNonce],
+    ) -> Result<SignatureShare, SignError> {
+        let nonce = self.take_stored_nonce(store, session_id)?;
+        let (key, a, _b) = compute::tweaked_key(&self.group_key, &Scalar::zero());
+
+        self.sign_with_key(nonce, session_id, msg, signers, nonces, &key, &a)
+    }
+
+    /// Sign `msg` like `sign_taproot`, but with the nonce issued in `store` for `session_id`, which is durably marked as consumed before signing
+    pub fn sign_taproot_with_store<S: NonceStore>(
+        &self,
+        store: &mut S,
+        session_id: SessionId,
+        msg: &[u8],
+        signers: &[u32],
+        nonces: &[PublicNonce],
+        merkle_root: Option<[u8; 32]>,
+    ) -> Result<SignatureShare, SignError> {
+        let nonce = self.take_stored_nonce(store, session_id)?;
+        let tweak = compute::tweak(&self.group_key, merkle_root);
+        let (key, a, _b) = compute::tweaked_key(&self.group_key, &tweak);
+
+        self.sign_with_key(nonce, session_id, msg, signers, nonces, &key, &a)
+    }
+
+    #[allow(non_snake_case, clippy::too_many_arguments)]
+    /// Sign `msg` with `nonce` in `session_id` for `key`, which is `a` times the group key plus a public tweak
+    fn sign_with_key(
+        &self,
+        nonce: Nonce,
+        session_id: SessionId,
+        msg: &[u8],
+        signers: &[u32],
+        nonces: &[PublicNonce],
+        key: &Point,
+        a: &Scalar,
+    ) -> Result<SignatureShare, SignError> {
+        let ctx = compute::BindingContext {
+            version: self.protocol_version,
+            group_key: &self.group_key,
+            session_id,
+            party_ids: signers,
+            key_ids: signers,
+        };
+        let (_R_vec, R) = compute::versioned_intermediate(&ctx, msg, nonces);
+        let mut z = &nonce.d + &nonce.e * compute::versioned_binding(&ctx, &self.id(), nonces, msg);
+        // an odd R is negated, along with every nonce
+        if !R.has_even_y() {
+            z = -z;
+        }
+        z += compute::challenge(key, &R, msg)
+            * a
+            * *self.private_key
+            * self.lagrange.lambda(self.id, signers);
+
+        Ok(SignatureShare {
             id: self.id,
             z_i: z,
             key_ids: vec![self.id],
+        })
+    }
+
+    /// Generate a nonce hedged with this party's private key and polynomial, for the signing `context`
+    fn hedged_nonce<RNG: RngCore + CryptoRng>(&self, context: &[u8], rng: &mut RNG) -> Nonce {
+        let mut secrets: Vec<&Scalar> = vec![&self.private_key];
+        secrets.extend(self.f.data());
+        let context = [self.id.to_be_bytes().as_slice(), context].concat();
+
+        Nonce::hedged(&secrets, &context, rng)
+    }
+
+    /// Take the nonce for the signing session `session_id`, so that it can never be used again
+    fn take_nonce(&mut self, session_id: SessionId) -> Result<Nonce, SignError> {
+        match self.nonces.take(session_id) {
+            None => Err(SignError::NoNonce(self.id)),
+            Some(nonce) if nonce.is_zero() => Err(SignError::ZeroNonce(self.id)),
+            Some(nonce) => Ok(nonce),
+        }
+    }
+
+    /// Consume the nonce issued in `store` for `session_id`, so that it can never be used again even after a restart
+    fn take_stored_nonce<S: NonceStore>(
+        &self,
+        store: &mut S,
+        session_id: SessionId,
+    ) -> Result<Nonce, SignError> {
+        let nonce = store.consume(self.id, session_id)?;
+        if nonce.is_zero() {
+            return Err(SignError::ZeroNonce(self.id));
+        }
+
+        Ok(nonce)
+    }
+
+    /// Take the preprocessed nonce at `index`, so that it can never be used again
+    fn take_preprocessed_nonce(&mut self, index: u32) -> Result<Nonce, SignError> {
+        match self.preprocessed.take(index) {
+            None => Err(SignError::NoPreprocessedNonce(self.id, index)),
+            Some(nonce) if nonce.is_zero() => Err(SignError::ZeroNonce(self.id)),
+            Some(nonce) => Ok(nonce),
         }
     }
 }
@@ -191,20 +659,54 @@ pub struct SignatureAggregator {
     pub T: u32,
     /// The aggregate group polynomial; poly[0] is the group public key
     pub poly: Vec<Point>,
+    /// The public key of each party, cached from the group polynomial
+    public_keys: HashMap<u32, Point>,
+    key_ids: Vec<u32>,
+    lagrange: compute::LagrangeCache,
+    protocol_version: ProtocolVersion,
+    dkg_id: u64,
 }
 
 impl SignatureAggregator {
     #[allow(non_snake_case)]
-    /// Construct a SignatureAggregator with the passed parameters and polynomial commitments
-    pub fn new(N: u32, T: u32, A: Vec<PolyCommitment>) -> Result<Self, AggregatorError> {
-        let len = N.try_into().unwrap();
+    /// Construct a SignatureAggregator with the passed parameters and polynomial commitments, indexed by party ID, whose proofs of possession are checked without a DKG context
+    pub fn new(N: u32, T: u32, A: &HashMap<u32, PolyCommitment>) -> Result<Self, AggregatorError> {
+        Self::new_with_context(&DkgContext::new(ProtocolVersion::V0, 0, N, T), A)
+    }
+
+    #[allow(non_snake_case)]
+    /// Construct a SignatureAggregator for the group of the DKG round `ctx` from the polynomial commitments made in it by every party, indexed by party ID
+    pub fn new_with_context(
+        ctx: &DkgContext,
+        A: &HashMap<u32, PolyCommitment>,
+    ) -> Result<Self, AggregatorError> {
+        let len = ctx.num_keys.try_into().unwrap();
         if A.len() != len {
             return Err(AggregatorError::BadPolyCommitmentLen(A.len(), len));
         }
 
+        // each party is a single key, so every key in the group has made a commitment
+        let mut key_ids: Vec<u32> = A.keys().cloned().collect();
+        key_ids.sort();
+
+        Self::new_qualified(ctx, &key_ids, A)
+    }
+
+    #[allow(non_snake_case)]
+    /// Construct a SignatureAggregator for the group of the DKG round `ctx`, whose keys are `key_ids`, from the poly commitments `A` of its qualified set of parties, indexed by party ID
+    pub fn new_qualified(
+        ctx: &DkgContext,
+        key_ids: &[u32],
+        A: &HashMap<u32, PolyCommitment>,
+    ) -> Result<Self, AggregatorError> {
+        let threshold = ctx.threshold.try_into().unwrap();
+        if A.len() < threshold {
+            return Err(AggregatorError::NotEnoughDealers(A.len(), threshold));
+        }
+
         let mut bad_poly_commitments = Vec::new();
-        for A_i in &A {
-            if !A_i.verify() {
+        for (i, A_i) in A {
+            if A_i.id.id != compute::id(*i) || !A_i.verify(ctx) {
                 bad_poly_commitments.push(A_i.id.id);
             }
         }
@@ -212,59 +714,206 @@ impl SignatureAggregator {
             return Err(AggregatorError::BadPolyCommitments(bad_poly_commitments));
         }
 
-        let mut poly = Vec::with_capacity(T.try_into().unwrap());
+        let mut poly = Vec::with_capacity(threshold);
 
         for i in 0..poly.capacity() {
             poly.push(Point::zero());
-            for p in &A {
+            for p in A.values() {
                 poly[i] += &p.A[i];
             }
         }
 
-        Ok(Self { N, T, poly })
+        Ok(Self::from_poly(ctx, key_ids, poly))
+    }
+
+    #[allow(non_snake_case)]
+    /// Construct a SignatureAggregator for the new committee of the resharing round `ctx`, whose keys are `key_ids`, from the reshare commitments `A`, indexed by old key ID, and the old group polynomial
+    pub fn reshare(
+        ctx: &DkgContext,
+        key_ids: &[u32],
+        A: &HashMap<u32, PolyCommitment>,
+        old_poly: &[Point],
+    ) -> Result<Self, AggregatorError> {
+        let poly = reshare::group_poly(A, old_poly, ctx).map_err(AggregatorError::Reshare)?;
+
+        Ok(Self::from_poly(ctx, key_ids, poly))
+    }
+
+    /// Construct a SignatureAggregator for the group of the DKG round `ctx` with the key IDs `key_ids` and the group polynomial `poly`
+    fn from_poly(ctx: &DkgContext, key_ids: &[u32], poly: Vec<Point>) -> Self {
+        Self {
+            N: ctx.num_keys,
+            T: ctx.threshold,
+            public_keys: compute::public_keys(key_ids, &poly),
+            key_ids: key_ids.to_vec(),
+            poly,
+            lagrange: compute::LagrangeCache::default(),
+            protocol_version: ctx.version,
+            dkg_id: ctx.dkg_id,
+        }
+    }
+
+    #[allow(non_snake_case)]
+    /// Add the refresh poly commitments, indexed by party ID, to the group polynomial, leaving the group public key unchanged
+    pub fn refresh(&mut self, A: &HashMap<u32, PolyCommitment>) -> Result<(), AggregatorError> {
+        let len = self.N.try_into().unwrap();
+        if A.len() != len {
+            return Err(AggregatorError::BadPolyCommitmentLen(A.len(), len));
+        }
+
+        let ctx = self.dkg_context();
+        let bad_refresh_commitments: Vec<Scalar> = A
+            .iter()
+            .filter(|(i, A_i)| A_i.id.id != compute::id(**i) || !A_i.verify_refresh(&ctx))
+            .map(|(_, A_i)| A_i.id.id)
+            .collect();
+        if !bad_refresh_commitments.is_empty() {
+            return Err(AggregatorError::BadRefreshCommitments(
+                bad_refresh_commitments,
+            ));
+        }
+
+        for (i, p) in self.poly.iter_mut().enumerate() {
+            for A_i in A.values() {
+                *p += &A_i.A[i];
+            }
+        }
+        self.public_keys = compute::public_keys(&self.key_ids, &self.poly);
+
+        Ok(())
+    }
+
+    /// Get the public key of each party, indexed by party ID
+    pub fn public_keys(&self) -> &HashMap<u32, Point> {
+        &self.public_keys
+    }
+
+    /// Set the IDs of every key in the group, whose public keys signature shares are checked against
+    pub fn set_group_key_ids(&mut self, key_ids: &[u32]) {
+        self.key_ids = key_ids.to_vec();
+        self.public_keys = compute::public_keys(&self.key_ids, &self.poly);
+    }
+
+    /// Get the signing protocol version of the group
+    pub fn protocol_version(&self) -> ProtocolVersion {
+        self.protocol_version
+    }
+
+    /// Set the signing protocol version of the group, which every signer must agree on
+    pub fn set_protocol_version(&mut self, version: ProtocolVersion) {
+        self.protocol_version = version;
+    }
+
+    /// Get the context of the group's current DKG round, which proofs of possession must commit to
+    pub fn dkg_context(&self) -> DkgContext {
+        DkgContext::new(self.protocol_version, self.dkg_id, self.N, self.T)
+    }
+
+    /// Set the ID of the group's current DKG round, which must be unique for each group and round
+    pub fn set_dkg_id(&mut self, dkg_id: u64) {
+        self.dkg_id = dkg_id;
     }
 
     #[allow(non_snake_case)]
-    /// Check and aggregate the party signatures
+    /// Check and aggregate the party signatures from signing session `session_id`
     pub fn sign(
         &mut self,
+        session_id: SessionId,
+        msg: &[u8],
+        nonces: &[PublicNonce],
+        sig_shares: &[SignatureShare],
+    ) -> Result<Signature, AggregatorError> {
+        // an odd group key is negated, along with every public key share
+        let (key, a, b) = compute::tweaked_key(&self.poly[0], &Scalar::zero());
+
+        self.sign_with_key(session_id, msg, nonces, sig_shares, &key, &a, &b)
+    }
+
+    #[allow(non_snake_case)]
+    /// Check and aggregate the party signatures from signing session `session_id` for the BIP-341 output key of the group key and an optional script `merkle_root`
+    pub fn sign_taproot(
+        &mut self,
+        session_id: SessionId,
+        msg: &[u8],
+        nonces: &[PublicNonce],
+        sig_shares: &[SignatureShare],
+        merkle_root: Option<[u8; 32]>,
+    ) -> Result<Signature, AggregatorError> {
+        let tweak = compute::tweak(&self.poly[0], merkle_root);
+        let (key, a, b) = compute::tweaked_key(&self.poly[0], &tweak);
+
+        self.sign_with_key(session_id, msg, nonces, sig_shares, &key, &a, &b)
+    }
+
+    #[allow(non_snake_case, clippy::too_many_arguments)]
+    /// Check and aggregate the party signatures for `key`, which is `a` times the group key plus `b` times G
+    fn sign_with_key(
+        &mut self,
+        session_id: SessionId,
         msg: &[u8],
         nonces: &[PublicNonce],
         sig_shares: &[SignatureShare],
+        key: &Point,
+        a: &Scalar,
+        b: &Scalar,
     ) -> Result<Signature, AggregatorError> {
         if nonces.len() != sig_shares.len() {
             return Err(AggregatorError::BadNonceLen(nonces.len(), sig_shares.len()));
         }
 
         let signers: Vec<u32> = sig_shares.iter().map(|ss| ss.id).collect();
-        let (R_vec, R) = compute::intermediate(msg, &signers, nonces);
+        let ctx = compute::BindingContext {
+            version: self.protocol_version,
+            group_key: &self.poly[0],
+            session_id,
+            party_ids: &signers,
+            key_ids: &signers,
+        };
+        let (mut R_vec, mut R) = compute::versioned_intermediate(&ctx, msg, nonces);
+        // an odd R is negated, along with every party's nonce commitment
+        if !R.has_even_y() {
+            R_vec = R_vec.iter().map(|R_i| -R_i).collect();
+            R = -R;
+        }
         let mut z = Scalar::zero();
-        let c = compute::challenge(&self.poly[0], &R, msg);
+        let c = compute::challenge(key, &R, msg);
         let mut bad_party_keys = Vec::new();
         let mut bad_party_sigs = Vec::new();
+        let lambdas = self.lagrange.lambdas(&signers);
+
+        // check each party signature independently, then collect the results in order
+        let checks: Vec<(bool, bool)> = into_par_iter!(0..sig_shares.len())
+            .map(|i| {
+                let id = sig_shares[i].id;
+                let (public_key, bad_key) = match self.public_keys.get(&id) {
+                    Some(p) => (*p, false),
+                    None => (Point::zero(), true),
+                };
+                let lambda = match lambdas.get(&id) {
+                    Some(lambda) => *lambda,
+                    None => compute::lambda(id, &signers),
+                };
+
+                let bad_sig = sig_shares[i].z_i * G != R_vec[i] + (lambda * c * a * public_key);
+                (bad_key, bad_sig)
+            })
+            .collect();
 
-        for i in 0..sig_shares.len() {
-            let id = compute::id(sig_shares[i].id);
-            let public_key = match compute::poly(&id, &self.poly) {
-                Ok(p) => p,
-                Err(_) => {
-                    bad_party_keys.push(sig_shares[i].id);
-                    Point::zero()
-                }
-            };
-
-            let z_i = sig_shares[i].z_i;
-
-            if z_i * G != R_vec[i] + (compute::lambda(sig_shares[i].id, &signers) * c * public_key)
-            {
-                bad_party_sigs.push(sig_shares[i].id);
+        for (sig_share, (bad_key, bad_sig)) in sig_shares.iter().zip(checks) {
+            if bad_key {
+                bad_party_keys.push(sig_share.id);
+            }
+            if bad_sig {
+                bad_party_sigs.push(sig_share.id);
             }
 
-            z += z_i;
+            z += sig_share.z_i;
         }
+        z += c * b;
+
         if bad_party_sigs.is_empty() {
             let sig = Signature { R, z };
-            if sig.verify(&self.poly[0], msg) {
+            if sig.verify(key, msg) {
                 Ok(sig)
             } else {
                 Err(AggregatorError::BadGroupSig)
@@ -277,20 +926,106 @@ impl SignatureAggregator {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-/// The saved state required to construct a Signer
-pub struct SignerState {
-    /// The associated ID
-    id: u32,
-    /// The total number of keys
-    n: u32,
-    /// The aggregate group public key
-    group_key: Point,
-    /// The set of states for the parties which this object encapsulates, indexed by their party/key IDs
-    parties: HashMap<u32, PartyState>,
-}
+impl crate::traits::Aggregator for SignatureAggregator {
+    const PARTY_IDS: PartyIds = PartyIds::KeyIds;
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+    #[allow(non_snake_case)]
+    fn new(
+        num_keys: u32,
+        threshold: u32,
+        A: &HashMap<u32, PolyCommitment>,
+    ) -> Result<Self, AggregatorError> {
+        SignatureAggregator::new(num_keys, threshold, A)
+    }
+
+    #[allow(non_snake_case)]
+    fn new_with_context(
+        ctx: &DkgContext,
+        key_ids: &[u32],
+        A: &HashMap<u32, PolyCommitment>,
+    ) -> Result<Self, AggregatorError> {
+        let mut aggregator = SignatureAggregator::new_with_context(ctx, A)?;
+        aggregator.set_group_key_ids(key_ids);
+
+        Ok(aggregator)
+    }
+
+    #[allow(non_snake_case)]
+    fn new_qualified(
+        ctx: &DkgContext,
+        key_ids: &[u32],
+        qualified: &SelectedSigners,
+        A: &HashMap<u32, PolyCommitment>,
+    ) -> Result<Self, AggregatorError> {
+        // each party is a single key, so the qualified set is the dealers themselves
+        let A: HashMap<u32, PolyCommitment> = A
+            .iter()
+            .filter(|(id, _)| qualified.contains_key(*id))
+            .map(|(id, A_i)| (*id, A_i.clone()))
+            .collect();
+
+        SignatureAggregator::new_qualified(ctx, key_ids, &A)
+    }
+
+    fn get_poly(&self) -> &[Point] {
+        &self.poly
+    }
+
+    fn get_public_keys(&self) -> &HashMap<u32, Point> {
+        self.public_keys()
+    }
+
+    #[allow(non_snake_case)]
+    fn refresh(&mut self, A: &HashMap<u32, PolyCommitment>) -> Result<(), AggregatorError> {
+        self.refresh(A)
+    }
+
+    fn set_protocol_version(&mut self, version: ProtocolVersion) {
+        self.set_protocol_version(version)
+    }
+
+    fn set_group_key_ids(&mut self, key_ids: &[u32]) {
+        self.set_group_key_ids(key_ids)
+    }
+
+    fn sign(
+        &mut self,
+        session_id: SessionId,
+        msg: &[u8],
+        nonces: &[PublicNonce],
+        sig_shares: &[SignatureShare],
+        _key_ids: &[u32],
+    ) -> Result<Signature, AggregatorError> {
+        self.sign(session_id, msg, nonces, sig_shares)
+    }
+
+    fn sign_taproot(
+        &mut self,
+        session_id: SessionId,
+        msg: &[u8],
+        nonces: &[PublicNonce],
+        sig_shares: &[SignatureShare],
+        _key_ids: &[u32],
+        merkle_root: Option<[u8; 32]>,
+    ) -> Result<Signature, AggregatorError> {
+        self.sign_taproot(session_id, msg, nonces, sig_shares, merkle_root)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+/// The saved state required to construct a Signer
+pub struct SignerState {
+    /// The associated ID
+    id: u32,
+    /// The total number of keys
+    n: u32,
+    /// The aggregate group public key
+    group_key: Point,
+    /// The set of states for the parties which this object encapsulates, indexed by their party/key IDs
+    parties: HashMap<u32, PartyState>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
 /// A set of encapsulated FROST parties
 pub struct Signer {
     /// The associated signer ID
@@ -340,6 +1075,98 @@ impl Signer {
         }
     }
 
+    #[allow(non_snake_case, clippy::too_many_arguments)]
+    /// Construct a Signer with the keys `key_ids` for the new committee of the resharing round `ctx`, whose keys are `group_key_ids`, from the sub-shares which the old key holders dealt, indexed by old key ID and then new key ID
+    pub fn reshare<RNG: RngCore + CryptoRng>(
+        id: u32,
+        key_ids: &[u32],
+        group_key_ids: &[u32],
+        ctx: &DkgContext,
+        shares: &HashMap<u32, HashMap<u32, Scalar>>,
+        A: &HashMap<u32, PolyCommitment>,
+        old_poly: &[Point],
+        rng: &mut RNG,
+    ) -> Result<Self, HashMap<u32, DkgError>> {
+        let mut parties = Vec::new();
+        let mut dkg_errors = HashMap::new();
+        for key_id in key_ids {
+            let key_shares: HashMap<u32, Scalar> = shares
+                .iter()
+                .filter_map(|(dealer, dealer_shares)| {
+                    dealer_shares.get(key_id).map(|share| (*dealer, *share))
+                })
+                .collect();
+            match Party::reshare(*key_id, group_key_ids, ctx, &key_shares, A, old_poly, rng) {
+                Ok(party) => parties.push(party),
+                Err(e) => {
+                    dkg_errors.insert(*key_id, e);
+                }
+            }
+        }
+        if !dkg_errors.is_empty() {
+            return Err(dkg_errors);
+        }
+
+        Ok(Self {
+            id,
+            n: ctx.num_keys,
+            group_key: parties
+                .first()
+                .map_or(Point::zero(), |party| party.group_key),
+            parties,
+        })
+    }
+
+    /// Deal sub-shares of the private keys of all parties to the new keys `key_ids` in the resharing round `ctx`, returning the commitments and shares indexed by old key ID
+    pub fn get_reshares<RNG: RngCore + CryptoRng>(
+        &self,
+        ctx: &DkgContext,
+        key_ids: &[u32],
+        rng: &mut RNG,
+    ) -> (
+        HashMap<u32, PolyCommitment>,
+        HashMap<u32, HashMap<u32, Scalar>>,
+    ) {
+        let mut comms = HashMap::new();
+        let mut shares = HashMap::new();
+        for party in &self.parties {
+            let (comm, party_shares) = party.get_reshare(ctx, key_ids, rng);
+            comms.insert(party.id, comm);
+            shares.insert(party.id, party_shares);
+        }
+
+        (comms, shares)
+    }
+
+    #[allow(non_snake_case)]
+    /// Compute the secrets of all parties from the qualified set of dealers whose poly commitments `A` are indexed by party ID, ignoring shares from any other party
+    pub fn compute_secrets_qualified(
+        &mut self,
+        shares: &HashMap<u32, HashMap<u32, Scalar>>,
+        A: &HashMap<u32, PolyCommitment>,
+    ) -> Result<(), HashMap<u32, DkgError>> {
+        let dkg_errors: HashMap<u32, DkgError> = par_iter_mut!(self.parties)
+            .filter_map(|party| {
+                let key_shares: HashMap<u32, Scalar> = shares
+                    .iter()
+                    .filter_map(|(dealer, dealer_shares)| {
+                        dealer_shares.get(&party.id).map(|share| (*dealer, *share))
+                    })
+                    .collect();
+                party
+                    .compute_secret_qualified(key_shares, A)
+                    .err()
+                    .map(|e| (party.id, e))
+            })
+            .collect();
+
+        if dkg_errors.is_empty() {
+            Ok(())
+        } else {
+            Err(dkg_errors)
+        }
+    }
+
     /// Save the state required to reconstruct the signer
     pub fn save(&self) -> SignerState {
         let mut parties = HashMap::new();
@@ -351,13 +1178,19 @@ impl Signer {
         SignerState {
             id: self.id,
             n: self.n,
-            group_key: self.group_key,
+            // the parties hold the group key once they have computed their secrets
+            group_key: self
+                .parties
+                .first()
+                .map_or(self.group_key, |party| party.group_key),
             parties,
         }
     }
 }
 
 impl crate::traits::Signer for Signer {
+    const PARTY_IDS: PartyIds = PartyIds::KeyIds;
+
     fn get_id(&self) -> u32 {
         self.id
     }
@@ -366,10 +1199,13 @@ impl crate::traits::Signer for Signer {
         self.parties.iter().map(|p| p.id).collect()
     }
 
-    fn get_poly_commitments<RNG: RngCore + CryptoRng>(&self, rng: &mut RNG) -> Vec<PolyCommitment> {
+    fn get_poly_commitments<RNG: RngCore + CryptoRng>(
+        &self,
+        rng: &mut RNG,
+    ) -> HashMap<u32, PolyCommitment> {
         self.parties
             .iter()
-            .map(|p| p.get_poly_commitment(rng))
+            .map(|p| (p.id, p.get_poly_commitment(rng)))
             .collect()
     }
 
@@ -390,16 +1226,68 @@ impl crate::traits::Signer for Signer {
     fn compute_secrets(
         &mut self,
         private_shares: &HashMap<u32, HashMap<u32, Scalar>>,
-        polys: &[PolyCommitment],
+        polys: &HashMap<u32, PolyCommitment>,
+    ) -> Result<(), HashMap<u32, DkgError>> {
+        let dkg_errors: HashMap<u32, DkgError> = par_iter_mut!(self.parties)
+            .filter_map(|party| {
+                // go through the shares, looking for this party's
+                let mut key_shares = HashMap::new();
+                for (signer_id, signer_shares) in private_shares.iter() {
+                    if let Some(share) = signer_shares.get(&party.id) {
+                        key_shares.insert(*signer_id, *share);
+                    }
+                }
+                party
+                    .compute_secret(key_shares, polys)
+                    .err()
+                    .map(|e| (party.id, e))
+            })
+            .collect();
+
+        if dkg_errors.is_empty() {
+            Ok(())
+        } else {
+            Err(dkg_errors)
+        }
+    }
+
+    fn compute_secrets_qualified(
+        &mut self,
+        qualified: &SelectedSigners,
+        private_shares: &HashMap<u32, HashMap<u32, Scalar>>,
+        polys: &HashMap<u32, PolyCommitment>,
+    ) -> Result<(), HashMap<u32, DkgError>> {
+        // each party is a single key, so the qualified set is the dealers themselves
+        let polys: HashMap<u32, PolyCommitment> = polys
+            .iter()
+            .filter(|(id, _)| qualified.contains_key(*id))
+            .map(|(id, poly)| (*id, poly.clone()))
+            .collect();
+
+        Signer::compute_secrets_qualified(self, private_shares, &polys)
+    }
+
+    fn reset_refresh_polys<RNG: RngCore + CryptoRng>(&mut self, rng: &mut RNG) {
+        for party in self.parties.iter_mut() {
+            party.reset_refresh_poly(rng);
+        }
+    }
+
+    fn refresh_secrets(
+        &mut self,
+        private_shares: &HashMap<u32, HashMap<u32, Scalar>>,
+        polys: &HashMap<u32, PolyCommitment>,
     ) -> Result<(), HashMap<u32, DkgError>> {
         let mut dkg_errors = HashMap::new();
         for party in &mut self.parties {
             // go through the shares, looking for this party's
             let mut key_shares = HashMap::new();
             for (signer_id, signer_shares) in private_shares.iter() {
-                key_shares.insert(*signer_id, signer_shares[&party.id]);
+                if let Some(share) = signer_shares.get(&party.id) {
+                    key_shares.insert(*signer_id, *share);
+                }
             }
-            if let Err(e) = party.compute_secret(key_shares, polys) {
+            if let Err(e) = party.refresh_secret(key_shares, polys) {
                 dkg_errors.insert(party.id, e);
             }
         }
@@ -411,8 +1299,68 @@ impl crate::traits::Signer for Signer {
         }
     }
 
-    fn gen_nonces<RNG: RngCore + CryptoRng>(&mut self, rng: &mut RNG) -> Vec<PublicNonce> {
-        self.parties.iter_mut().map(|p| p.gen_nonce(rng)).collect()
+    fn gen_session_nonces<RNG: RngCore + CryptoRng>(
+        &mut self,
+        session_id: SessionId,
+        rng: &mut RNG,
+    ) -> Vec<PublicNonce> {
+        self.parties
+            .iter_mut()
+            .map(|p| p.gen_session_nonce(session_id, rng))
+            .collect()
+    }
+
+    fn gen_session_nonces_with_store<S: NonceStore, RNG: RngCore + CryptoRng>(
+        &mut self,
+        session_id: SessionId,
+        store: &mut S,
+        rng: &mut RNG,
+    ) -> Result<Vec<PublicNonce>, NonceStoreError> {
+        self.parties
+            .iter()
+            .map(|p| p.gen_session_nonce_with_store(session_id, store, rng))
+            .collect()
+    }
+
+    fn preprocess_nonces<RNG: RngCore + CryptoRng>(
+        &mut self,
+        count: u32,
+        rng: &mut RNG,
+    ) -> Vec<(u32, Vec<PublicNonce>)> {
+        let party_nonces = self
+            .parties
+            .iter_mut()
+            .map(|p| p.preprocess_nonces(count, rng))
+            .collect();
+
+        group_preprocessed(party_nonces)
+    }
+
+    fn preprocessed_nonces(&self) -> Vec<(u32, Vec<PublicNonce>)> {
+        group_preprocessed(
+            self.parties
+                .iter()
+                .map(|p| p.preprocessed_nonces())
+                .collect(),
+        )
+    }
+
+    fn set_protocol_version(&mut self, version: ProtocolVersion) {
+        for party in &mut self.parties {
+            party.set_protocol_version(version);
+        }
+    }
+
+    fn set_dkg_id(&mut self, dkg_id: u64) {
+        for party in &mut self.parties {
+            party.set_dkg_id(dkg_id);
+        }
+    }
+
+    fn set_group_key_ids(&mut self, key_ids: &[u32]) {
+        for party in &mut self.parties {
+            party.set_group_key_ids(key_ids);
+        }
     }
 
     fn compute_intermediate(
@@ -425,22 +1373,114 @@ impl crate::traits::Signer for Signer {
     }
 
     fn sign(
-        &self,
+        &mut self,
+        session_id: SessionId,
+        msg: &[u8],
+        _signer_ids: &[u32],
+        key_ids: &[u32],
+        nonces: &[PublicNonce],
+    ) -> Result<Vec<SignatureShare>, SignError> {
+        self.parties
+            .iter_mut()
+            .map(|p| p.sign(session_id, msg, key_ids, nonces))
+            .collect()
+    }
+
+    fn sign_taproot(
+        &mut self,
+        session_id: SessionId,
         msg: &[u8],
         _signer_ids: &[u32],
         key_ids: &[u32],
         nonces: &[PublicNonce],
-    ) -> Vec<SignatureShare> {
+        merkle_root: Option<[u8; 32]>,
+    ) -> Result<Vec<SignatureShare>, SignError> {
+        self.parties
+            .iter_mut()
+            .map(|p| p.sign_taproot(session_id, msg, key_ids, nonces, merkle_root))
+            .collect()
+    }
+
+    fn sign_with_store<S: NonceStore>(
+        &mut self,
+        store: &mut S,
+        session_id: SessionId,
+        msg: &[u8],
+        _signer_ids: &[u32],
+        key_ids: &[u32],
+        nonces: &[PublicNonce],
+    ) -> Result<Vec<SignatureShare>, SignError> {
         self.parties
             .iter()
-            .map(|p| p.sign(msg, key_ids, nonces))
+            .map(|p| p.sign_with_store(store, session_id, msg, key_ids, nonces))
+            .collect()
+    }
+
+    fn sign_taproot_with_store<S: NonceStore>(
+        &mut self,
+        store: &mut S,
+        session_id: SessionId,
+        msg: &[u8],
+        _signer_ids: &[u32],
+        key_ids: &[u32],
+        nonces: &[PublicNonce],
+        merkle_root: Option<[u8; 32]>,
+    ) -> Result<Vec<SignatureShare>, SignError> {
+        self.parties
+            .iter()
+            .map(|p| {
+                p.sign_taproot_with_store(store, session_id, msg, key_ids, nonces, merkle_root)
+            })
+            .collect()
+    }
+
+    fn sign_preprocessed(
+        &mut self,
+        index: u32,
+        session_id: SessionId,
+        msg: &[u8],
+        _signer_ids: &[u32],
+        key_ids: &[u32],
+        nonces: &[PublicNonce],
+    ) -> Result<Vec<SignatureShare>, SignError> {
+        self.parties
+            .iter_mut()
+            .map(|p| p.sign_preprocessed(index, session_id, msg, key_ids, nonces))
             .collect()
     }
+
+    fn sign_taproot_preprocessed(
+        &mut self,
+        index: u32,
+        session_id: SessionId,
+        msg: &[u8],
+        _signer_ids: &[u32],
+        key_ids: &[u32],
+        nonces: &[PublicNonce],
+        merkle_root: Option<[u8; 32]>,
+    ) -> Result<Vec<SignatureShare>, SignError> {
+        self.parties
+            .iter_mut()
+            .map(|p| {
+                p.sign_taproot_preprocessed(index, session_id, msg, key_ids, nonces, merkle_root)
+            })
+            .collect()
+    }
+}
+
+/// Group the preprocessed public nonces of each party by index, keeping the party order within each index
+fn group_preprocessed(party_nonces: Vec<Vec<(u32, PublicNonce)>>) -> Vec<(u32, Vec<PublicNonce>)> {
+    let mut grouped: BTreeMap<u32, Vec<PublicNonce>> = BTreeMap::new();
+    for (index, nonce) in party_nonces.into_iter().flatten() {
+        grouped.entry(index).or_default().push(nonce);
+    }
+
+    grouped.into_iter().collect()
 }
 
 /// Helper functions for tests
 pub mod test_helpers {
-    use crate::common::{PolyCommitment, PublicNonce};
+    use crate::common::{PolyCommitment, PublicNonce, SessionId};
     use crate::errors::DkgError;
     use crate::traits::Signer;
     use crate::v1;
@@ -448,13 +1488,41 @@ pub mod test_helpers {
     use hashbrown::HashMap;
     use rand_core::{CryptoRng, RngCore};
 
+    /// Construct a signer for each entry of `signer_key_ids`, with signer IDs counting up from zero
+    pub fn new_signers<RNG: RngCore + CryptoRng>(
+        signer_key_ids: &[Vec<u32>],
+        threshold: u32,
+        rng: &mut RNG,
+    ) -> Vec<v1::Signer> {
+        let num_keys = signer_key_ids.iter().map(|k| k.len()).sum::<usize>();
+        let num_keys = num_keys.try_into().unwrap();
+        signer_key_ids
+            .iter()
+            .enumerate()
+            .map(|(id, ids)| v1::Signer::new(id.try_into().unwrap(), ids, num_keys, threshold, rng))
+            .collect()
+    }
+
+    #[allow(non_snake_case)]
+    /// Construct signers as `new_signers` does and run a distributed key generation round among them
+    pub fn setup_dkg<RNG: RngCore + CryptoRng>(
+        signer_key_ids: &[Vec<u32>],
+        threshold: u32,
+        rng: &mut RNG,
+    ) -> (Vec<v1::Signer>, HashMap<u32, PolyCommitment>) {
+        let mut signers = new_signers(signer_key_ids, threshold, rng);
+        let A = dkg(&mut signers, rng).expect("DKG failed");
+
+        (signers, A)
+    }
+
     #[allow(non_snake_case)]
     /// Run a distributed key generation round
     pub fn dkg<RNG: RngCore + CryptoRng>(
         signers: &mut [v1::Signer],
         rng: &mut RNG,
-    ) -> Result<Vec<PolyCommitment>, HashMap<u32, DkgError>> {
-        let A: Vec<PolyCommitment> = signers
+    ) -> Result<HashMap<u32, PolyCommitment>, HashMap<u32, DkgError>> {
+        let A: HashMap<u32, PolyCommitment> = signers
             .iter()
             .flat_map(|s| s.get_poly_commitments(rng))
             .collect();
@@ -492,31 +1560,105 @@ pub mod test_helpers {
         }
     }
 
-    /// Run a signing round for the passed `msg`
+    #[allow(non_snake_case)]
+    /// Run a share refresh round, which changes every party's private key but not the group key
+    pub fn refresh<RNG: RngCore + CryptoRng>(
+        signers: &mut [v1::Signer],
+        rng: &mut RNG,
+    ) -> Result<HashMap<u32, PolyCommitment>, HashMap<u32, DkgError>> {
+        for signer in signers.iter_mut() {
+            signer.reset_refresh_polys(rng);
+        }
+
+        let A: HashMap<u32, PolyCommitment> = signers
+            .iter()
+            .flat_map(|s| s.get_poly_commitments(rng))
+            .collect();
+
+        let mut private_shares = Vec::new();
+        for signer in signers.iter() {
+            for party in &signer.parties {
+                private_shares.push((party.id, party.get_shares()));
+            }
+        }
+
+        let mut secret_errors = HashMap::new();
+        for signer in signers.iter_mut() {
+            for party in signer.parties.iter_mut() {
+                let mut h = HashMap::new();
+
+                for (id, share) in &private_shares {
+                    h.insert(*id, share[&party.id]);
+                }
+
+                if let Err(secret_error) = party.refresh_secret(h, &A) {
+                    secret_errors.insert(party.id, secret_error);
+                }
+            }
+        }
+
+        if secret_errors.is_empty() {
+            Ok(A)
+        } else {
+            Err(secret_errors)
+        }
+    }
+
+    /// Run a signing round for the passed `msg`, returning the session ID which the aggregator must check the signature shares against
     pub fn sign<RNG: RngCore + CryptoRng>(
         msg: &[u8],
         signers: &mut [v1::Signer],
         rng: &mut RNG,
-    ) -> (Vec<PublicNonce>, Vec<v1::SignatureShare>) {
+    ) -> (SessionId, Vec<PublicNonce>, Vec<v1::SignatureShare>) {
         let ids: Vec<u32> = signers.iter().flat_map(|s| s.get_key_ids()).collect();
-        let nonces: Vec<PublicNonce> = signers.iter_mut().flat_map(|s| s.gen_nonces(rng)).collect();
+        // every signer shares the session ID, which is bound into the binding factor under ProtocolVersion::V1
+        let session_id = rng.next_u64();
+        let nonces: Vec<PublicNonce> = signers
+            .iter_mut()
+            .flat_map(|s| s.gen_session_nonces(session_id, rng))
+            .collect();
         let shares = signers
-            .iter()
-            .flat_map(|s| s.sign(msg, &ids, &ids, &nonces))
+            .iter_mut()
+            .flat_map(|s| {
+                s.sign(session_id, msg, &ids, &ids, &nonces)
+                    .expect("signing failed")
+            })
             .collect();
 
-        (nonces, shares)
+        (session_id, nonces, shares)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::common::{ProtocolVersion, PublicNonce, SessionId};
+    use crate::errors::{AggregatorError, DkgError, SignError};
+    use crate::schnorr::DkgContext;
     use crate::traits::Signer;
     use crate::v1;
+    use crate::{compute, Scalar};
 
+    use hashbrown::HashMap;
     use num_traits::Zero;
     use rand_core::OsRng;
 
+    /// The number of keys in the group most tests use
+    const NUM_KEYS: u32 = 10;
+
+    /// The threshold of the group most tests use
+    const THRESHOLD: u32 = 7;
+
+    /// The key IDs of each signer in the group most tests use, where signers [0,1,3] hold exactly `THRESHOLD` keys
+    fn signer_key_ids() -> Vec<Vec<u32>> {
+        [
+            [0, 1, 2].to_vec(),
+            [3, 4].to_vec(),
+            [5, 6, 7].to_vec(),
+            [8, 9].to_vec(),
+        ]
+        .to_vec()
+    }
+
     #[test]
     fn signer_new() {
         let mut rng = OsRng::default();
@@ -541,15 +1683,16 @@ mod tests {
         let mut signer = v1::Signer::new(id, &key_ids, n, t, &mut rng);
 
         for party in &signer.parties {
-            assert!(party.nonce.is_zero());
+            assert!(party.nonces.is_empty());
         }
 
-        let nonces = signer.gen_nonces(&mut rng);
+        let session_id = 1;
+        let nonces = signer.gen_session_nonces(session_id, &mut rng);
 
         assert_eq!(nonces.len(), key_ids.len());
 
         for party in &signer.parties {
-            assert!(!party.nonce.is_zero());
+            assert!(party.nonces.contains(session_id));
         }
     }
 
@@ -574,38 +1717,422 @@ mod tests {
     fn aggregator_sign() {
         let mut rng = OsRng::default();
         let msg = "It was many and many a year ago".as_bytes();
-        let N: u32 = 10;
-        let T: u32 = 7;
-        let signer_ids: Vec<Vec<u32>> = [
-            [0, 1, 2].to_vec(),
-            [3, 4].to_vec(),
-            [5, 6, 7].to_vec(),
-            [8, 9].to_vec(),
-        ]
-        .to_vec();
-        let mut signers: Vec<v1::Signer> = signer_ids
+        let (signers, A) = v1::test_helpers::setup_dkg(&signer_key_ids(), THRESHOLD, &mut rng);
+
+        // signers [0,1,3] who have T keys
+        {
+            let mut signers = [signers[0].clone(), signers[1].clone(), signers[3].clone()].to_vec();
+            let mut sig_agg = v1::SignatureAggregator::new(NUM_KEYS, THRESHOLD, &A)
+                .expect("aggregator ctor failed");
+
+            let (session_id, nonces, sig_shares) =
+                v1::test_helpers::sign(&msg, &mut signers, &mut rng);
+            if let Err(e) = sig_agg.sign(session_id, &msg, &nonces, &sig_shares) {
+                panic!("Aggregator sign failed: {:?}", e);
+            }
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[test]
+    fn preprocessed_signing() {
+        let mut rng = OsRng::default();
+        let msg = "It was many and many a year ago".as_bytes();
+        let (signers, A) = v1::test_helpers::setup_dkg(&signer_key_ids(), THRESHOLD, &mut rng);
+        let mut sig_agg =
+            v1::SignatureAggregator::new(NUM_KEYS, THRESHOLD, &A).expect("aggregator ctor failed");
+        sig_agg.set_protocol_version(ProtocolVersion::V1);
+
+        let mut signers = [signers[0].clone(), signers[1].clone(), signers[3].clone()].to_vec();
+        for signer in signers.iter_mut() {
+            signer.set_protocol_version(ProtocolVersion::V1);
+            let preprocessed = signer.preprocess_nonces(4, &mut rng);
+            let indices: Vec<u32> = preprocessed.iter().map(|(index, _)| *index).collect();
+            assert_eq!(indices, vec![0, 1, 2, 3]);
+            assert!(preprocessed
+                .iter()
+                .all(|(_, nonces)| nonces.len() == signer.get_key_ids().len()));
+        }
+
+        // sign with the published nonces at an index, in a single round of the session `session_id` which the shares are bound to
+        let sign_at = |index: u32,
+                       session_id: SessionId,
+                       signers: &mut [v1::Signer],
+                       sig_agg: &mut v1::SignatureAggregator| {
+            let key_ids: Vec<u32> = signers.iter().flat_map(|s| s.get_key_ids()).collect();
+            let nonces: Vec<PublicNonce> = signers
+                .iter()
+                .flat_map(|s| {
+                    s.preprocessed_nonces()
+                        .into_iter()
+                        .find(|(i, _)| *i == index)
+                        .map(|(_, nonces)| nonces)
+                        .unwrap_or_default()
+                })
+                .collect();
+            let mut sig_shares = Vec::new();
+            for signer in signers.iter_mut() {
+                sig_shares.extend(signer.sign_preprocessed(
+                    index,
+                    session_id,
+                    msg,
+                    &[],
+                    &key_ids,
+                    &nonces,
+                )?);
+            }
+            let sig = sig_agg
+                .sign(session_id, msg, &nonces, &sig_shares)
+                .expect("aggregator sign failed");
+            assert!(sig.verify_xonly(&sig_agg.poly[0], msg));
+            Ok::<(), SignError>(())
+        };
+
+        sign_at(2, 20, &mut signers, &mut sig_agg).expect("signing failed");
+
+        // the consumed index survives a save and load
+        let mut signers: Vec<v1::Signer> = signers
             .iter()
-            .enumerate()
-            .map(|(id, ids)| v1::Signer::new(id.try_into().unwrap(), ids, N, T, &mut rng))
+            .map(|s| v1::Signer::load(&s.save()))
             .collect();
+        for signer in &signers {
+            let indices: Vec<u32> = signer
+                .preprocessed_nonces()
+                .iter()
+                .map(|(index, _)| *index)
+                .collect();
+            assert_eq!(indices, vec![0, 1, 3]);
+        }
+        assert!(matches!(
+            sign_at(2, 21, &mut signers, &mut sig_agg),
+            Err(SignError::NoPreprocessedNonce(_, 2))
+        ));
+        sign_at(0, 22, &mut signers, &mut sig_agg).expect("signing failed");
+    }
+
+    #[allow(non_snake_case)]
+    #[test]
+    fn refresh_shares() {
+        let mut rng = OsRng::default();
+        let msg = "It was many and many a year ago".as_bytes();
+        let (mut signers, A) = v1::test_helpers::setup_dkg(&signer_key_ids(), THRESHOLD, &mut rng);
+        let mut sig_agg =
+            v1::SignatureAggregator::new(NUM_KEYS, THRESHOLD, &A).expect("aggregator ctor failed");
+        let group_key = sig_agg.poly[0];
+        let old_signers = signers.clone();
 
-        let A = match v1::test_helpers::dkg(&mut signers, &mut rng) {
-            Ok(A) => A,
-            Err(secret_errors) => {
-                panic!("Got secret errors from DKG: {:?}", secret_errors);
+        let A = v1::test_helpers::refresh(&mut signers, &mut rng).expect("refresh failed");
+        sig_agg.refresh(&A).expect("aggregator refresh failed");
+        assert_eq!(sig_agg.poly[0], group_key);
+
+        for (old_signer, signer) in old_signers.iter().zip(&signers) {
+            for party in &signer.parties {
+                assert_eq!(party.group_key, group_key);
             }
-        };
+            let old_state = old_signer.save();
+            let state = signer.save();
+            for (id, party_state) in &state.parties {
+                assert_ne!(party_state.private_key, old_state.parties[id].private_key);
+            }
+        }
 
-        // signers [0,1,3] who have T keys
+        // the refreshed shares sign for the same group key
         {
             let mut signers = [signers[0].clone(), signers[1].clone(), signers[3].clone()].to_vec();
-            let mut sig_agg =
-                v1::SignatureAggregator::new(N, T, A.clone()).expect("aggregator ctor failed");
+            let (session_id, nonces, sig_shares) =
+                v1::test_helpers::sign(msg, &mut signers, &mut rng);
+            let sig = sig_agg
+                .sign(session_id, msg, &nonces, &sig_shares)
+                .expect("aggregator sign failed");
+            assert!(sig.verify_xonly(&group_key, msg));
+        }
 
-            let (nonces, sig_shares) = v1::test_helpers::sign(&msg, &mut signers, &mut rng);
-            if let Err(e) = sig_agg.sign(&msg, &nonces, &sig_shares) {
-                panic!("Aggregator sign failed: {:?}", e);
+        // old shares can no longer be combined with refreshed ones
+        {
+            let mut signers = [
+                old_signers[0].clone(),
+                signers[1].clone(),
+                signers[3].clone(),
+            ]
+            .to_vec();
+            let (session_id, nonces, sig_shares) =
+                v1::test_helpers::sign(msg, &mut signers, &mut rng);
+            assert!(sig_agg.sign(session_id, msg, &nonces, &sig_shares).is_err());
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[test]
+    fn versioned_binding() {
+        let mut rng = OsRng::default();
+        let msg = "It was many and many a year ago".as_bytes();
+        let (signers, A) = v1::test_helpers::setup_dkg(&signer_key_ids(), THRESHOLD, &mut rng);
+        let mut sig_agg =
+            v1::SignatureAggregator::new(NUM_KEYS, THRESHOLD, &A).expect("aggregator ctor failed");
+        sig_agg.set_protocol_version(ProtocolVersion::V1);
+
+        let mut signers = [signers[0].clone(), signers[1].clone(), signers[3].clone()].to_vec();
+        for signer in &mut signers {
+            signer.set_protocol_version(ProtocolVersion::V1);
+        }
+
+        // every party shares the session ID, which is bound into its binding factor
+        let (session_id, nonces, sig_shares) = v1::test_helpers::sign(msg, &mut signers, &mut rng);
+        let sig = sig_agg
+            .sign(session_id, msg, &nonces, &sig_shares)
+            .expect("aggregator sign failed");
+        assert!(sig.verify_xonly(&sig_agg.poly[0], msg));
+
+        // the shares do not verify for another session, or under the legacy binding factor
+        assert!(matches!(
+            sig_agg.sign(session_id.wrapping_add(1), msg, &nonces, &sig_shares),
+            Err(AggregatorError::BadPartySigs(_))
+        ));
+        sig_agg.set_protocol_version(ProtocolVersion::V0);
+        assert!(matches!(
+            sig_agg.sign(session_id, msg, &nonces, &sig_shares),
+            Err(AggregatorError::BadPartySigs(_))
+        ));
+    }
+
+    #[test]
+    fn refresh_without_private_key() {
+        let mut rng = OsRng::default();
+
+        // the party has not completed DKG, so refreshing cannot turn its missing key into a share of zero
+        let mut party = v1::Party::new(0, NUM_KEYS, THRESHOLD, &mut rng);
+        assert!(matches!(
+            party.refresh_secret(HashMap::new(), &HashMap::new()),
+            Err(DkgError::NoPrivateKey(0))
+        ));
+        assert!(party.private_key.is_zero());
+    }
+
+    #[allow(non_snake_case)]
+    #[test]
+    fn reshare_new_committee() {
+        let mut rng = OsRng::default();
+        let msg = "It was many and many a year ago".as_bytes();
+        let (signers, A) = v1::test_helpers::setup_dkg(&signer_key_ids(), THRESHOLD, &mut rng);
+        let sig_agg =
+            v1::SignatureAggregator::new(NUM_KEYS, THRESHOLD, &A).expect("aggregator ctor failed");
+        let group_key = sig_agg.poly[0];
+
+        // signers [0,1,2] hold more than T keys, so they can reshare to the new committee
+        let new_N: u32 = 6;
+        let new_key_ids: Vec<u32> = (0..new_N).collect();
+        let new_T: u32 = 4;
+        let ctx = DkgContext::new(ProtocolVersion::V0, 1, new_N, new_T);
+        let mut A = HashMap::new();
+        let mut shares = HashMap::new();
+        for signer in &signers[..3] {
+            let (comms, signer_shares) = signer.get_reshares(&ctx, &new_key_ids, &mut rng);
+            A.extend(comms);
+            shares.extend(signer_shares);
+        }
+
+        let new_signer_ids: Vec<Vec<u32>> =
+            [[0, 1].to_vec(), [2, 3].to_vec(), [4, 5].to_vec()].to_vec();
+        let new_signers: Vec<v1::Signer> = new_signer_ids
+            .iter()
+            .enumerate()
+            .map(|(id, ids)| {
+                let signer = v1::Signer::reshare(
+                    id.try_into().unwrap(),
+                    ids,
+                    &new_key_ids,
+                    &ctx,
+                    &shares,
+                    &A,
+                    &sig_agg.poly,
+                    &mut rng,
+                )
+                .expect("reshare failed");
+                v1::Signer::load(&signer.save())
+            })
+            .collect();
+        // the loaded parties keep the resharing round's context
+        assert!(new_signers
+            .iter()
+            .flat_map(|signer| &signer.parties)
+            .all(|party| party.dkg_context() == ctx));
+
+        let mut new_sig_agg =
+            v1::SignatureAggregator::reshare(&ctx, &new_key_ids, &A, &sig_agg.poly)
+                .expect("aggregator reshare failed");
+        assert_eq!(new_sig_agg.poly[0], group_key);
+        assert_eq!(new_sig_agg.poly.len(), usize::try_from(new_T).unwrap());
+
+        let mut new_signers = [new_signers[0].clone(), new_signers[2].clone()].to_vec();
+        let (session_id, nonces, sig_shares) =
+            v1::test_helpers::sign(msg, &mut new_signers, &mut rng);
+        let sig = new_sig_agg
+            .sign(session_id, msg, &nonces, &sig_shares)
+            .expect("aggregator sign failed");
+        assert!(sig.verify_xonly(&group_key, msg));
+
+        // too few old keys cannot reshare
+        let (A, _) = signers[0].get_reshares(&ctx, &new_key_ids, &mut rng);
+        assert!(v1::SignatureAggregator::reshare(&ctx, &new_key_ids, &A, &sig_agg.poly).is_err());
+    }
+
+    #[allow(non_snake_case)]
+    #[test]
+    fn qualified_dkg() {
+        let mut rng = OsRng::default();
+        let msg = "It was many and many a year ago".as_bytes();
+        let mut signers = v1::test_helpers::new_signers(&signer_key_ids(), THRESHOLD, &mut rng);
+
+        // signer 3 is offline while the others deal, so only parties [0..8) are qualified
+        let mut A = HashMap::new();
+        let mut shares = HashMap::new();
+        for signer in &signers[..3] {
+            A.extend(signer.get_poly_commitments(&mut rng));
+            shares.extend(signer.get_shares());
+        }
+        for signer in signers.iter_mut() {
+            signer
+                .compute_secrets_qualified(&shares, &A)
+                .expect("qualified DKG failed");
+        }
+
+        let ctx = DkgContext::new(ProtocolVersion::V0, 0, NUM_KEYS, THRESHOLD);
+        let key_ids: Vec<u32> = (0..NUM_KEYS).collect();
+        let mut sig_agg = v1::SignatureAggregator::new_qualified(&ctx, &key_ids, &A)
+            .expect("aggregator ctor failed");
+        assert!(signers
+            .iter()
+            .all(|s| s.parties.iter().all(|p| p.group_key == sig_agg.poly[0])));
+
+        // signers [1,2,3] include the signer which did not deal
+        let mut signers = [signers[1].clone(), signers[2].clone(), signers[3].clone()].to_vec();
+        let (session_id, nonces, sig_shares) = v1::test_helpers::sign(msg, &mut signers, &mut rng);
+        let sig = sig_agg
+            .sign(session_id, msg, &nonces, &sig_shares)
+            .expect("aggregator sign failed");
+        assert!(sig.verify_xonly(&sig_agg.poly[0], msg));
+
+        // the qualified set must hold at least T keys
+        A.remove(&6);
+        A.remove(&7);
+        assert!(matches!(
+            v1::SignatureAggregator::new_qualified(&ctx, &key_ids, &A),
+            Err(AggregatorError::NotEnoughDealers(6, 7))
+        ));
+        let mut signer = signers[0].clone();
+        match signer.compute_secrets_qualified(&shares, &A) {
+            Err(errors) => assert!(errors
+                .values()
+                .all(|e| matches!(e, DkgError::NotEnoughDealers(6, 7)))),
+            Ok(()) => panic!("expected NotEnoughDealers"),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[test]
+    fn parallel_matches_sequential() {
+        // the `parallel` feature only changes how the work is scheduled, so CI runs this with and without it
+        let mut rng = OsRng::default();
+        let msg = "It was many and many a year ago".as_bytes();
+        let (signers, A) = v1::test_helpers::setup_dkg(&signer_key_ids(), THRESHOLD, &mut rng);
+
+        for party in signers.iter().flat_map(|signer| &signer.parties) {
+            let shares = party.get_shares();
+            for key_id in 0..NUM_KEYS {
+                assert_eq!(shares[&key_id], party.f.eval(compute::id(key_id)));
             }
+            let mut private_key = Scalar::zero();
+            for dealer in signers.iter().flat_map(|signer| &signer.parties) {
+                private_key += dealer.f.eval(compute::id(party.id));
+            }
+            assert_eq!(*party.private_key, private_key);
+        }
+
+        let mut sig_agg =
+            v1::SignatureAggregator::new(NUM_KEYS, THRESHOLD, &A).expect("aggregator ctor failed");
+        let mut signers = [signers[0].clone(), signers[1].clone(), signers[3].clone()].to_vec();
+        let (session_id, nonces, sig_shares) = v1::test_helpers::sign(msg, &mut signers, &mut rng);
+        let sig = sig_agg
+            .sign(session_id, msg, &nonces, &sig_shares)
+            .expect("aggregator sign failed");
+
+        let key_ids: Vec<u32> = sig_shares.iter().map(|ss| ss.id).collect();
+        let ctx = compute::BindingContext {
+            version: sig_agg.protocol_version(),
+            group_key: &sig_agg.poly[0],
+            session_id,
+            party_ids: &key_ids,
+            key_ids: &key_ids,
+        };
+        let (_, mut R) = compute::versioned_intermediate(&ctx, msg, &nonces);
+        if !R.has_even_y() {
+            R = -R;
+        }
+        let mut z = Scalar::zero();
+        for sig_share in &sig_shares {
+            z += sig_share.z_i;
+        }
+        assert_eq!(sig.R, R);
+        assert_eq!(sig.z, z);
+    }
+
+    #[allow(non_snake_case)]
+    #[test]
+    fn aggregator_public_keys() {
+        let mut rng = OsRng::default();
+        let signer_key_ids: Vec<Vec<u32>> = [
+            [4, 9, 12].to_vec(),
+            [15, 22].to_vec(),
+            [30, 31, 40].to_vec(),
+            [50, u32::MAX].to_vec(),
+        ]
+        .to_vec();
+        let group_key_ids: Vec<u32> = signer_key_ids.iter().flatten().cloned().collect();
+        let mut signers: Vec<v1::Signer> = signer_key_ids
+            .iter()
+            .enumerate()
+            .map(|(id, ids)| {
+                let mut signer =
+                    v1::Signer::new(id.try_into().unwrap(), ids, NUM_KEYS, THRESHOLD, &mut rng);
+                signer.set_group_key_ids(&group_key_ids);
+                signer
+            })
+            .collect();
+        let A = v1::test_helpers::dkg(&mut signers, &mut rng).expect("DKG failed");
+        let mut sig_agg =
+            v1::SignatureAggregator::new_with_context(&signers[0].parties[0].dkg_context(), &A)
+                .expect("aggregator ctor failed");
+
+        // the cached public keys are the group polynomial evaluated at each key ID, and match the parties' keys
+        let check = |sig_agg: &v1::SignatureAggregator, key_ids: &[u32]| {
+            assert_eq!(sig_agg.public_keys().len(), key_ids.len());
+            for key_id in key_ids {
+                let public_key = compute::poly(&compute::id(*key_id), &sig_agg.poly)
+                    .expect("failed to evaluate the group poly");
+                assert_eq!(sig_agg.public_keys()[key_id], public_key);
+            }
+        };
+        check(&sig_agg, &group_key_ids);
+        for party in signers.iter().flat_map(|signer| &signer.parties) {
+            assert_eq!(sig_agg.public_keys()[&party.id], party.public_key);
+        }
+
+        let A = v1::test_helpers::refresh(&mut signers, &mut rng).expect("refresh failed");
+        sig_agg.refresh(&A).expect("aggregator refresh failed");
+        check(&sig_agg, &group_key_ids);
+        for party in signers.iter().flat_map(|signer| &signer.parties) {
+            assert_eq!(sig_agg.public_keys()[&party.id], party.public_key);
+        }
+
+        let new_key_ids: Vec<u32> = [2, 5, 8, 13, 21, u32::MAX].to_vec();
+        let ctx = DkgContext::new(ProtocolVersion::V0, 1, 6, 4);
+        let mut A = HashMap::new();
+        for signer in &signers {
+            let (comms, _) = signer.get_reshares(&ctx, &new_key_ids, &mut rng);
+            A.extend(comms);
         }
+        let new_sig_agg = v1::SignatureAggregator::reshare(&ctx, &new_key_ids, &A, &sig_agg.poly)
+            .expect("aggregator reshare failed");
+        check(&new_sig_agg, &new_key_ids);
     }
 }
@@ -1,23 +1,28 @@
-use hashbrown::{HashMap, HashSet};
+use hashbrown::HashMap;
 use num_traits::Zero;
 use p256k1::{
     point::{Point, G},
     scalar::Scalar,
 };
-use polynomial::Polynomial;
 use rand_core::{CryptoRng, RngCore};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use crate::common::{Nonce, PolyCommitment, PublicNonce, Signature, SignatureShare};
+use crate::common::{
+    Nonce, NonceBatch, NonceSessions, PartyIds, PolyCommitment, ProtocolVersion, PublicNonce,
+    SecretPoly, SecretScalar, SessionId, Signature, SignatureShare,
+};
 use crate::compute;
-use crate::errors::{AggregatorError, DkgError};
-use crate::schnorr::ID;
+use crate::errors::{AggregatorError, DkgError, NonceStoreError, SignError};
+use crate::reshare;
+use crate::schnorr::{DkgContext, ID};
+use crate::traits::NonceStore;
 use crate::vss::VSS;
 
 /// A map of private keys indexed by key ID
-pub type PrivKeyMap = HashMap<u32, Scalar>;
-/// A signing set of key IDs indexed by party ID
-pub type SelectedSigners = HashMap<u32, HashSet<u32>>;
+pub type PrivKeyMap = HashMap<u32, SecretScalar>;
+pub use crate::common::SelectedSigners;
 
 #[derive(Serialize, Deserialize)]
 /// The saved state required to construct a party
@@ -33,14 +38,26 @@ pub struct PartyState {
     /// The threshold for signing
     pub threshold: u32,
     /// The party's private polynomial
-    pub polynomial: Polynomial<Scalar>,
+    pub polynomial: SecretPoly,
     /// The private keys for this party, indexed by ID
     pub private_keys: PrivKeyMap,
     /// The aggregate group public key
     pub group_key: Point,
+    /// The party's preprocessed nonces which have not yet been used, so only the latest saved state may be loaded, as `NonceBatch` explains
+    #[serde(default)]
+    pub preprocessed: NonceBatch,
+    /// The signing protocol version of the party's group
+    #[serde(default)]
+    pub protocol_version: ProtocolVersion,
+    /// The ID of the party's current DKG round
+    #[serde(default)]
+    pub dkg_id: u64,
+    /// The IDs of every key in the party's group, which are `0..num_keys` if empty
+    #[serde(default)]
+    pub group_key_ids: Vec<u32>,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug)]
 #[allow(non_snake_case)]
 /// A WSTS party, which encapsulates a single polynomial, nonce, and one private key per key ID
 pub struct Party {
@@ -52,12 +69,38 @@ pub struct Party {
     num_keys: u32,
     num_parties: u32,
     threshold: u32,
-    f: Polynomial<Scalar>,
+    group_key_ids: Vec<u32>,
+    f: SecretPoly,
     private_keys: PrivKeyMap,
     group_key: Point,
-    nonce: Nonce,
+    nonces: NonceSessions,
+    preprocessed: NonceBatch,
+    protocol_version: ProtocolVersion,
+    dkg_id: u64,
+    lagrange: compute::LagrangeCache,
+}
+
+/// Parties are equal if their state is, regardless of what their Lagrange caches hold
+impl PartialEq for Party {
+    fn eq(&self, other: &Self) -> bool {
+        self.party_id == other.party_id
+            && self.key_ids == other.key_ids
+            && self.num_keys == other.num_keys
+            && self.num_parties == other.num_parties
+            && self.threshold == other.threshold
+            && self.group_key_ids == other.group_key_ids
+            && self.f == other.f
+            && self.private_keys == other.private_keys
+            && self.group_key == other.group_key
+            && self.nonces == other.nonces
+            && self.preprocessed == other.preprocessed
+            && self.protocol_version == other.protocol_version
+            && self.dkg_id == other.dkg_id
+    }
 }
 
+impl Eq for Party {}
+
 impl Party {
     #[allow(non_snake_case)]
     /// Construct a random Party with the passed party ID, key IDs, and parameters
@@ -75,26 +118,111 @@ impl Party {
             num_keys,
             num_parties,
             threshold,
+            group_key_ids: (0..num_keys).collect(),
             f: VSS::random_poly(threshold - 1, rng),
             private_keys: PrivKeyMap::new(),
             group_key: Point::zero(),
-            nonce: Nonce::zero(),
+            nonces: NonceSessions::default(),
+            preprocessed: NonceBatch::default(),
+            protocol_version: ProtocolVersion::default(),
+            dkg_id: 0,
+            lagrange: compute::LagrangeCache::default(),
         }
     }
 
     /// Load a party from `state`
     pub fn load(state: &PartyState) -> Self {
+        let group_key_ids = if state.group_key_ids.is_empty() {
+            (0..state.num_keys).collect()
+        } else {
+            state.group_key_ids.clone()
+        };
+
         Self {
             party_id: state.party_id,
             key_ids: state.key_ids.clone(),
             num_keys: state.num_keys,
             num_parties: state.num_parties,
             threshold: state.threshold,
+            group_key_ids,
             f: state.polynomial.clone(),
             private_keys: state.private_keys.clone(),
             group_key: state.group_key,
-            nonce: Nonce::zero(),
+            nonces: NonceSessions::default(),
+            preprocessed: state.preprocessed.clone(),
+            protocol_version: state.protocol_version,
+            dkg_id: state.dkg_id,
+            lagrange: compute::LagrangeCache::default(),
+        }
+    }
+
+    #[allow(non_snake_case, clippy::too_many_arguments)]
+    /// Construct a Party with the keys `key_ids` for the new committee of the resharing round `ctx`, whose keys are `group_key_ids`, from the sub-shares which the old key holders dealt, indexed by old key ID and then new key ID
+    pub fn reshare<RNG: RngCore + CryptoRng>(
+        party_id: u32,
+        key_ids: &[u32],
+        group_key_ids: &[u32],
+        num_parties: u32,
+        ctx: &DkgContext,
+        shares: &HashMap<u32, HashMap<u32, Scalar>>,
+        A: &HashMap<u32, PolyCommitment>,
+        old_poly: &[Point],
+        rng: &mut RNG,
+    ) -> Result<Self, DkgError> {
+        let poly = reshare::group_poly(A, old_poly, ctx)?;
+
+        let mut private_keys = PrivKeyMap::new();
+        for key_id in key_ids {
+            let key_shares: HashMap<u32, Scalar> = shares
+                .iter()
+                .filter_map(|(dealer, dealer_shares)| {
+                    dealer_shares.get(key_id).map(|share| (*dealer, *share))
+                })
+                .collect();
+            private_keys.insert(
+                *key_id,
+                reshare::combine_shares(*key_id, &key_shares, A)?.into(),
+            );
         }
+
+        Ok(Self {
+            party_id,
+            key_ids: key_ids.to_vec(),
+            num_keys: ctx.num_keys,
+            num_parties,
+            threshold: ctx.threshold,
+            group_key_ids: group_key_ids.to_vec(),
+            f: VSS::random_poly(ctx.threshold - 1, rng),
+            private_keys,
+            group_key: poly[0],
+            nonces: NonceSessions::default(),
+            preprocessed: NonceBatch::default(),
+            protocol_version: ctx.version,
+            dkg_id: ctx.dkg_id,
+            lagrange: compute::LagrangeCache::default(),
+        })
+    }
+
+    /// Deal sub-shares of this party's private keys to the new keys `key_ids` in the resharing round `ctx`, any `ctx.threshold` of which can reconstruct them, returning the commitments and shares indexed by old key ID
+    pub fn get_reshares<RNG: RngCore + CryptoRng>(
+        &self,
+        ctx: &DkgContext,
+        key_ids: &[u32],
+        rng: &mut RNG,
+    ) -> Result<reshare::Reshares, DkgError> {
+        let mut comms = HashMap::new();
+        let mut shares = HashMap::new();
+        for key_id in &self.key_ids {
+            let private_key = self
+                .private_keys
+                .get(key_id)
+                .ok_or(DkgError::NoPrivateKey(*key_id))?;
+            let (comm, key_shares) = reshare::deal(*key_id, private_key, ctx, key_ids, rng);
+            comms.insert(*key_id, comm);
+            shares.insert(*key_id, key_shares);
+        }
+
+        Ok((comms, shares))
     }
 
     /// Save the state required to reconstruct the party
@@ -108,42 +236,149 @@ impl Party {
             polynomial: self.f.clone(),
             private_keys: self.private_keys.clone(),
             group_key: self.group_key,
+            preprocessed: self.preprocessed.clone(),
+            protocol_version: self.protocol_version,
+            dkg_id: self.dkg_id,
+            group_key_ids: self.group_key_ids.clone(),
         }
     }
 
-    /// Generate and store a private nonce for a signing round
-    pub fn gen_nonce<RNG: RngCore + CryptoRng>(&mut self, rng: &mut RNG) -> PublicNonce {
-        self.nonce = Nonce::random(rng);
+    /// Get the signing protocol version of this party's group
+    pub fn protocol_version(&self) -> ProtocolVersion {
+        self.protocol_version
+    }
+
+    /// Set the signing protocol version of this party's group, which every signer and the aggregator must agree on
+    pub fn set_protocol_version(&mut self, version: ProtocolVersion) {
+        self.protocol_version = version;
+    }
+
+    /// Get the context of this party's current DKG round, which its proofs of possession commit to
+    pub fn dkg_context(&self) -> DkgContext {
+        DkgContext::new(
+            self.protocol_version,
+            self.dkg_id,
+            self.num_keys,
+            self.threshold,
+        )
+    }
+
+    /// Set the ID of this party's current DKG round, which must be unique for each group and round
+    pub fn set_dkg_id(&mut self, dkg_id: u64) {
+        self.dkg_id = dkg_id;
+    }
+
+    /// Get the IDs of every key in this party's group
+    pub fn group_key_ids(&self) -> &[u32] {
+        &self.group_key_ids
+    }
+
+    /// Set the IDs of every key in this party's group, which it deals shares to
+    pub fn set_group_key_ids(&mut self, key_ids: &[u32]) {
+        self.group_key_ids = key_ids.to_vec();
+    }
+
+    /// Generate and store a hedged private nonce for the signing session `session_id`, replacing any pending nonce for it
+    ///
+    /// Every party in the session and the aggregator must use the same `session_id`, since it is bound into the binding factor under `ProtocolVersion::V1`
+    pub fn gen_session_nonce<RNG: RngCore + CryptoRng>(
+        &mut self,
+        session_id: SessionId,
+        rng: &mut RNG,
+    ) -> PublicNonce {
+        let nonce = self.hedged_nonce(
+            &[b"session".as_slice(), &session_id.to_be_bytes()].concat(),
+            rng,
+        );
+        let public_nonce = PublicNonce::from(&nonce);
+        self.nonces.insert(session_id, nonce);
+
+        public_nonce
+    }
+
+    /// Generate and store a private nonce which depends only on `rng` for the signing session `session_id`, replacing any pending nonce for it
+    pub fn gen_random_nonce<RNG: RngCore + CryptoRng>(
+        &mut self,
+        session_id: SessionId,
+        rng: &mut RNG,
+    ) -> PublicNonce {
+        let nonce = Nonce::random(rng);
+        let public_nonce = PublicNonce::from(&nonce);
+        self.nonces.insert(session_id, nonce);
+
+        public_nonce
+    }
+
+    /// Generate a private nonce for the signing session `session_id` and durably record it as issued in `store`
+    ///
+    /// The nonce is only kept in `store`, so a party which restarts before signing can still sign with it using `sign_with_store`
+    pub fn gen_session_nonce_with_store<S: NonceStore, RNG: RngCore + CryptoRng>(
+        &self,
+        session_id: SessionId,
+        store: &mut S,
+        rng: &mut RNG,
+    ) -> Result<PublicNonce, NonceStoreError> {
+        let nonce = self.hedged_nonce(
+            &[b"session".as_slice(), &session_id.to_be_bytes()].concat(),
+            rng,
+        );
+        store.issue(self.party_id, session_id, &nonce)?;
+
+        Ok(PublicNonce::from(&nonce))
+    }
+
+    /// Generate and store `count` preprocessed nonces, returning their indices and public nonces so they can be published ahead of signing
+    pub fn preprocess_nonces<RNG: RngCore + CryptoRng>(
+        &mut self,
+        count: u32,
+        rng: &mut RNG,
+    ) -> Vec<(u32, PublicNonce)> {
+        (0..count)
+            .map(|_| {
+                let index = self.preprocessed.next_index().to_be_bytes();
+                let nonce = self.hedged_nonce(&[b"preprocessed".as_slice(), &index].concat(), rng);
+                self.preprocessed.push(nonce)
+            })
+            .collect()
+    }
 
-        PublicNonce::from(&self.nonce)
+    /// Get the indices and public nonces of the preprocessed nonces which have not yet been used
+    pub fn preprocessed_nonces(&self) -> Vec<(u32, PublicNonce)> {
+        self.preprocessed.public_nonces()
     }
 
     #[allow(non_snake_case)]
     /// Get a public commitment to the private polynomial
     pub fn get_poly_commitment<RNG: RngCore + CryptoRng>(&self, rng: &mut RNG) -> PolyCommitment {
         PolyCommitment {
-            id: ID::new(&self.id(), &self.f.data()[0], rng),
+            id: ID::new(&self.id(), &self.f.data()[0], &self.dkg_context(), rng),
             A: (0..self.f.data().len())
                 .map(|i| &self.f.data()[i] * G)
                 .collect(),
         }
     }
 
-    /// Get the shares of this party's private polynomial for all keys
+    /// Make a new polynomial with a zero constant term, for a share refresh round
+    pub fn reset_refresh_poly<RNG: RngCore + CryptoRng>(&mut self, rng: &mut RNG) {
+        self.f = VSS::random_zero_poly(self.threshold - 1, rng);
+    }
+
+    /// Get the shares of this party's private polynomial for every key in the group
     pub fn get_shares(&self) -> HashMap<u32, Scalar> {
-        let mut shares = HashMap::new();
-        for i in 0..self.num_keys {
-            shares.insert(i, self.f.eval(compute::id(i)));
-        }
-        shares
+        into_par_iter!(0..self.group_key_ids.len())
+            .map(|i| {
+                let key_id = self.group_key_ids[i];
+                (key_id, self.f.eval(compute::id(key_id)))
+            })
+            .collect()
     }
 
     #[allow(non_snake_case)]
-    /// Compute this party's share of the group secret key
+    /// Compute this party's shares of the group secret key from the shares for each of its keys and the poly commitments `A` of every party, both indexed by party ID
     pub fn compute_secret(
         &mut self,
         shares: &HashMap<u32, HashMap<u32, Scalar>>,
-        A: &[PolyCommitment],
+        A: &HashMap<u32, PolyCommitment>,
     ) -> Result<(), DkgError> {
         let mut missing_shares = Vec::new();
         for key_id in &self.key_ids {
@@ -155,16 +390,22 @@ impl Party {
             return Err(DkgError::MissingShares(missing_shares));
         }
 
+        self.group_key = Point::zero();
+
+        let ctx = self.dkg_context();
         let mut bad_ids = Vec::new();
-        for (i, Ai) in A.iter().enumerate() {
-            if !Ai.verify() {
-                bad_ids.push(i.try_into().unwrap());
+        for (i, Ai) in A {
+            if Ai.id.id != compute::id(*i) || !Ai.verify(&ctx) {
+                bad_ids.push(*i);
             }
-            self.group_key += Ai.A[0];
         }
         if !bad_ids.is_empty() {
+            bad_ids.sort();
             return Err(DkgError::BadIds(bad_ids));
         }
+        for Ai in A.values() {
+            self.group_key += Ai.A[0];
+        }
 
         let mut not_enough_shares = Vec::new();
         for key_id in &self.key_ids {
@@ -176,26 +417,188 @@ impl Party {
             return Err(DkgError::NotEnoughShares(not_enough_shares));
         }
 
-        let mut bad_shares = Vec::new();
+        let mut key_shares = Vec::new();
         for key_id in &self.key_ids {
             for (sender, s) in &shares[key_id] {
-                let Ai = &A[usize::try_from(*sender).unwrap()];
-                if s * G != compute::poly(&compute::id(*key_id), &Ai.A)? {
-                    bad_shares.push(*sender);
+                key_shares.push((*key_id, *sender, *s));
+            }
+        }
+        let mut bad_shares: Vec<u32> = compute::verify_shares(&key_shares, A)?
+            .into_iter()
+            .map(|i| key_shares[i].1)
+            .collect();
+        if !bad_shares.is_empty() {
+            bad_shares.sort();
+            bad_shares.dedup();
+            return Err(DkgError::BadShares(bad_shares));
+        }
+
+        for key_id in &self.key_ids {
+            let mut private_key = SecretScalar::zero();
+            for s in shares[key_id].values() {
+                *private_key += s;
+            }
+            self.private_keys.insert(*key_id, private_key);
+        }
+
+        Ok(())
+    }
+
+    #[allow(non_snake_case)]
+    /// Compute this party's shares of the group secret key from the `qualified` set of dealing parties, whose poly commitments `A` are indexed by party ID, ignoring shares from any other party
+    ///
+    /// The qualified set must hold at least as many keys as the threshold, and every qualified party must have sent a share for each of this party's keys
+    pub fn compute_secret_qualified(
+        &mut self,
+        qualified: &SelectedSigners,
+        shares: &HashMap<u32, HashMap<u32, Scalar>>,
+        A: &HashMap<u32, PolyCommitment>,
+    ) -> Result<(), DkgError> {
+        let num_keys: usize = qualified.values().map(|key_ids| key_ids.len()).sum();
+        let threshold = self.threshold.try_into().unwrap();
+        if num_keys < threshold {
+            return Err(DkgError::NotEnoughDealers(num_keys, threshold));
+        }
+
+        let mut missing_shares = Vec::new();
+        for key_id in &self.key_ids {
+            if shares.get(key_id).is_none() {
+                missing_shares.push(*key_id);
+            }
+        }
+        if !missing_shares.is_empty() {
+            return Err(DkgError::MissingShares(missing_shares));
+        }
+
+        let mut dealers: Vec<u32> = qualified.keys().cloned().collect();
+        dealers.sort();
+
+        self.group_key = Point::zero();
+
+        let ctx = self.dkg_context();
+        let mut bad_ids = Vec::new();
+        for i in &dealers {
+            match A.get(i) {
+                Some(Ai) if Ai.id.id == compute::id(*i) && Ai.verify(&ctx) => {
+                    self.group_key += Ai.A[0];
                 }
+                _ => bad_ids.push(*i),
+            }
+        }
+        if !bad_ids.is_empty() {
+            return Err(DkgError::BadIds(bad_ids));
+        }
+
+        let mut not_enough_shares = Vec::new();
+        for key_id in &self.key_ids {
+            if dealers.iter().any(|i| shares[key_id].get(i).is_none()) {
+                not_enough_shares.push(*key_id);
+            }
+        }
+        if !not_enough_shares.is_empty() {
+            return Err(DkgError::NotEnoughShares(not_enough_shares));
+        }
+
+        let comms: HashMap<u32, PolyCommitment> =
+            dealers.iter().map(|i| (*i, A[i].clone())).collect();
+        let mut key_shares = Vec::new();
+        for key_id in &self.key_ids {
+            for i in &dealers {
+                key_shares.push((*key_id, *i, shares[key_id][i]));
             }
         }
+        let mut bad_shares: Vec<u32> = compute::verify_shares(&key_shares, &comms)?
+            .into_iter()
+            .map(|k| key_shares[k].1)
+            .collect();
         if !bad_shares.is_empty() {
+            bad_shares.sort();
+            bad_shares.dedup();
             return Err(DkgError::BadShares(bad_shares));
         }
 
         for key_id in &self.key_ids {
-            self.private_keys.insert(*key_id, Scalar::zero());
+            let mut private_key = SecretScalar::zero();
+            for i in &dealers {
+                *private_key += shares[key_id][i];
+            }
+            self.private_keys.insert(*key_id, private_key);
+        }
+
+        Ok(())
+    }
+
+    #[allow(non_snake_case)]
+    /// Add refresh shares to this party's shares of the group secret key, leaving the group key unchanged
+    pub fn refresh_secret(
+        &mut self,
+        shares: &HashMap<u32, HashMap<u32, Scalar>>,
+        A: &HashMap<u32, PolyCommitment>,
+    ) -> Result<(), DkgError> {
+        // refresh shares are only added to keys which DKG already computed
+        if let Some(key_id) = self
+            .key_ids
+            .iter()
+            .find(|key_id| !self.private_keys.contains_key(*key_id))
+        {
+            return Err(DkgError::NoPrivateKey(*key_id));
+        }
+
+        let mut missing_shares = Vec::new();
+        for key_id in &self.key_ids {
+            if shares.get(key_id).is_none() {
+                missing_shares.push(*key_id);
+            }
+        }
+        if !missing_shares.is_empty() {
+            return Err(DkgError::MissingShares(missing_shares));
+        }
+
+        let ctx = self.dkg_context();
+        let mut bad_ids = Vec::new();
+        for (i, Ai) in A {
+            if Ai.id.id != compute::id(*i) || !Ai.verify_refresh(&ctx) {
+                bad_ids.push(*i);
+            }
+        }
+        if !bad_ids.is_empty() {
+            bad_ids.sort();
+            return Err(DkgError::BadRefreshCommitments(bad_ids));
+        }
+
+        // only the dealers in `A` need to send shares, so a qualified subset of the group can refresh
+        let mut not_enough_shares = Vec::new();
+        for key_id in &self.key_ids {
+            if A.keys().any(|i| shares[key_id].get(i).is_none()) {
+                not_enough_shares.push(*key_id);
+            }
+        }
+        if !not_enough_shares.is_empty() {
+            return Err(DkgError::NotEnoughShares(not_enough_shares));
+        }
+
+        let mut key_shares = Vec::new();
+        for key_id in &self.key_ids {
+            for (sender, s) in &shares[key_id] {
+                key_shares.push((*key_id, *sender, *s));
+            }
+        }
+        let mut bad_shares: Vec<u32> = compute::verify_shares(&key_shares, A)?
+            .into_iter()
+            .map(|i| key_shares[i].1)
+            .collect();
+        if !bad_shares.is_empty() {
+            bad_shares.sort();
+            bad_shares.dedup();
+            return Err(DkgError::BadShares(bad_shares));
+        }
 
-            for (_sender, s) in &shares[key_id] {
-                self.private_keys
-                    .insert(*key_id, self.private_keys[key_id] + s);
+        for key_id in &self.key_ids {
+            let mut private_key = self.private_keys[key_id].clone();
+            for s in shares[key_id].values() {
+                *private_key += s;
             }
+            self.private_keys.insert(*key_id, private_key);
         }
 
         Ok(())
@@ -208,25 +611,219 @@ impl Party {
 
     #[allow(non_snake_case)]
     /// Sign `msg` with this party's shares of the group private key, using the set of `party_ids`, `key_ids` and corresponding `nonces`
+    ///
+    /// This consumes the party's nonce for `session_id`, so each session can only sign once
     pub fn sign(
+        &mut self,
+        session_id: SessionId,
+        msg: &[u8],
+        party_ids: &[u32],
+        key_ids: &[u32],
+        nonces: &[PublicNonce],
+    ) -> Result<SignatureShare, SignError> {
+        let nonce = self.take_nonce(session_id)?;
+        // an odd group key is negated, along with every private key
+        let (key, a, _b) = compute::tweaked_key(&self.group_key, &Scalar::zero());
+
+        self.sign_with_key(nonce, session_id, msg, party_ids, key_ids, nonces, &key, &a)
+    }
+
+    /// Sign `msg` for the BIP-341 output key of the group key and an optional script `merkle_root`, using the set of `party_ids`, `key_ids` and corresponding `nonces`
+    pub fn sign_taproot(
+        &mut self,
+        session_id: SessionId,
+        msg: &[u8],
+        party_ids: &[u32],
+        key_ids: &[u32],
+        nonces: &[PublicNonce],
+        merkle_root: Option<[u8; 32]>,
+    ) -> Result<SignatureShare, SignError> {
+        let nonce = self.take_nonce(session_id)?;
+        let tweak = compute::tweak(&self.group_key, merkle_root);
+        let (key, a, _b) = compute::tweaked_key(&self.group_key, &tweak);
+
+        self.sign_with_key(nonce, session_id, msg, party_ids, key_ids, nonces, &key, &a)
+    }
+
+    /// Sign `msg` like `sign` in the signing session `session_id`, but with the preprocessed nonce at `index`, which can never be used again
+    ///
+    /// The index only selects the nonce; `session_id` is what the aggregator must check the signature shares against
+    pub fn sign_preprocessed(
+        &mut self,
+        index: u32,
+        session_id: SessionId,
+        msg: &[u8],
+        party_ids: &[u32],
+        key_ids: &[u32],
+        nonces: &[PublicNonce],
+    ) -> Result<SignatureShare, SignError> {
+        let nonce = self.take_preprocessed_nonce(index)?;
+        let (key, a, _b) = compute::tweaked_key(&self.group_key, &Scalar::zero());
+
+        self.sign_with_key(nonce, session_id, msg, party_ids, key_ids, nonces, &key, &a)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    /// Sign `msg` like `sign_taproot` in the signing session `session_id`, but with the preprocessed nonce at `index`, which can never be used again
+    pub fn sign_taproot_preprocessed(
+        &mut self,
+        index: u32,
+        session_id: SessionId,
+        msg: &[u8],
+        party_ids: &[u32],
+        key_ids: &[u32],
+        nonces: &[PublicNonce],
+        merkle_root: Option<[u8; 32]>,
+    ) -> Result<SignatureShare, SignError> {
+        let nonce = self.take_preprocessed_nonce(index)?;
+        let tweak = compute::tweak(&self.group_key, merkle_root);
+        let (key, a, _b) = compute::tweaked_key(&self.group_key, &tweak);
+
+        self.sign_with_key(nonce, session_id, msg, party_ids, key_ids, nonces, &key, &a)
+    }
+
+    /// Sign `msg` like `sign`, but with the nonce issued in `store` for `session_id`, which is durably marked as consumed before signing
+    pub fn sign_with_store<S: NonceStore>(
+        &self,
+        store: &mut S,
+        session_id: SessionId,
+        msg: &[u8],
+        party_ids: &[u32],
+        key_ids: &[u32],
+        nonces: &[PublicNonce],
+    ) -> Result<SignatureShare, SignError> {
+        let nonce = self.take_stored_nonce(store, session_id)?;
+        let (key, a, _b) = compute::tweaked_key(&self.group_key, &Scalar::zero());
+
+        self.sign_with_key(nonce, session_id, msg, party_ids, key_ids, nonces, &key, &a)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    /// Sign `msg` like `sign_taproot`, but with the nonce issued in `store` for `session_id`, which is durably marked as consumed before signing
+    pub fn sign_taproot_with_store<S: NonceStore>(
+        &self,
+        store: &mut S,
+        session_id: SessionId,
+        msg: &[u8],
+        party_ids: &[u32],
+        key_ids: &[u32],
+        nonces: &[PublicNonce],
+        merkle_root: Option<[u8; 32]>,
+    ) -> Result<SignatureShare, SignError> {
+        let nonce = self.take_stored_nonce(store, session_id)?;
+        let tweak = compute::tweak(&self.group_key, merkle_root);
+        let (key, a, _b) = compute::tweaked_key(&self.group_key, &tweak);
+
+        self.sign_with_key(nonce, session_id, msg, party_ids, key_ids, nonces, &key, &a)
+    }
+
+    #[allow(non_snake_case, clippy::too_many_arguments)]
+    /// Sign `msg` with `nonce` in `session_id` for `key`, which is `a` times the group key plus a public tweak
+    fn sign_with_key(
         &self,
+        nonce: Nonce,
+        session_id: SessionId,
         msg: &[u8],
         party_ids: &[u32],
         key_ids: &[u32],
         nonces: &[PublicNonce],
-    ) -> SignatureShare {
-        let (_R_vec, R) = compute::intermediate(msg, party_ids, nonces);
-        let c = compute::challenge(&self.group_key, &R, msg);
+        key: &Point,
+        a: &Scalar,
+    ) -> Result<SignatureShare, SignError> {
+        let ctx = compute::BindingContext {
+            version: self.protocol_version,
+            group_key: &self.group_key,
+            session_id,
+            party_ids,
+            key_ids,
+        };
+        let (_R_vec, R) = compute::versioned_intermediate(&ctx, msg, nonces);
+        let c = compute::challenge(key, &R, msg);
 
-        let mut z = &self.nonce.d + &self.nonce.e * compute::binding(&self.id(), nonces, msg);
+        let mut z = &nonce.d + &nonce.e * compute::versioned_binding(&ctx, &self.id(), nonces, msg);
+        // an odd R is negated, along with every nonce
+        if !R.has_even_y() {
+            z = -z;
+        }
+        let lambdas = self.lagrange.lambdas(key_ids);
         for key_id in self.key_ids.iter() {
-            z += c * &self.private_keys[key_id] * compute::lambda(*key_id, key_ids);
+            let lambda = match lambdas.get(key_id) {
+                Some(lambda) => *lambda,
+                None => compute::lambda(*key_id, key_ids),
+            };
+            let private_key = self
+                .private_keys
+                .get(key_id)
+                .ok_or(SignError::NoPrivateKey(*key_id))?;
+            z += c * a * **private_key * lambda;
         }
 
-        SignatureShare {
+        Ok(SignatureShare {
             id: self.party_id,
             z_i: z,
             key_ids: self.key_ids.clone(),
+        })
+    }
+
+    /// Generate a nonce hedged with this party's private keys and polynomial, for the signing `context`
+    fn hedged_nonce<RNG: RngCore + CryptoRng>(&self, context: &[u8], rng: &mut RNG) -> Nonce {
+        let mut secrets: Vec<&Scalar> = self
+            .key_ids
+            .iter()
+            .filter_map(|key_id| self.private_keys.get(key_id))
+            .map(|private_key| &**private_key)
+            .collect();
+        secrets.extend(self.f.data());
+        let context = [self.party_id.to_be_bytes().as_slice(), context].concat();
+
+        Nonce::hedged(&secrets, &context, rng)
+    }
+
+    /// Check that this party holds a private key for each of its keys, so that it can sign
+    fn check_private_keys(&self) -> Result<(), SignError> {
+        match self
+            .key_ids
+            .iter()
+            .find(|key_id| !self.private_keys.contains_key(*key_id))
+        {
+            Some(key_id) => Err(SignError::NoPrivateKey(*key_id)),
+            None => Ok(()),
+        }
+    }
+
+    /// Take the nonce for the signing session `session_id`, so that it can never be used again
+    fn take_nonce(&mut self, session_id: SessionId) -> Result<Nonce, SignError> {
+        // never consume a nonce which this party cannot sign with
+        self.check_private_keys()?;
+        match self.nonces.take(session_id) {
+            None => Err(SignError::NoNonce(self.party_id)),
+            Some(nonce) if nonce.is_zero() => Err(SignError::ZeroNonce(self.party_id)),
+            Some(nonce) => Ok(nonce),
+        }
+    }
+
+    /// Consume the nonce issued in `store` for `session_id`, so that it can never be used again even after a restart
+    fn take_stored_nonce<S: NonceStore>(
+        &self,
+        store: &mut S,
+        session_id: SessionId,
+    ) -> Result<Nonce, SignError> {
+        self.check_private_keys()?;
+        let nonce = store.consume(self.party_id, session_id)?;
+        if nonce.is_zero() {
+            return Err(SignError::ZeroNonce(self.party_id));
+        }
+
+        Ok(nonce)
+    }
+
+    /// Take the preprocessed nonce at `index`, so that it can never be used again
+    fn take_preprocessed_nonce(&mut self, index: u32) -> Result<Nonce, SignError> {
+        self.check_private_keys()?;
+        match self.preprocessed.take(index) {
+            None => Err(SignError::NoPreprocessedNonce(self.party_id, index)),
+            Some(nonce) if nonce.is_zero() => Err(SignError::ZeroNonce(self.party_id)),
+            Some(nonce) => Ok(nonce),
         }
     }
 }
@@ -240,19 +837,41 @@ pub struct SignatureAggregator {
     pub threshold: u32,
     /// The aggregate group polynomial; poly[0] is the group public key
     pub poly: Vec<Point>,
+    /// The public key of each key ID, cached from the group polynomial
+    public_keys: HashMap<u32, Point>,
+    key_ids: Vec<u32>,
+    lagrange: compute::LagrangeCache,
+    protocol_version: ProtocolVersion,
+    dkg_id: u64,
 }
 
 impl SignatureAggregator {
     #[allow(non_snake_case)]
-    /// Construct a SignatureAggregator with the passed parameters and polynomial commitments
+    /// Construct a SignatureAggregator with the passed parameters and polynomial commitments, indexed by party ID, for a group with the key IDs `0..num_keys`, whose proofs of possession are checked without a DKG context
     pub fn new(
         num_keys: u32,
         threshold: u32,
-        A: Vec<PolyCommitment>, // one per party_id
+        A: &HashMap<u32, PolyCommitment>,
+    ) -> Result<Self, AggregatorError> {
+        let key_ids: Vec<u32> = (0..num_keys).collect();
+
+        Self::new_with_context(
+            &DkgContext::new(ProtocolVersion::V0, 0, num_keys, threshold),
+            &key_ids,
+            A,
+        )
+    }
+
+    #[allow(non_snake_case)]
+    /// Construct a SignatureAggregator for the group of the DKG round `ctx`, whose keys are `key_ids`, from the polynomial commitments made in it, indexed by party ID
+    pub fn new_with_context(
+        ctx: &DkgContext,
+        key_ids: &[u32],
+        A: &HashMap<u32, PolyCommitment>,
     ) -> Result<Self, AggregatorError> {
         let mut bad_poly_commitments = Vec::new();
-        for A_i in &A {
-            if !A_i.verify() {
+        for (i, A_i) in A {
+            if A_i.id.id != compute::id(*i) || !A_i.verify(ctx) {
                 bad_poly_commitments.push(A_i.id.id);
             }
         }
@@ -260,69 +879,256 @@ impl SignatureAggregator {
             return Err(AggregatorError::BadPolyCommitments(bad_poly_commitments));
         }
 
-        let mut poly = Vec::with_capacity(threshold.try_into().unwrap());
+        let mut poly = Vec::with_capacity(ctx.threshold.try_into().unwrap());
 
         for i in 0..poly.capacity() {
             poly.push(Point::zero());
-            for p in &A {
+            for p in A.values() {
                 poly[i] += &p.A[i];
             }
         }
 
-        Ok(Self {
-            num_keys,
-            threshold,
-            poly,
-        })
+        Ok(Self::from_poly(ctx, key_ids, poly))
     }
 
     #[allow(non_snake_case)]
-    /// Check and aggregate the party signatures
-    pub fn sign(
-        &mut self,
-        msg: &[u8],
-        nonces: &[PublicNonce],
-        sig_shares: &[SignatureShare],
+    /// Construct a SignatureAggregator for the group of the DKG round `ctx`, whose keys are `key_ids`, from the `qualified` set of parties and their poly commitments `A`, indexed by party ID
+    pub fn new_qualified(
+        ctx: &DkgContext,
         key_ids: &[u32],
-    ) -> Result<Signature, AggregatorError> {
-        if nonces.len() != sig_shares.len() {
-            return Err(AggregatorError::BadNonceLen(nonces.len(), sig_shares.len()));
+        qualified: &SelectedSigners,
+        A: &HashMap<u32, PolyCommitment>,
+    ) -> Result<Self, AggregatorError> {
+        let num_keys: usize = qualified.values().map(|key_ids| key_ids.len()).sum();
+        let threshold = ctx.threshold.try_into().unwrap();
+        if num_keys < threshold {
+            return Err(AggregatorError::NotEnoughDealers(num_keys, threshold));
+        }
+
+        let mut bad_poly_commitments = Vec::new();
+        let mut comms = Vec::new();
+        for i in qualified.keys() {
+            match A.get(i) {
+                Some(A_i) if A_i.id.id == compute::id(*i) && A_i.verify(ctx) => comms.push(A_i),
+                Some(A_i) => bad_poly_commitments.push(A_i.id.id),
+                None => bad_poly_commitments.push(compute::id(*i)),
+            }
+        }
+        if !bad_poly_commitments.is_empty() {
+            return Err(AggregatorError::BadPolyCommitments(bad_poly_commitments));
+        }
+
+        let mut poly = Vec::with_capacity(threshold);
+
+        for i in 0..poly.capacity() {
+            poly.push(Point::zero());
+            for p in &comms {
+                poly[i] += &p.A[i];
+            }
+        }
+
+        Ok(Self::from_poly(ctx, key_ids, poly))
+    }
+
+    #[allow(non_snake_case)]
+    /// Construct a SignatureAggregator for the new committee of the resharing round `ctx`, whose keys are `key_ids`, from the reshare commitments `A`, indexed by old key ID, and the old group polynomial
+    pub fn reshare(
+        ctx: &DkgContext,
+        key_ids: &[u32],
+        A: &HashMap<u32, PolyCommitment>,
+        old_poly: &[Point],
+    ) -> Result<Self, AggregatorError> {
+        let poly = reshare::group_poly(A, old_poly, ctx).map_err(AggregatorError::Reshare)?;
+
+        Ok(Self::from_poly(ctx, key_ids, poly))
+    }
+
+    /// Construct a SignatureAggregator for the group of the DKG round `ctx` with the key IDs `key_ids` and the group polynomial `poly`
+    fn from_poly(ctx: &DkgContext, key_ids: &[u32], poly: Vec<Point>) -> Self {
+        Self {
+            num_keys: ctx.num_keys,
+            threshold: ctx.threshold,
+            public_keys: compute::public_keys(key_ids, &poly),
+            key_ids: key_ids.to_vec(),
+            poly,
+            lagrange: compute::LagrangeCache::default(),
+            protocol_version: ctx.version,
+            dkg_id: ctx.dkg_id,
+        }
+    }
+
+    #[allow(non_snake_case)]
+    /// Add the refresh poly commitments, indexed by party ID, to the group polynomial, leaving the group public key unchanged
+    pub fn refresh(&mut self, A: &HashMap<u32, PolyCommitment>) -> Result<(), AggregatorError> {
+        let ctx = self.dkg_context();
+        let bad_refresh_commitments: Vec<Scalar> = A
+            .iter()
+            .filter(|(i, A_i)| A_i.id.id != compute::id(**i) || !A_i.verify_refresh(&ctx))
+            .map(|(_, A_i)| A_i.id.id)
+            .collect();
+        if !bad_refresh_commitments.is_empty() {
+            return Err(AggregatorError::BadRefreshCommitments(
+                bad_refresh_commitments,
+            ));
+        }
+
+        for (i, p) in self.poly.iter_mut().enumerate() {
+            for A_i in A.values() {
+                *p += &A_i.A[i];
+            }
+        }
+        self.public_keys = compute::public_keys(&self.key_ids, &self.poly);
+
+        Ok(())
+    }
+
+    /// Get the public key of each key ID, indexed by key ID
+    pub fn public_keys(&self) -> &HashMap<u32, Point> {
+        &self.public_keys
+    }
+
+    /// Set the IDs of every key in the group, whose public keys signature shares are checked against
+    pub fn set_group_key_ids(&mut self, key_ids: &[u32]) {
+        self.key_ids = key_ids.to_vec();
+        self.public_keys = compute::public_keys(&self.key_ids, &self.poly);
+    }
+
+    /// Get the signing protocol version of the group
+    pub fn protocol_version(&self) -> ProtocolVersion {
+        self.protocol_version
+    }
+
+    /// Set the signing protocol version of the group, which every signer must agree on
+    pub fn set_protocol_version(&mut self, version: ProtocolVersion) {
+        self.protocol_version = version;
+    }
+
+    /// Get the context of the group's current DKG round, which proofs of possession must commit to
+    pub fn dkg_context(&self) -> DkgContext {
+        DkgContext::new(
+            self.protocol_version,
+            self.dkg_id,
+            self.num_keys,
+            self.threshold,
+        )
+    }
+
+    /// Set the ID of the group's current DKG round, which must be unique for each group and round
+    pub fn set_dkg_id(&mut self, dkg_id: u64) {
+        self.dkg_id = dkg_id;
+    }
+
+    #[allow(non_snake_case)]
+    /// Check and aggregate the party signatures from signing session `session_id`
+    pub fn sign(
+        &mut self,
+        session_id: SessionId,
+        msg: &[u8],
+        nonces: &[PublicNonce],
+        sig_shares: &[SignatureShare],
+        key_ids: &[u32],
+    ) -> Result<Signature, AggregatorError> {
+        // an odd group key is negated, along with every public key share
+        let (key, a, b) = compute::tweaked_key(&self.poly[0], &Scalar::zero());
+
+        self.sign_with_key(session_id, msg, nonces, sig_shares, key_ids, &key, &a, &b)
+    }
+
+    #[allow(non_snake_case)]
+    /// Check and aggregate the party signatures from signing session `session_id` for the BIP-341 output key of the group key and an optional script `merkle_root`
+    pub fn sign_taproot(
+        &mut self,
+        session_id: SessionId,
+        msg: &[u8],
+        nonces: &[PublicNonce],
+        sig_shares: &[SignatureShare],
+        key_ids: &[u32],
+        merkle_root: Option<[u8; 32]>,
+    ) -> Result<Signature, AggregatorError> {
+        let tweak = compute::tweak(&self.poly[0], merkle_root);
+        let (key, a, b) = compute::tweaked_key(&self.poly[0], &tweak);
+
+        self.sign_with_key(session_id, msg, nonces, sig_shares, key_ids, &key, &a, &b)
+    }
+
+    #[allow(non_snake_case, clippy::too_many_arguments)]
+    /// Check and aggregate the party signatures for `key`, which is `a` times the group key plus `b` times G
+    fn sign_with_key(
+        &mut self,
+        session_id: SessionId,
+        msg: &[u8],
+        nonces: &[PublicNonce],
+        sig_shares: &[SignatureShare],
+        key_ids: &[u32],
+        key: &Point,
+        a: &Scalar,
+        b: &Scalar,
+    ) -> Result<Signature, AggregatorError> {
+        if nonces.len() != sig_shares.len() {
+            return Err(AggregatorError::BadNonceLen(nonces.len(), sig_shares.len()));
         }
 
         let party_ids: Vec<u32> = sig_shares.iter().map(|ss| ss.id).collect();
-        let (Ris, R) = compute::intermediate(msg, &party_ids, nonces);
+        let ctx = compute::BindingContext {
+            version: self.protocol_version,
+            group_key: &self.poly[0],
+            session_id,
+            party_ids: &party_ids,
+            key_ids,
+        };
+        let (mut Ris, mut R) = compute::versioned_intermediate(&ctx, msg, nonces);
+        // an odd R is negated, along with every party's nonce commitment
+        if !R.has_even_y() {
+            Ris = Ris.iter().map(|R_i| -R_i).collect();
+            R = -R;
+        }
         let mut z = Scalar::zero();
-        let c = compute::challenge(&self.poly[0], &R, msg);
+        let c = compute::challenge(key, &R, msg);
         let mut bad_party_keys = Vec::new();
         let mut bad_party_sigs = Vec::new();
+        let lambdas = self.lagrange.lambdas(key_ids);
+
+        // check each party signature independently, then collect the results in order
+        let checks: Vec<(usize, bool)> = into_par_iter!(0..sig_shares.len())
+            .map(|i| {
+                let mut bad_keys = 0;
+                let mut cx = Point::zero();
+
+                for key_id in &sig_shares[i].key_ids {
+                    let public_key = match self.public_keys.get(key_id) {
+                        Some(p) => *p,
+                        None => {
+                            bad_keys += 1;
+                            Point::zero()
+                        }
+                    };
+                    let lambda = match lambdas.get(key_id) {
+                        Some(lambda) => *lambda,
+                        None => compute::lambda(*key_id, key_ids),
+                    };
+
+                    cx += lambda * c * a * public_key;
+                }
 
-        for i in 0..sig_shares.len() {
-            let z_i = sig_shares[i].z_i;
-            let mut cx = Point::zero();
-
-            for key_id in &sig_shares[i].key_ids {
-                let kid = compute::id(*key_id);
-                let public_key = match compute::poly(&kid, &self.poly) {
-                    Ok(p) => p,
-                    Err(_) => {
-                        bad_party_keys.push(sig_shares[i].id);
-                        Point::zero()
-                    }
-                };
+                (bad_keys, sig_shares[i].z_i * G != (Ris[i] + cx))
+            })
+            .collect();
 
-                cx += compute::lambda(*key_id, key_ids) * c * public_key;
+        for (sig_share, (bad_keys, bad_sig)) in sig_shares.iter().zip(checks) {
+            for _ in 0..bad_keys {
+                bad_party_keys.push(sig_share.id);
             }
-
-            if z_i * G != (Ris[i] + cx) {
-                bad_party_sigs.push(sig_shares[i].id);
+            if bad_sig {
+                bad_party_sigs.push(sig_share.id);
             }
 
-            z += z_i;
+            z += sig_share.z_i;
         }
+        z += c * b;
 
         if bad_party_sigs.is_empty() {
             let sig = Signature { R, z };
-            if sig.verify(&self.poly[0], msg) {
+            if sig.verify(key, msg) {
                 Ok(sig)
             } else {
                 Err(AggregatorError::BadGroupSig)
@@ -335,12 +1141,90 @@ impl SignatureAggregator {
     }
 }
 
+impl crate::traits::Aggregator for SignatureAggregator {
+    const PARTY_IDS: PartyIds = PartyIds::SignerIds;
+
+    #[allow(non_snake_case)]
+    fn new(
+        num_keys: u32,
+        threshold: u32,
+        A: &HashMap<u32, PolyCommitment>,
+    ) -> Result<Self, AggregatorError> {
+        SignatureAggregator::new(num_keys, threshold, A)
+    }
+
+    #[allow(non_snake_case)]
+    fn new_with_context(
+        ctx: &DkgContext,
+        key_ids: &[u32],
+        A: &HashMap<u32, PolyCommitment>,
+    ) -> Result<Self, AggregatorError> {
+        SignatureAggregator::new_with_context(ctx, key_ids, A)
+    }
+
+    #[allow(non_snake_case)]
+    fn new_qualified(
+        ctx: &DkgContext,
+        key_ids: &[u32],
+        qualified: &SelectedSigners,
+        A: &HashMap<u32, PolyCommitment>,
+    ) -> Result<Self, AggregatorError> {
+        SignatureAggregator::new_qualified(ctx, key_ids, qualified, A)
+    }
+
+    fn get_poly(&self) -> &[Point] {
+        &self.poly
+    }
+
+    fn get_public_keys(&self) -> &HashMap<u32, Point> {
+        self.public_keys()
+    }
+
+    #[allow(non_snake_case)]
+    fn refresh(&mut self, A: &HashMap<u32, PolyCommitment>) -> Result<(), AggregatorError> {
+        self.refresh(A)
+    }
+
+    fn set_protocol_version(&mut self, version: ProtocolVersion) {
+        self.set_protocol_version(version)
+    }
+
+    fn set_group_key_ids(&mut self, key_ids: &[u32]) {
+        self.set_group_key_ids(key_ids)
+    }
+
+    fn sign(
+        &mut self,
+        session_id: SessionId,
+        msg: &[u8],
+        nonces: &[PublicNonce],
+        sig_shares: &[SignatureShare],
+        key_ids: &[u32],
+    ) -> Result<Signature, AggregatorError> {
+        self.sign(session_id, msg, nonces, sig_shares, key_ids)
+    }
+
+    fn sign_taproot(
+        &mut self,
+        session_id: SessionId,
+        msg: &[u8],
+        nonces: &[PublicNonce],
+        sig_shares: &[SignatureShare],
+        key_ids: &[u32],
+        merkle_root: Option<[u8; 32]>,
+    ) -> Result<Signature, AggregatorError> {
+        self.sign_taproot(session_id, msg, nonces, sig_shares, key_ids, merkle_root)
+    }
+}
+
 /// Typedef so we can use the same tokens for v1 and v2
 pub type SignerState = PartyState;
 /// Typedef so we can use the same tokens for v1 and v2
 pub type Signer = Party;
 
 impl crate::traits::Signer for Party {
+    const PARTY_IDS: PartyIds = PartyIds::SignerIds;
+
     fn get_id(&self) -> u32 {
         self.party_id
     }
@@ -349,8 +1233,15 @@ impl crate::traits::Signer for Party {
         self.key_ids.clone()
     }
 
-    fn get_poly_commitments<RNG: RngCore + CryptoRng>(&self, rng: &mut RNG) -> Vec<PolyCommitment> {
-        vec![self.get_poly_commitment(rng)]
+    fn get_poly_commitments<RNG: RngCore + CryptoRng>(
+        &self,
+        rng: &mut RNG,
+    ) -> HashMap<u32, PolyCommitment> {
+        let mut comms = HashMap::new();
+
+        comms.insert(self.party_id, self.get_poly_commitment(rng));
+
+        comms
     }
 
     fn reset_polys<RNG: RngCore + CryptoRng>(&mut self, rng: &mut RNG) {
@@ -368,14 +1259,16 @@ impl crate::traits::Signer for Party {
     fn compute_secrets(
         &mut self,
         private_shares: &HashMap<u32, HashMap<u32, Scalar>>,
-        polys: &[PolyCommitment],
+        polys: &HashMap<u32, PolyCommitment>,
     ) -> Result<(), HashMap<u32, DkgError>> {
         // go through the shares, looking for this party's
         let mut key_shares = HashMap::new();
         for key_id in self.get_key_ids() {
             let mut shares = HashMap::new();
             for (signer_id, signer_shares) in private_shares.iter() {
-                shares.insert(*signer_id, signer_shares[&key_id]);
+                if let Some(share) = signer_shares.get(&key_id) {
+                    shares.insert(*signer_id, *share);
+                }
             }
             key_shares.insert(key_id, shares);
         }
@@ -390,8 +1283,112 @@ impl crate::traits::Signer for Party {
         }
     }
 
-    fn gen_nonces<RNG: RngCore + CryptoRng>(&mut self, rng: &mut RNG) -> Vec<PublicNonce> {
-        vec![self.gen_nonce(rng)]
+    fn compute_secrets_qualified(
+        &mut self,
+        qualified: &SelectedSigners,
+        private_shares: &HashMap<u32, HashMap<u32, Scalar>>,
+        polys: &HashMap<u32, PolyCommitment>,
+    ) -> Result<(), HashMap<u32, DkgError>> {
+        // go through the shares, looking for this party's
+        let mut key_shares = HashMap::new();
+        for key_id in self.get_key_ids() {
+            let mut shares = HashMap::new();
+            for (signer_id, signer_shares) in private_shares.iter() {
+                if let Some(share) = signer_shares.get(&key_id) {
+                    shares.insert(*signer_id, *share);
+                }
+            }
+            key_shares.insert(key_id, shares);
+        }
+
+        match self.compute_secret_qualified(qualified, &key_shares, polys) {
+            Ok(()) => Ok(()),
+            Err(dkg_error) => {
+                let mut dkg_errors = HashMap::new();
+                dkg_errors.insert(self.party_id, dkg_error);
+                Err(dkg_errors)
+            }
+        }
+    }
+
+    fn reset_refresh_polys<RNG: RngCore + CryptoRng>(&mut self, rng: &mut RNG) {
+        self.reset_refresh_poly(rng);
+    }
+
+    fn refresh_secrets(
+        &mut self,
+        private_shares: &HashMap<u32, HashMap<u32, Scalar>>,
+        polys: &HashMap<u32, PolyCommitment>,
+    ) -> Result<(), HashMap<u32, DkgError>> {
+        // go through the shares, looking for this party's
+        let mut key_shares = HashMap::new();
+        for key_id in self.get_key_ids() {
+            let mut shares = HashMap::new();
+            for (signer_id, signer_shares) in private_shares.iter() {
+                if let Some(share) = signer_shares.get(&key_id) {
+                    shares.insert(*signer_id, *share);
+                }
+            }
+            key_shares.insert(key_id, shares);
+        }
+
+        match self.refresh_secret(&key_shares, polys) {
+            Ok(()) => Ok(()),
+            Err(dkg_error) => {
+                let mut dkg_errors = HashMap::new();
+                dkg_errors.insert(self.party_id, dkg_error);
+                Err(dkg_errors)
+            }
+        }
+    }
+
+    fn gen_session_nonces<RNG: RngCore + CryptoRng>(
+        &mut self,
+        session_id: SessionId,
+        rng: &mut RNG,
+    ) -> Vec<PublicNonce> {
+        vec![self.gen_session_nonce(session_id, rng)]
+    }
+
+    fn gen_session_nonces_with_store<S: NonceStore, RNG: RngCore + CryptoRng>(
+        &mut self,
+        session_id: SessionId,
+        store: &mut S,
+        rng: &mut RNG,
+    ) -> Result<Vec<PublicNonce>, NonceStoreError> {
+        Ok(vec![
+            self.gen_session_nonce_with_store(session_id, store, rng)?
+        ])
+    }
+
+    fn preprocess_nonces<RNG: RngCore + CryptoRng>(
+        &mut self,
+        count: u32,
+        rng: &mut RNG,
+    ) -> Vec<(u32, Vec<PublicNonce>)> {
+        self.preprocess_nonces(count, rng)
+            .into_iter()
+            .map(|(index, nonce)| (index, vec![nonce]))
+            .collect()
+    }
+
+    fn preprocessed_nonces(&self) -> Vec<(u32, Vec<PublicNonce>)> {
+        self.preprocessed_nonces()
+            .into_iter()
+            .map(|(index, nonce)| (index, vec![nonce]))
+            .collect()
+    }
+
+    fn set_protocol_version(&mut self, version: ProtocolVersion) {
+        self.set_protocol_version(version)
+    }
+
+    fn set_dkg_id(&mut self, dkg_id: u64) {
+        self.set_dkg_id(dkg_id)
+    }
+
+    fn set_group_key_ids(&mut self, key_ids: &[u32]) {
+        self.set_group_key_ids(key_ids)
     }
 
     fn compute_intermediate(
@@ -404,19 +1401,112 @@ impl crate::traits::Signer for Party {
     }
 
     fn sign(
-        &self,
+        &mut self,
+        session_id: SessionId,
+        msg: &[u8],
+        signer_ids: &[u32],
+        key_ids: &[u32],
+        nonces: &[PublicNonce],
+    ) -> Result<Vec<SignatureShare>, SignError> {
+        Ok(vec![
+            self.sign(session_id, msg, signer_ids, key_ids, nonces)?
+        ])
+    }
+
+    fn sign_taproot(
+        &mut self,
+        session_id: SessionId,
+        msg: &[u8],
+        signer_ids: &[u32],
+        key_ids: &[u32],
+        nonces: &[PublicNonce],
+        merkle_root: Option<[u8; 32]>,
+    ) -> Result<Vec<SignatureShare>, SignError> {
+        Ok(vec![self.sign_taproot(
+            session_id,
+            msg,
+            signer_ids,
+            key_ids,
+            nonces,
+            merkle_root,
+        )?])
+    }
+
+    fn sign_with_store<S: NonceStore>(
+        &mut self,
+        store: &mut S,
+        session_id: SessionId,
+        msg: &[u8],
+        signer_ids: &[u32],
+        key_ids: &[u32],
+        nonces: &[PublicNonce],
+    ) -> Result<Vec<SignatureShare>, SignError> {
+        Ok(vec![Party::sign_with_store(
+            self, store, session_id, msg, signer_ids, key_ids, nonces,
+        )?])
+    }
+
+    fn sign_taproot_with_store<S: NonceStore>(
+        &mut self,
+        store: &mut S,
+        session_id: SessionId,
+        msg: &[u8],
+        signer_ids: &[u32],
+        key_ids: &[u32],
+        nonces: &[PublicNonce],
+        merkle_root: Option<[u8; 32]>,
+    ) -> Result<Vec<SignatureShare>, SignError> {
+        Ok(vec![Party::sign_taproot_with_store(
+            self,
+            store,
+            session_id,
+            msg,
+            signer_ids,
+            key_ids,
+            nonces,
+            merkle_root,
+        )?])
+    }
+
+    fn sign_preprocessed(
+        &mut self,
+        index: u32,
+        session_id: SessionId,
+        msg: &[u8],
+        signer_ids: &[u32],
+        key_ids: &[u32],
+        nonces: &[PublicNonce],
+    ) -> Result<Vec<SignatureShare>, SignError> {
+        Ok(vec![self.sign_preprocessed(
+            index, session_id, msg, signer_ids, key_ids, nonces,
+        )?])
+    }
+
+    fn sign_taproot_preprocessed(
+        &mut self,
+        index: u32,
+        session_id: SessionId,
         msg: &[u8],
         signer_ids: &[u32],
         key_ids: &[u32],
         nonces: &[PublicNonce],
-    ) -> Vec<SignatureShare> {
-        vec![self.sign(msg, signer_ids, key_ids, nonces)]
+        merkle_root: Option<[u8; 32]>,
+    ) -> Result<Vec<SignatureShare>, SignError> {
+        Ok(vec![self.sign_taproot_preprocessed(
+            index,
+            session_id,
+            msg,
+            signer_ids,
+            key_ids,
+            nonces,
+            merkle_root,
+        )?])
     }
 }
 
 /// Helper functions for tests
 pub mod test_helpers {
-    use crate::common::{PolyCommitment, PublicNonce};
+    use crate::common::{PolyCommitment, PublicNonce, SessionId};
     use crate::errors::DkgError;
     use crate::v2;
     use crate::v2::SignatureShare;
@@ -424,14 +1514,54 @@ pub mod test_helpers {
     use hashbrown::HashMap;
     use rand_core::{CryptoRng, RngCore};
 
+    /// Construct a party for each entry of `party_key_ids`, with party IDs counting up from zero
+    pub fn new_parties<RNG: RngCore + CryptoRng>(
+        party_key_ids: &[Vec<u32>],
+        threshold: u32,
+        rng: &mut RNG,
+    ) -> Vec<v2::Party> {
+        let num_parties = party_key_ids.len().try_into().unwrap();
+        let num_keys = party_key_ids.iter().map(|k| k.len()).sum::<usize>();
+        let num_keys = num_keys.try_into().unwrap();
+        party_key_ids
+            .iter()
+            .enumerate()
+            .map(|(pid, pkids)| {
+                v2::Party::new(
+                    pid.try_into().unwrap(),
+                    pkids,
+                    num_parties,
+                    num_keys,
+                    threshold,
+                    rng,
+                )
+            })
+            .collect()
+    }
+
+    #[allow(non_snake_case)]
+    /// Construct parties as `new_parties` does and run a distributed key generation round among them
+    pub fn setup_dkg<RNG: RngCore + CryptoRng>(
+        party_key_ids: &[Vec<u32>],
+        threshold: u32,
+        rng: &mut RNG,
+    ) -> (Vec<v2::Party>, HashMap<u32, PolyCommitment>) {
+        let mut parties = new_parties(party_key_ids, threshold, rng);
+        let A = dkg(&mut parties, rng).expect("DKG failed");
+
+        (parties, A)
+    }
+
     #[allow(non_snake_case)]
     /// Run a distributed key generation round
     pub fn dkg<RNG: RngCore + CryptoRng>(
         signers: &mut [v2::Party],
         rng: &mut RNG,
-    ) -> Result<Vec<PolyCommitment>, HashMap<u32, DkgError>> {
-        let polys: Vec<PolyCommitment> =
-            signers.iter().map(|s| s.get_poly_commitment(rng)).collect();
+    ) -> Result<HashMap<u32, PolyCommitment>, HashMap<u32, DkgError>> {
+        let polys: HashMap<u32, PolyCommitment> = signers
+            .iter()
+            .map(|s| (s.party_id, s.get_poly_commitment(rng)))
+            .collect();
 
         // each party broadcasts their commitments
         let mut broadcast_shares = Vec::new();
@@ -466,29 +1596,111 @@ pub mod test_helpers {
         }
     }
 
-    /// Run a signing round for the passed `msg`
+    #[allow(non_snake_case)]
+    /// Run a share refresh round, which changes every party's private keys but not the group key
+    pub fn refresh<RNG: RngCore + CryptoRng>(
+        signers: &mut [v2::Party],
+        rng: &mut RNG,
+    ) -> Result<HashMap<u32, PolyCommitment>, HashMap<u32, DkgError>> {
+        for party in signers.iter_mut() {
+            party.reset_refresh_poly(rng);
+        }
+
+        let polys: HashMap<u32, PolyCommitment> = signers
+            .iter()
+            .map(|s| (s.party_id, s.get_poly_commitment(rng)))
+            .collect();
+
+        let mut broadcast_shares = Vec::new();
+        for party in signers.iter() {
+            broadcast_shares.push((party.party_id, party.get_shares()));
+        }
+
+        let mut secret_errors = HashMap::new();
+        for party in signers.iter_mut() {
+            let mut party_shares = HashMap::new();
+            for key_id in party.key_ids.clone() {
+                let mut key_shares = HashMap::new();
+
+                for (id, shares) in &broadcast_shares {
+                    key_shares.insert(*id, shares[&key_id]);
+                }
+
+                party_shares.insert(key_id, key_shares);
+            }
+
+            if let Err(secret_error) = party.refresh_secret(&party_shares, &polys) {
+                secret_errors.insert(party.party_id, secret_error);
+            }
+        }
+
+        if secret_errors.is_empty() {
+            Ok(polys)
+        } else {
+            Err(secret_errors)
+        }
+    }
+
+    /// Run a signing round for the passed `msg`, returning the session ID which the aggregator must check the signature shares against
     pub fn sign<RNG: RngCore + CryptoRng>(
         msg: &[u8],
         signers: &mut [v2::Party],
         rng: &mut RNG,
-    ) -> (Vec<PublicNonce>, Vec<SignatureShare>, Vec<u32>) {
+    ) -> (SessionId, Vec<PublicNonce>, Vec<SignatureShare>, Vec<u32>) {
         let party_ids: Vec<u32> = signers.iter().map(|s| s.party_id).collect();
         let key_ids: Vec<u32> = signers.iter().flat_map(|s| s.key_ids.clone()).collect();
-        let nonces: Vec<PublicNonce> = signers.iter_mut().map(|s| s.gen_nonce(rng)).collect();
+        // every party shares the session ID, which is bound into the binding factor under ProtocolVersion::V1
+        let session_id = rng.next_u64();
+        let nonces: Vec<PublicNonce> = signers
+            .iter_mut()
+            .map(|s| s.gen_session_nonce(session_id, rng))
+            .collect();
         let shares = signers
-            .iter()
-            .map(|s| s.sign(msg, &party_ids, &key_ids, &nonces))
+            .iter_mut()
+            .map(|s| {
+                s.sign(session_id, msg, &party_ids, &key_ids, &nonces)
+                    .expect("signing failed")
+            })
             .collect();
 
-        (nonces, shares, key_ids)
+        (session_id, nonces, shares, key_ids)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::common::{
+        Nonce, NonceSessions, PolyCommitment, ProtocolVersion, PublicNonce, SessionId,
+        SignatureShare,
+    };
+    use crate::errors::{AggregatorError, DkgError, NonceStoreError, SignError};
+    use crate::nonce_store::FileNonceStore;
+    use crate::schnorr::DkgContext;
     use crate::v2;
+    use crate::{compute, Scalar, G};
 
+    use core::time::Duration;
+    use hashbrown::{HashMap, HashSet};
+    use num_traits::Zero;
     use rand_core::OsRng;
+    use std::time::Instant;
+
+    /// The number of keys in the group most tests use
+    const NUM_KEYS: u32 = 10;
+
+    /// The threshold of the group most tests use
+    const THRESHOLD: u32 = 7;
+
+    /// The key IDs of each party in the group most tests use, where parties [0,1,3] hold exactly `THRESHOLD` keys
+    fn party_key_ids() -> Vec<Vec<u32>> {
+        [
+            [0, 1, 2].to_vec(),
+            [3, 4].to_vec(),
+            [5, 6, 7].to_vec(),
+            [8, 9].to_vec(),
+        ]
+        .to_vec()
+    }
 
     #[test]
     fn party_save_load() {
@@ -497,33 +1709,773 @@ mod tests {
         let n: u32 = 10;
         let t: u32 = 7;
 
-        let signer = v2::Party::new(0, &key_ids, 1, n, t, &mut rng);
+        let mut signer = v2::Party::new(0, &key_ids, 1, n, t, &mut rng);
+        signer.preprocess_nonces(3, &mut rng);
+        signer.preprocessed.take(1);
 
         let state = signer.save();
         let loaded = v2::Party::load(&state);
 
         assert_eq!(signer, loaded);
+        assert!(!format!("{:?}", loaded).contains("secp256k1_scalar"));
+
+        // consumed preprocessed nonces stay consumed, and their indices are never reused
+        let indices: Vec<u32> = loaded
+            .preprocessed_nonces()
+            .iter()
+            .map(|(index, _)| *index)
+            .collect();
+        assert_eq!(indices, vec![0, 2]);
+        assert_eq!(loaded.preprocessed.next_index(), 3);
     }
 
     #[allow(non_snake_case)]
     #[test]
     fn aggregator_sign() {
+        let mut rng = OsRng::default();
+        let msg = "It was many and many a year ago".as_bytes();
+        let (signers, A) = v2::test_helpers::setup_dkg(&party_key_ids(), THRESHOLD, &mut rng);
+
+        // signers [0,1,3] who have T keys
+        {
+            let mut signers = [signers[0].clone(), signers[1].clone(), signers[3].clone()].to_vec();
+            let mut sig_agg = v2::SignatureAggregator::new(NUM_KEYS, THRESHOLD, &A)
+                .expect("aggregator ctor failed");
+
+            // the cached public keys match every signer's private keys
+            assert_eq!(
+                sig_agg.public_keys().len(),
+                usize::try_from(NUM_KEYS).unwrap()
+            );
+            for signer in &signers {
+                let state = signer.save();
+                for key_id in &state.key_ids {
+                    assert_eq!(
+                        sig_agg.public_keys()[key_id],
+                        *state.private_keys[key_id] * crate::G
+                    );
+                }
+            }
+
+            let (session_id, nonces, sig_shares, key_ids) =
+                v2::test_helpers::sign(&msg, &mut signers, &mut rng);
+            if let Err(e) = sig_agg.sign(session_id, &msg, &nonces, &sig_shares, &key_ids) {
+                panic!("Aggregator sign failed: {:?}", e);
+            }
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[test]
+    fn sign_consumes_nonce() {
+        let mut rng = OsRng::default();
+        let msg = "It was many and many a year ago".as_bytes();
+        let (signers, _) = v2::test_helpers::setup_dkg(&party_key_ids(), THRESHOLD, &mut rng);
+
+        let mut signers = [signers[0].clone(), signers[1].clone(), signers[3].clone()].to_vec();
+        let party_ids: Vec<u32> = signers.iter().map(|s| s.party_id).collect();
+        let key_ids: Vec<u32> = signers.iter().flat_map(|s| s.key_ids.clone()).collect();
+
+        // signing without a session fails
+        assert!(matches!(
+            signers[0].sign(0, msg, &party_ids, &key_ids, &[]),
+            Err(SignError::NoNonce(0))
+        ));
+
+        let session_id: SessionId = 1;
+        let nonces: Vec<PublicNonce> = signers
+            .iter_mut()
+            .map(|s| s.gen_session_nonce(session_id, &mut rng))
+            .collect();
+        assert!(signers[0]
+            .sign(session_id, msg, &party_ids, &key_ids, &nonces)
+            .is_ok());
+
+        // the nonce is consumed, so signing again fails even for another message
+        assert!(matches!(
+            signers[0].sign(
+                session_id,
+                b"another message",
+                &party_ids,
+                &key_ids,
+                &nonces
+            ),
+            Err(SignError::NoNonce(0))
+        ));
+        assert!(matches!(
+            signers[0].sign_taproot(session_id, msg, &party_ids, &key_ids, &nonces, None),
+            Err(SignError::NoNonce(0))
+        ));
+
+        // a zero nonce is refused, and consumed like any other
+        signers[1].nonces.insert(session_id, Nonce::zero());
+        assert!(matches!(
+            signers[1].sign(session_id, msg, &party_ids, &key_ids, &nonces),
+            Err(SignError::ZeroNonce(1))
+        ));
+        assert!(!signers[1].nonces.contains(session_id));
+    }
+
+    #[allow(non_snake_case)]
+    #[test]
+    fn preprocessed_signing() {
+        let mut rng = OsRng::default();
+        let msg = "It was many and many a year ago".as_bytes();
+        let (signers, A) = v2::test_helpers::setup_dkg(&party_key_ids(), THRESHOLD, &mut rng);
+        let mut sig_agg =
+            v2::SignatureAggregator::new(NUM_KEYS, THRESHOLD, &A).expect("aggregator ctor failed");
+        sig_agg.set_protocol_version(ProtocolVersion::V1);
+
+        let mut signers = [signers[0].clone(), signers[1].clone(), signers[3].clone()].to_vec();
+        let party_ids: Vec<u32> = signers.iter().map(|s| s.party_id).collect();
+        let key_ids: Vec<u32> = signers.iter().flat_map(|s| s.key_ids.clone()).collect();
+        for signer in signers.iter_mut() {
+            signer.set_protocol_version(ProtocolVersion::V1);
+            signer.preprocess_nonces(2, &mut rng);
+        }
+        let nonces_at = |index: u32, signers: &[v2::Party]| -> Vec<PublicNonce> {
+            signers
+                .iter()
+                .filter_map(|s| {
+                    s.preprocessed_nonces()
+                        .into_iter()
+                        .find(|(i, _)| *i == index)
+                        .map(|(_, nonce)| nonce)
+                })
+                .collect()
+        };
+
+        // the index only selects the nonces, and the session ID is what the shares are bound to
+        let nonces = nonces_at(1, &signers);
+        let session_id: SessionId = 10;
+        let sig_shares: Vec<SignatureShare> = signers
+            .iter_mut()
+            .map(|s| {
+                s.sign_preprocessed(1, session_id, msg, &party_ids, &key_ids, &nonces)
+                    .expect("signing failed")
+            })
+            .collect();
+        let sig = sig_agg
+            .sign(session_id, msg, &nonces, &sig_shares, &key_ids)
+            .expect("aggregator sign failed");
+        assert!(sig.verify_xonly(&sig_agg.poly[0], msg));
+        assert!(matches!(
+            sig_agg.sign(1, msg, &nonces, &sig_shares, &key_ids),
+            Err(AggregatorError::BadPartySigs(_))
+        ));
+
+        // state saved after signing refuses the consumed index once loaded
+        let mut signers: Vec<v2::Party> =
+            signers.iter().map(|s| v2::Party::load(&s.save())).collect();
+        assert!(matches!(
+            signers[0].sign_preprocessed(1, session_id, msg, &party_ids, &key_ids, &nonces),
+            Err(SignError::NoPreprocessedNonce(0, 1))
+        ));
+
+        let nonces = nonces_at(0, &signers);
+        let session_id: SessionId = 11;
+        let sig_shares: Vec<SignatureShare> = signers
+            .iter_mut()
+            .map(|s| {
+                s.sign_preprocessed(0, session_id, msg, &party_ids, &key_ids, &nonces)
+                    .expect("signing failed")
+            })
+            .collect();
+        let sig = sig_agg
+            .sign(session_id, msg, &nonces, &sig_shares, &key_ids)
+            .expect("aggregator sign failed");
+        assert!(sig.verify_xonly(&sig_agg.poly[0], msg));
+    }
+
+    #[allow(non_snake_case)]
+    #[test]
+    fn missing_private_key() {
+        let mut rng = OsRng::default();
+        let msg = "It was many and many a year ago".as_bytes();
+        let Nk: u32 = 4;
+        let T: u32 = 3;
+        let key_ids: Vec<u32> = [0, 1].to_vec();
+
+        // the party has not completed DKG, so it holds no private keys
+        let mut party = v2::Party::new(0, &key_ids, 2, Nk, T, &mut rng);
+        let session_id: SessionId = 1;
+        let nonce = party.gen_session_nonce(session_id, &mut rng);
+        assert!(matches!(
+            party.sign(
+                session_id,
+                msg,
+                &[0],
+                &key_ids,
+                std::slice::from_ref(&nonce)
+            ),
+            Err(SignError::NoPrivateKey(0))
+        ));
+        // the nonce is only consumed by a party which can sign with it
+        assert!(party.nonces.contains(session_id));
+
+        party.preprocess_nonces(1, &mut rng);
+        assert!(matches!(
+            party.sign_preprocessed(0, session_id, msg, &[0], &key_ids, &[nonce]),
+            Err(SignError::NoPrivateKey(0))
+        ));
+        assert!(party.preprocessed.take(0).is_some());
+
+        assert!(matches!(
+            party.get_reshares(&party.dkg_context(), &key_ids, &mut rng),
+            Err(DkgError::NoPrivateKey(0))
+        ));
+
+        // refreshing cannot turn the missing keys into shares of zero
+        assert!(matches!(
+            party.refresh_secret(&HashMap::new(), &HashMap::new()),
+            Err(DkgError::NoPrivateKey(0))
+        ));
+        assert!(party.private_keys.is_empty());
+    }
+
+    #[allow(non_snake_case)]
+    #[test]
+    fn concurrent_sessions() {
+        let mut rng = OsRng::default();
+        let msgs = [
+            "It was many and many a year ago".as_bytes(),
+            "In a kingdom by the sea".as_bytes(),
+        ];
+        let (signers, A) = v2::test_helpers::setup_dkg(&party_key_ids(), THRESHOLD, &mut rng);
+        let mut sig_agg =
+            v2::SignatureAggregator::new(NUM_KEYS, THRESHOLD, &A).expect("aggregator ctor failed");
+
+        let mut signers = [signers[0].clone(), signers[1].clone(), signers[3].clone()].to_vec();
+        let party_ids: Vec<u32> = signers.iter().map(|s| s.party_id).collect();
+        let key_ids: Vec<u32> = signers.iter().flat_map(|s| s.key_ids.clone()).collect();
+
+        // open both sessions before signing in either
+        let sessions: Vec<(SessionId, Vec<PublicNonce>)> = (1..=msgs.len())
+            .map(|session_id| {
+                let session_id = session_id.try_into().unwrap();
+                let nonces = signers
+                    .iter_mut()
+                    .map(|s| s.gen_session_nonce(session_id, &mut rng))
+                    .collect();
+                (session_id, nonces)
+            })
+            .collect();
+
+        // sign in the opposite order to the one the sessions were opened in
+        for (msg, (session_id, nonces)) in msgs.iter().zip(&sessions).rev() {
+            let sig_shares: Vec<SignatureShare> = signers
+                .iter_mut()
+                .map(|s| {
+                    s.sign(*session_id, msg, &party_ids, &key_ids, nonces)
+                        .expect("signing failed")
+                })
+                .collect();
+            let sig = sig_agg
+                .sign(*session_id, msg, nonces, &sig_shares, &key_ids)
+                .expect("aggregator sign failed");
+            assert!(sig.verify_xonly(&sig_agg.poly[0], msg));
+        }
+        assert!(signers.iter().all(|s| s.nonces.is_empty()));
+
+        // the oldest session is evicted when the party runs out of room, and sessions expire
+        let mut party = signers[0].clone();
+        party.nonces = NonceSessions::new(2, Duration::from_secs(60));
+        let (first, second, third): (SessionId, SessionId, SessionId) = (3, 4, 5);
+        for session_id in [first, second, third] {
+            party.gen_session_nonce(session_id, &mut rng);
+        }
+        assert!(!party.nonces.contains(first));
+        assert!(party.nonces.contains(second) && party.nonces.contains(third));
+
+        let expired = Instant::now() + Duration::from_secs(60);
+        assert!(!party.nonces.contains_at(third, expired));
+        assert!(party.nonces.take_at(second, expired).is_none());
+        assert!(party.nonces.is_empty());
+        assert!(matches!(
+            party.sign(third, msgs[0], &party_ids, &key_ids, &sessions[0].1),
+            Err(SignError::NoNonce(0))
+        ));
+    }
+
+    #[allow(non_snake_case)]
+    #[test]
+    fn versioned_binding() {
+        let mut rng = OsRng::default();
+        let msg = "It was many and many a year ago".as_bytes();
+        let (signers, A) = v2::test_helpers::setup_dkg(&party_key_ids(), THRESHOLD, &mut rng);
+        let mut sig_agg =
+            v2::SignatureAggregator::new(NUM_KEYS, THRESHOLD, &A).expect("aggregator ctor failed");
+        sig_agg.set_protocol_version(ProtocolVersion::V1);
+
+        let mut signers = [signers[0].clone(), signers[1].clone(), signers[3].clone()].to_vec();
+        for signer in &mut signers {
+            signer.set_protocol_version(ProtocolVersion::V1);
+        }
+        let party_ids: Vec<u32> = signers.iter().map(|s| s.party_id).collect();
+        let key_ids: Vec<u32> = signers.iter().flat_map(|s| s.key_ids.clone()).collect();
+
+        // every signer shares the session ID, which is bound into its binding factor
+        let session_id: SessionId = 7;
+        let nonces: Vec<PublicNonce> = signers
+            .iter_mut()
+            .map(|s| s.gen_session_nonce(session_id, &mut rng))
+            .collect();
+        let sig_shares: Vec<SignatureShare> = signers
+            .iter_mut()
+            .map(|s| {
+                s.sign(session_id, msg, &party_ids, &key_ids, &nonces)
+                    .expect("signing failed")
+            })
+            .collect();
+
+        let sig = sig_agg
+            .sign(session_id, msg, &nonces, &sig_shares, &key_ids)
+            .expect("aggregator sign failed");
+        assert!(sig.verify_xonly(&sig_agg.poly[0], msg));
+
+        // the shares do not verify for another session, or under the legacy binding factor
+        assert!(matches!(
+            sig_agg.sign(session_id + 1, msg, &nonces, &sig_shares, &key_ids),
+            Err(AggregatorError::BadPartySigs(_))
+        ));
+        sig_agg.set_protocol_version(ProtocolVersion::V0);
+        assert!(matches!(
+            sig_agg.sign(session_id, msg, &nonces, &sig_shares, &key_ids),
+            Err(AggregatorError::BadPartySigs(_))
+        ));
+    }
+
+    #[allow(non_snake_case)]
+    #[test]
+    fn dkg_context() {
+        let mut rng = OsRng::default();
+        let mut signers = v2::test_helpers::new_parties(&party_key_ids(), THRESHOLD, &mut rng);
+        for party in signers.iter_mut() {
+            party.set_protocol_version(ProtocolVersion::V1);
+            party.set_dkg_id(5);
+        }
+        let A = v2::test_helpers::dkg(&mut signers, &mut rng).expect("DKG failed");
+
+        let ctx = DkgContext::new(ProtocolVersion::V1, 5, NUM_KEYS, THRESHOLD);
+        let key_ids: Vec<u32> = (0..NUM_KEYS).collect();
+        assert_eq!(signers[0].dkg_context(), ctx);
+        assert_eq!(v2::Party::load(&signers[0].save()).dkg_context(), ctx);
+        let sig_agg = v2::SignatureAggregator::new_with_context(&ctx, &key_ids, &A)
+            .expect("aggregator ctor failed");
+        assert_eq!(sig_agg.protocol_version(), ProtocolVersion::V1);
+
+        // the proofs of possession cannot be replayed in another round, another group, or without a context
+        for other in [
+            DkgContext::new(ProtocolVersion::V1, 6, NUM_KEYS, THRESHOLD),
+            DkgContext::new(ProtocolVersion::V1, 5, NUM_KEYS + 1, THRESHOLD),
+            DkgContext::new(ProtocolVersion::V1, 5, NUM_KEYS, THRESHOLD - 1),
+        ] {
+            assert!(A.values().all(|A_i| !A_i.verify(&other)));
+        }
+        assert!(matches!(
+            v2::SignatureAggregator::new(NUM_KEYS, THRESHOLD, &A),
+            Err(AggregatorError::BadPolyCommitments(_))
+        ));
+
+        // legacy proofs still verify without a context
+        let legacy: HashMap<u32, PolyCommitment> =
+            v2::test_helpers::new_parties(&party_key_ids(), THRESHOLD, &mut rng)
+                .iter()
+                .map(|party| (party.party_id, party.get_poly_commitment(&mut rng)))
+                .collect();
+        assert!(v2::SignatureAggregator::new(NUM_KEYS, THRESHOLD, &legacy).is_ok());
+        assert!(matches!(
+            v2::SignatureAggregator::new_with_context(&ctx, &key_ids, &legacy),
+            Err(AggregatorError::BadPolyCommitments(_))
+        ));
+    }
+
+    #[allow(non_snake_case)]
+    #[test]
+    fn sign_after_restart_with_store() {
+        let mut rng = OsRng::default();
+        let msg = "It was many and many a year ago".as_bytes();
+        let (signers, A) = v2::test_helpers::setup_dkg(&party_key_ids(), THRESHOLD, &mut rng);
+        let mut sig_agg =
+            v2::SignatureAggregator::new(NUM_KEYS, THRESHOLD, &A).expect("aggregator ctor failed");
+
+        let signers = [signers[0].clone(), signers[1].clone(), signers[3].clone()].to_vec();
+        let party_ids: Vec<u32> = signers.iter().map(|s| s.party_id).collect();
+        let key_ids: Vec<u32> = signers.iter().flat_map(|s| s.key_ids.clone()).collect();
+
+        let dir = tempfile::tempdir().expect("failed to make temp dir");
+        let path = dir.path().join("nonces");
+        let session_id: SessionId = 1;
+        let nonces: Vec<PublicNonce> = {
+            let mut store = FileNonceStore::open(&path).expect("failed to open store");
+            signers
+                .iter()
+                .map(|s| {
+                    s.gen_session_nonce_with_store(session_id, &mut store, &mut rng)
+                        .expect("failed to issue nonce")
+                })
+                .collect()
+        };
+
+        // every party restarts after publishing its nonce, and still completes the round
+        let signers: Vec<v2::Party> = signers.iter().map(|s| v2::Party::load(&s.save())).collect();
+        let mut store = FileNonceStore::open(&path).expect("failed to reopen store");
+        let sig_shares: Vec<SignatureShare> = signers
+            .iter()
+            .map(|s| {
+                s.sign_with_store(&mut store, session_id, msg, &party_ids, &key_ids, &nonces)
+                    .expect("signing failed")
+            })
+            .collect();
+        let sig = sig_agg
+            .sign(session_id, msg, &nonces, &sig_shares, &key_ids)
+            .expect("aggregator sign failed");
+        assert!(sig.verify_xonly(&sig_agg.poly[0], msg));
+
+        // after another restart, the consumed nonce is refused rather than reused
+        let mut store = FileNonceStore::open(&path).expect("failed to reopen store");
+        assert!(matches!(
+            signers[0].sign_with_store(
+                &mut store,
+                session_id,
+                b"another message",
+                &party_ids,
+                &key_ids,
+                &nonces
+            ),
+            Err(SignError::Store(NonceStoreError::Consumed(0, _)))
+        ));
+    }
+
+    #[allow(non_snake_case)]
+    #[test]
+    fn compute_secret_bad_shares() {
+        let mut rng = OsRng::default();
+        let mut signers = v2::test_helpers::new_parties(&party_key_ids(), THRESHOLD, &mut rng);
+
+        let polys: HashMap<u32, PolyCommitment> = signers
+            .iter()
+            .map(|s| (s.party_id, s.get_poly_commitment(&mut rng)))
+            .collect();
+        let broadcast_shares: Vec<(u32, HashMap<u32, Scalar>)> = signers
+            .iter()
+            .map(|s| (s.party_id, s.get_shares()))
+            .collect();
+
+        let mut party_shares = HashMap::new();
+        for key_id in &signers[0].key_ids {
+            let mut key_shares = HashMap::new();
+            for (id, shares) in &broadcast_shares {
+                key_shares.insert(*id, shares[key_id]);
+            }
+            party_shares.insert(*key_id, key_shares);
+        }
+
+        // a clean set of shares passes the batched check
+        let mut party = signers[0].clone();
+        assert!(party.compute_secret(&party_shares, &polys).is_ok());
+
+        // corrupting one key's share from two parties identifies both of them
+        let key_shares = party_shares.get_mut(&1).unwrap();
+        key_shares.insert(1, Scalar::random(&mut rng));
+        key_shares.insert(3, Scalar::random(&mut rng));
+        match signers[0].compute_secret(&party_shares, &polys) {
+            Err(DkgError::BadShares(bad_shares)) => assert_eq!(bad_shares, vec![1, 3]),
+            result => panic!("expected BadShares, got {:?}", result),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[test]
+    fn bad_poly_commitment_len() {
+        let mut rng = OsRng::default();
+        let signers = v2::test_helpers::new_parties(&party_key_ids(), THRESHOLD, &mut rng);
+        let ctx = signers[0].dkg_context();
+        let key_ids: Vec<u32> = (0..NUM_KEYS).collect();
+
+        let polys: HashMap<u32, PolyCommitment> = signers
+            .iter()
+            .map(|s| (s.party_id, s.get_poly_commitment(&mut rng)))
+            .collect();
+        let mut party_shares = HashMap::new();
+        for key_id in &signers[0].key_ids {
+            let key_shares: HashMap<u32, Scalar> = signers
+                .iter()
+                .map(|s| (s.party_id, s.get_shares()[key_id]))
+                .collect();
+            party_shares.insert(*key_id, key_shares);
+        }
+
+        // the schnorr ID still verifies, but commitments with too few, too many, or no coefficients are rejected rather than indexed
+        for len in [0, THRESHOLD - 1, THRESHOLD + 1] {
+            let mut bad_polys = polys.clone();
+            bad_polys
+                .get_mut(&1)
+                .unwrap()
+                .A
+                .resize(len.try_into().unwrap(), G);
+            assert!(!bad_polys[&1].verify(&ctx));
+
+            let mut party = signers[0].clone();
+            assert!(matches!(
+                party.compute_secret(&party_shares, &bad_polys),
+                Err(DkgError::BadIds(ids)) if ids == vec![1]
+            ));
+            assert!(matches!(
+                v2::SignatureAggregator::new_with_context(&ctx, &key_ids, &bad_polys),
+                Err(AggregatorError::BadPolyCommitments(_))
+            ));
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[test]
+    fn qualified_dkg() {
+        let mut rng = OsRng::default();
+        let msg = "It was many and many a year ago".as_bytes();
+        let mut signers = v2::test_helpers::new_parties(&party_key_ids(), THRESHOLD, &mut rng);
+
+        // party 3 is offline while the others deal, so only parties [0,1,2] are qualified
+        let qualified: v2::SelectedSigners = (0..3u32)
+            .map(|pid| {
+                let key_ids: HashSet<u32> = signers[pid as usize].key_ids.iter().cloned().collect();
+                (pid, key_ids)
+            })
+            .collect();
+        let A: HashMap<u32, PolyCommitment> = (0..3u32)
+            .map(|pid| (pid, signers[pid as usize].get_poly_commitment(&mut rng)))
+            .collect();
+        let broadcast_shares: HashMap<u32, HashMap<u32, Scalar>> = (0..3u32)
+            .map(|pid| (pid, signers[pid as usize].get_shares()))
+            .collect();
+
+        // every party, including the absent one once it is back, computes its keys from the qualified dealers
+        for party in signers.iter_mut() {
+            let mut party_shares = HashMap::new();
+            for key_id in &party.key_ids {
+                let key_shares: HashMap<u32, Scalar> = broadcast_shares
+                    .iter()
+                    .map(|(pid, shares)| (*pid, shares[key_id]))
+                    .collect();
+                party_shares.insert(*key_id, key_shares);
+            }
+            party
+                .compute_secret_qualified(&qualified, &party_shares, &A)
+                .expect("qualified DKG failed");
+        }
+
+        let ctx = DkgContext::new(ProtocolVersion::V0, 0, NUM_KEYS, THRESHOLD);
+        let group_key_ids: Vec<u32> = (0..NUM_KEYS).collect();
+        let mut sig_agg =
+            v2::SignatureAggregator::new_qualified(&ctx, &group_key_ids, &qualified, &A)
+                .expect("aggregator ctor failed");
+        let group_key = A
+            .values()
+            .fold(crate::Point::zero(), |acc, A_i| acc + A_i.A[0]);
+        assert_eq!(sig_agg.poly[0], group_key);
+        assert!(signers.iter().all(|s| s.group_key == group_key));
+
+        // only the qualified parties deal refresh shares, which every party accepts
+        for party in signers[..3].iter_mut() {
+            party.reset_refresh_poly(&mut rng);
+        }
+        let A_refresh: HashMap<u32, PolyCommitment> = (0..3u32)
+            .map(|pid| (pid, signers[pid as usize].get_poly_commitment(&mut rng)))
+            .collect();
+        let broadcast_shares: HashMap<u32, HashMap<u32, Scalar>> = (0..3u32)
+            .map(|pid| (pid, signers[pid as usize].get_shares()))
+            .collect();
+        let old_signers = signers.clone();
+        for party in signers.iter_mut() {
+            let mut party_shares = HashMap::new();
+            for key_id in &party.key_ids {
+                let key_shares: HashMap<u32, Scalar> = broadcast_shares
+                    .iter()
+                    .map(|(pid, shares)| (*pid, shares[key_id]))
+                    .collect();
+                party_shares.insert(*key_id, key_shares);
+            }
+            party
+                .refresh_secret(&party_shares, &A_refresh)
+                .expect("qualified refresh failed");
+        }
+        sig_agg
+            .refresh(&A_refresh)
+            .expect("aggregator refresh failed");
+        assert_eq!(sig_agg.poly[0], group_key);
+        for (old_signer, signer) in old_signers.iter().zip(&signers) {
+            for key_id in &signer.key_ids {
+                assert_ne!(
+                    signer.save().private_keys[key_id],
+                    old_signer.save().private_keys[key_id]
+                );
+            }
+        }
+
+        // signers [1,2,3] include the party which did not deal
+        let mut signers = [signers[1].clone(), signers[2].clone(), signers[3].clone()].to_vec();
+        let (session_id, nonces, sig_shares, key_ids) =
+            v2::test_helpers::sign(&msg, &mut signers, &mut rng);
+        if let Err(e) = sig_agg.sign(session_id, &msg, &nonces, &sig_shares, &key_ids) {
+            panic!("Aggregator sign failed: {:?}", e);
+        }
+
+        // the qualified set must hold at least T keys
+        let mut too_few = qualified.clone();
+        too_few.remove(&2);
+        assert!(matches!(
+            v2::SignatureAggregator::new_qualified(&ctx, &group_key_ids, &too_few, &A),
+            Err(AggregatorError::NotEnoughDealers(5, 7))
+        ));
+    }
+
+    #[allow(non_snake_case)]
+    #[test]
+    fn refresh_shares() {
+        let mut rng = OsRng::default();
+        let msg = "It was many and many a year ago".as_bytes();
+        let (mut signers, A) = v2::test_helpers::setup_dkg(&party_key_ids(), THRESHOLD, &mut rng);
+        let mut sig_agg =
+            v2::SignatureAggregator::new(NUM_KEYS, THRESHOLD, &A).expect("aggregator ctor failed");
+        let group_key = sig_agg.poly[0];
+        let old_signers = signers.clone();
+
+        let A = v2::test_helpers::refresh(&mut signers, &mut rng).expect("refresh failed");
+        sig_agg.refresh(&A).expect("aggregator refresh failed");
+        assert_eq!(sig_agg.poly[0], group_key);
+
+        for (old_signer, signer) in old_signers.iter().zip(&signers) {
+            let old_state = old_signer.save();
+            let state = signer.save();
+            assert_eq!(state.group_key, group_key);
+            for key_id in &state.key_ids {
+                assert_ne!(state.private_keys[key_id], old_state.private_keys[key_id]);
+            }
+        }
+
+        // the refreshed shares sign for the same group key
+        {
+            let mut signers = [signers[0].clone(), signers[1].clone(), signers[3].clone()].to_vec();
+            let (session_id, nonces, sig_shares, key_ids) =
+                v2::test_helpers::sign(msg, &mut signers, &mut rng);
+            let sig = sig_agg
+                .sign(session_id, msg, &nonces, &sig_shares, &key_ids)
+                .expect("aggregator sign failed");
+            assert!(sig.verify_xonly(&group_key, msg));
+        }
+
+        // old shares can no longer be combined with refreshed ones
+        {
+            let mut signers = [
+                old_signers[0].clone(),
+                signers[1].clone(),
+                signers[3].clone(),
+            ]
+            .to_vec();
+            let (session_id, nonces, sig_shares, key_ids) =
+                v2::test_helpers::sign(msg, &mut signers, &mut rng);
+            assert!(sig_agg
+                .sign(session_id, msg, &nonces, &sig_shares, &key_ids)
+                .is_err());
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[test]
+    fn reshare_new_committee() {
+        let mut rng = OsRng::default();
+        let msg = "It was many and many a year ago".as_bytes();
+        let (signers, A) = v2::test_helpers::setup_dkg(&party_key_ids(), THRESHOLD, &mut rng);
+        let sig_agg =
+            v2::SignatureAggregator::new(NUM_KEYS, THRESHOLD, &A).expect("aggregator ctor failed");
+        let group_key = sig_agg.poly[0];
+
+        // parties [0,1,2] hold more than T keys, so they can reshare to the new committee
+        let new_Nk: u32 = 6;
+        let new_key_ids: Vec<u32> = (0..new_Nk).collect();
+        let new_T: u32 = 4;
+        let ctx = DkgContext::new(ProtocolVersion::V0, 1, new_Nk, new_T);
+        let mut A = HashMap::new();
+        let mut shares = HashMap::new();
+        for signer in &signers[..3] {
+            let (comms, signer_shares) = signer
+                .get_reshares(&ctx, &new_key_ids, &mut rng)
+                .expect("failed to deal reshares");
+            A.extend(comms);
+            shares.extend(signer_shares);
+        }
+
+        let new_party_key_ids: Vec<Vec<u32>> =
+            [[0, 1].to_vec(), [2, 3].to_vec(), [4, 5].to_vec()].to_vec();
+        let new_Np = new_party_key_ids.len().try_into().unwrap();
+        let new_signers: Vec<v2::Party> = new_party_key_ids
+            .iter()
+            .enumerate()
+            .map(|(pid, pkids)| {
+                let party = v2::Party::reshare(
+                    pid.try_into().unwrap(),
+                    pkids,
+                    &new_key_ids,
+                    new_Np,
+                    &ctx,
+                    &shares,
+                    &A,
+                    &sig_agg.poly,
+                    &mut rng,
+                )
+                .expect("reshare failed");
+                v2::Party::load(&party.save())
+            })
+            .collect();
+        // the loaded parties keep the resharing round's context
+        assert!(new_signers.iter().all(|party| party.dkg_context() == ctx));
+
+        let mut new_sig_agg =
+            v2::SignatureAggregator::reshare(&ctx, &new_key_ids, &A, &sig_agg.poly)
+                .expect("aggregator reshare failed");
+        assert_eq!(new_sig_agg.poly[0], group_key);
+        assert_eq!(new_sig_agg.poly.len(), usize::try_from(new_T).unwrap());
+
+        let mut new_signers = [new_signers[0].clone(), new_signers[2].clone()].to_vec();
+        let (session_id, nonces, sig_shares, key_ids) =
+            v2::test_helpers::sign(msg, &mut new_signers, &mut rng);
+        let sig = new_sig_agg
+            .sign(session_id, msg, &nonces, &sig_shares, &key_ids)
+            .expect("aggregator sign failed");
+        assert!(sig.verify_xonly(&group_key, msg));
+
+        // too few old keys cannot reshare
+        let (A, _) = signers[0]
+            .get_reshares(&ctx, &new_key_ids, &mut rng)
+            .expect("failed to deal reshares");
+        assert!(v2::SignatureAggregator::reshare(&ctx, &new_key_ids, &A, &sig_agg.poly).is_err());
+    }
+
+    #[allow(non_snake_case)]
+    #[test]
+    fn sparse_ids() {
         let mut rng = OsRng::default();
         let msg = "It was many and many a year ago".as_bytes();
         let Nk: u32 = 10;
         let T: u32 = 7;
+        let party_ids: Vec<u32> = [3, 7, 11, u32::MAX].to_vec();
         let party_key_ids: Vec<Vec<u32>> = [
-            [0, 1, 2].to_vec(),
-            [3, 4].to_vec(),
-            [5, 6, 7].to_vec(),
-            [8, 9].to_vec(),
+            [4, 9, 12].to_vec(),
+            [15, 22].to_vec(),
+            [30, 31, 40].to_vec(),
+            [50, u32::MAX].to_vec(),
         ]
         .to_vec();
-        let Np = party_key_ids.len().try_into().unwrap();
-        let mut signers: Vec<v2::Party> = party_key_ids
+        let group_key_ids: Vec<u32> = party_key_ids.iter().flatten().cloned().collect();
+        let Np = party_ids.len().try_into().unwrap();
+        let mut signers: Vec<v2::Party> = party_ids
             .iter()
-            .enumerate()
-            .map(|(pid, pkids)| v2::Party::new(pid.try_into().unwrap(), pkids, Np, Nk, T, &mut rng))
+            .zip(&party_key_ids)
+            .map(|(pid, pkids)| {
+                let mut party = v2::Party::new(*pid, pkids, Np, Nk, T, &mut rng);
+                party.set_group_key_ids(&group_key_ids);
+                party
+            })
             .collect();
 
         let A = match v2::test_helpers::dkg(&mut signers, &mut rng) {
@@ -532,18 +2484,101 @@ mod tests {
                 panic!("Got secret errors from DKG: {:?}", secret_errors);
             }
         };
+        let mut sorted_ids: Vec<u32> = A.keys().cloned().collect();
+        sorted_ids.sort();
+        assert_eq!(sorted_ids, party_ids);
+
+        let mut sig_agg = v2::SignatureAggregator::new_with_context(
+            &signers[0].dkg_context(),
+            &group_key_ids,
+            &A,
+        )
+        .expect("aggregator ctor failed");
+        for signer in &signers {
+            let state = signer.save();
+            for key_id in &state.key_ids {
+                assert_eq!(
+                    sig_agg.public_keys()[key_id],
+                    *state.private_keys[key_id] * crate::G
+                );
+            }
+        }
 
-        // signers [0,1,3] who have T keys
-        {
-            let mut signers = [signers[0].clone(), signers[1].clone(), signers[3].clone()].to_vec();
-            let mut sig_agg =
-                v2::SignatureAggregator::new(Nk, T, A.clone()).expect("aggregator ctor failed");
+        // the largest key ID is not evaluated at zero, where it would be dealt the group secret
+        assert_ne!(sig_agg.public_keys()[&u32::MAX], sig_agg.poly[0]);
 
-            let (nonces, sig_shares, key_ids) =
-                v2::test_helpers::sign(&msg, &mut signers, &mut rng);
-            if let Err(e) = sig_agg.sign(&msg, &nonces, &sig_shares, &key_ids) {
-                panic!("Aggregator sign failed: {:?}", e);
+        // signers [0,1,3] who have T keys, including the largest party and key IDs
+        let mut signers = [signers[0].clone(), signers[1].clone(), signers[3].clone()].to_vec();
+        let (session_id, nonces, sig_shares, key_ids) =
+            v2::test_helpers::sign(msg, &mut signers, &mut rng);
+        if let Err(e) = sig_agg.sign(session_id, msg, &nonces, &sig_shares, &key_ids) {
+            panic!("Aggregator sign failed: {:?}", e);
+        }
+
+        // a commitment filed under the wrong party ID is rejected rather than trusted
+        let mut misfiled = A.clone();
+        let comm = misfiled.remove(&3).unwrap();
+        misfiled.insert(4, comm);
+        assert!(matches!(
+            v2::SignatureAggregator::new(Nk, T, &misfiled),
+            Err(AggregatorError::BadPolyCommitments(_))
+        ));
+    }
+
+    #[allow(non_snake_case)]
+    #[test]
+    fn parallel_matches_sequential() {
+        // the `parallel` feature only changes how the work is scheduled, so CI runs this with and without it
+        let mut rng = OsRng::default();
+        let msg = "It was many and many a year ago".as_bytes();
+        let (signers, A) = v2::test_helpers::setup_dkg(&party_key_ids(), THRESHOLD, &mut rng);
+
+        for signer in &signers {
+            let shares = signer.get_shares();
+            for key_id in 0..NUM_KEYS {
+                assert_eq!(shares[&key_id], signer.f.eval(compute::id(key_id)));
+            }
+            for key_id in &signer.key_ids {
+                let mut private_key = Scalar::zero();
+                for dealer in &signers {
+                    private_key += dealer.f.eval(compute::id(*key_id));
+                }
+                assert_eq!(*signer.private_keys[key_id], private_key);
             }
         }
+
+        let mut sig_agg =
+            v2::SignatureAggregator::new(NUM_KEYS, THRESHOLD, &A).expect("aggregator ctor failed");
+        for key_id in 0..NUM_KEYS {
+            let public_key = compute::poly(&compute::id(key_id), &sig_agg.poly)
+                .expect("failed to evaluate the group poly");
+            assert_eq!(sig_agg.public_keys()[&key_id], public_key);
+        }
+
+        let mut signers = [signers[0].clone(), signers[1].clone(), signers[3].clone()].to_vec();
+        let (session_id, nonces, sig_shares, key_ids) =
+            v2::test_helpers::sign(msg, &mut signers, &mut rng);
+        let sig = sig_agg
+            .sign(session_id, msg, &nonces, &sig_shares, &key_ids)
+            .expect("aggregator sign failed");
+
+        let party_ids: Vec<u32> = sig_shares.iter().map(|ss| ss.id).collect();
+        let ctx = compute::BindingContext {
+            version: sig_agg.protocol_version(),
+            group_key: &sig_agg.poly[0],
+            session_id,
+            party_ids: &party_ids,
+            key_ids: &key_ids,
+        };
+        let (_, mut R) = compute::versioned_intermediate(&ctx, msg, &nonces);
+        if !R.has_even_y() {
+            R = -R;
+        }
+        let mut z = Scalar::zero();
+        for sig_share in &sig_shares {
+            z += sig_share.z_i;
+        }
+        assert_eq!(sig.R, R);
+        assert_eq!(sig.z, z);
     }
 }
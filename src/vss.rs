@@ -1,14 +1,30 @@
+use num_traits::Zero;
 use p256k1::scalar::Scalar;
-use polynomial::Polynomial;
 use rand_core::{CryptoRng, RngCore};
 
+use crate::common::SecretPoly;
+
 /// A verifiable secret share algorithm
 pub struct VSS {}
 
 impl VSS {
     /// Construct a random polynomial of the passed degree `n`
-    pub fn random_poly<RNG: RngCore + CryptoRng>(n: u32, rng: &mut RNG) -> Polynomial<Scalar> {
+    pub fn random_poly<RNG: RngCore + CryptoRng>(n: u32, rng: &mut RNG) -> SecretPoly {
         let params: Vec<Scalar> = (0..n + 1).map(|_| Scalar::random(rng)).collect();
-        Polynomial::new(params)
+        SecretPoly::new(params)
+    }
+
+    /// Construct a random polynomial of the passed degree `n` with a zero constant term
+    pub fn random_zero_poly<RNG: RngCore + CryptoRng>(n: u32, rng: &mut RNG) -> SecretPoly {
+        let params: Vec<Scalar> = (0..n + 1)
+            .map(|i| {
+                if i == 0 {
+                    Scalar::zero()
+                } else {
+                    Scalar::random(rng)
+                }
+            })
+            .collect();
+        SecretPoly::new(params)
     }
 }